@@ -0,0 +1,250 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use enhanced_music_core::cancel::CancelToken;
+use enhanced_music_core::config::Config;
+use enhanced_music_core::error::{Error, Result};
+use enhanced_music_core::llm::OnDelta;
+use enhanced_music_core::player::{self, Track};
+use enhanced_music_core::{album, artwork, classical, episode, lyrics, music, net};
+use serde::Serialize;
+
+const USAGE: &str = "\
+Usage: emusic <command> [--json]
+
+Commands:
+  now                     The track playing, whichever player it is in
+  analyze [--album]       Analysis of the track playing: its lyrics, the work
+                          for classical music, the episode for a podcast;
+                          with --album, the deep dive on its album
+  artwork [--save [PATH]] Colours of the artwork; with --save, the image
+                          itself (default: \"<artist> - <album>\" here)
+  history [--limit N]     Tracks played lately, newest first (default 10)
+
+Options:
+  --json                  Print JSON instead of text
+
+Reads ~/.config/enhanced-music/config.toml, like the app.";
+
+enum Command {
+    Now,
+    Analyze { album: bool },
+    Artwork { save: Option<Option<PathBuf>> },
+    History { limit: Option<usize> },
+}
+
+struct Args {
+    command: Command,
+    json: bool,
+}
+
+/// `Err` carries what to print above the usage.
+fn parse_args(args: &[String]) -> std::result::Result<Args, String> {
+    let json = args.iter().any(|a| a == "--json");
+    let mut rest = args.iter().filter(|a| *a != "--json").map(String::as_str);
+
+    let command = match rest.next() {
+        Some("now") => Command::Now,
+        Some("analyze") => Command::Analyze { album: rest.next() == Some("--album") },
+        Some("artwork") => Command::Artwork {
+            save: match rest.next() {
+                Some("--save") => Some(rest.next().map(PathBuf::from)),
+                Some(other) => return Err(format!("unexpected argument `{other}`")),
+                None => None,
+            },
+        },
+        Some("history") => Command::History {
+            limit: match (rest.next(), rest.next()) {
+                (Some("--limit"), Some(n)) => Some(n.parse().map_err(|_| format!("invalid limit `{n}`"))?),
+                (None, _) => None,
+                (Some(other), _) => return Err(format!("unexpected argument `{other}`")),
+            },
+        },
+        Some(other) => return Err(format!("unknown command `{other}`")),
+        None => return Err(String::new()),
+    };
+    if let Some(extra) = rest.next() {
+        return Err(format!("unexpected argument `{extra}`"));
+    }
+    Ok(Args { command, json })
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+}
+
+async fn playing(config: &Config) -> Result<Track> {
+    player::current_track(config, None)
+        .await?
+        .ok_or_else(|| Error::NotFound("Aucune lecture en cours".into()))
+}
+
+async fn now(config: &Config, json: bool) -> Result<()> {
+    let track = player::current_track(config, None).await?;
+    if json {
+        print_json(&track);
+        return Ok(());
+    }
+    match track {
+        Some(t) => {
+            let state = if t.is_playing { "▶" } else { "⏸" };
+            let from  = t.station.as_deref().unwrap_or(&t.album);
+            if from.is_empty() {
+                println!("{state} {} — {}", t.artist, t.title);
+            } else {
+                println!("{state} {} — {} ({from})", t.artist, t.title);
+            }
+        }
+        None => println!("Nothing playing"),
+    }
+    Ok(())
+}
+
+async fn analyze(config: &Config, whole_album: bool, json: bool) -> Result<()> {
+    let track  = playing(config).await?;
+    let cancel = CancelToken::default();
+
+    if whole_album {
+        let dive = album::deep_dive(config, &cancel, &track.album, &track.artist, track.is_compilation, false).await?;
+        if json {
+            print_json(&dive);
+        } else {
+            println!("{}\n\n{}\n\n{}", dive.overview, dive.themes, dive.sequencing);
+            for t in &dive.standout_tracks {
+                println!("\n• {} — {}", t.title, t.reason);
+            }
+        }
+        return Ok(());
+    }
+
+    if track.is_podcast {
+        let info = episode::info(config, &cancel, &track.album, &track.title).await?;
+        if json {
+            print_json(&info);
+        } else {
+            println!("{}", info.summary);
+            for point in &info.key_points {
+                println!("• {point}");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(work) = &track.classical {
+        let movement = work.movement.as_deref();
+        let info     = classical::info(config, &cancel, &work.composer, &work.work, movement, &track.artist, &track.album).await?;
+        if json {
+            print_json(&info);
+        } else {
+            let parts = [info.composed.as_str(), &info.work, info.movement.as_deref().unwrap_or_default(), &info.performance];
+            println!("{}", parts.into_iter().filter(|p| !p.is_empty()).collect::<Vec<_>>().join("\n\n"));
+        }
+        return Ok(());
+    }
+
+    // Text streams in as the model writes it; JSON waits for the whole answer
+    let mut shown   = 0;
+    let mut on_text = |text: &str| {
+        if let Some(new) = text.get(shown..) {
+            print!("{new}");
+            let _ = std::io::stdout().flush();
+            shown = text.len();
+        }
+    };
+    let on_text: Option<&mut OnDelta<'_>> = if json { None } else { Some(&mut on_text) };
+    let analysis = lyrics::analysis(config, &cancel, &track.title, &track.artist, &track.album, on_text).await?;
+    if json {
+        print_json(&analysis);
+    } else if let Some(rest) = analysis.interpretation.get(shown..) {
+        println!("{rest}");
+    }
+    Ok(())
+}
+
+async fn artwork(config: &Config, save: Option<Option<PathBuf>>, json: bool) -> Result<()> {
+    if !config.features.artwork {
+        return Err(Error::Disabled);
+    }
+    let track = playing(config).await?;
+    let bytes = artwork::fetch(config, &track.title, &track.artist, &track.album).await?;
+
+    let Some(path) = save else {
+        let palette = artwork::palette(&artwork::thumbnail(&bytes, artwork::PALETTE_SIZE).await?);
+        if json {
+            print_json(&palette);
+        } else {
+            println!("dominant {}\naccent   {}", palette.dominant, palette.accent);
+        }
+        return Ok(());
+    };
+
+    let extension = if bytes.starts_with(b"\x89PNG") { "png" } else { "jpg" };
+    let path      = path.unwrap_or_else(|| {
+        let name = format!("{} - {}", track.artist, track.album).replace('/', "-");
+        PathBuf::from(format!("{name}.{extension}"))
+    });
+    std::fs::write(&path, bytes.as_slice()).map_err(|e| Error::Io(e.to_string()))?;
+    if json {
+        print_json(&serde_json::json!({ "path": path.display().to_string() }));
+    } else {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+async fn history(limit: Option<usize>, json: bool) -> Result<()> {
+    let tracks = music::get_recently_played(limit).await?;
+    if json {
+        print_json(&tracks);
+        return Ok(());
+    }
+    for t in &tracks {
+        println!("{} — {} ({})", t.track.artist, t.track.title, t.track.album);
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let Args { command, json } = match parse_args(&args) {
+        Ok(args) => args,
+        Err(problem) => {
+            if !problem.is_empty() {
+                eprintln!("emusic: {problem}\n");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("emusic: {e} — using the defaults");
+        Config::fallback()
+    });
+    net::configure(&config.http);
+    net::set_offline(config.offline);
+
+    let result = match command {
+        Command::Now => now(&config, json).await,
+        Command::Analyze { album } => analyze(&config, album, json).await,
+        Command::Artwork { save } => artwork(&config, save, json).await,
+        Command::History { limit } => history(limit, json).await,
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                print_json(&e);
+            } else {
+                eprintln!("emusic: {e}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,658 @@
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::cache;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::discogs;
+use crate::error::{Error, Result};
+use crate::genius;
+use crate::inflight;
+use crate::insights::{self, Source};
+use crate::itunes;
+use crate::lastfm;
+use crate::llm::{self, language_name};
+use crate::lyrics;
+use crate::musicbrainz;
+use crate::musickit;
+use crate::net;
+use crate::vault;
+use crate::webhook;
+use crate::wikipedia;
+
+// ─── Album info ───────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct Popularity {
+    pub listeners: u64,
+    pub playcount: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ChartPeak {
+    pub chart: String,
+    pub position: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct Certification {
+    /// Certifying body, e.g. "RIAA", "SNEP".
+    pub body: String,
+    /// e.g. "3× Platine".
+    pub level: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct AlbumInfo {
+    pub release_year: String,
+    pub genre: String,
+    pub context: String,
+    pub notable_fact: String,
+    /// Where the original release came out (ISO code); MusicBrainz only.
+    pub country: String,
+    /// MusicBrainz, else guessed from the iTunes copyright line.
+    pub label: String,
+    pub producers: Vec<String>,
+    /// Running time in seconds, from MusicBrainz or iTunes.
+    pub length_secs: Option<u64>,
+    /// MusicBrainz IDs of the original release and its release group.
+    pub release_mbid: String,
+    pub release_group_mbid: String,
+    /// Last.fm user tags, most used first; empty without a Last.fm key.
+    pub tags: Vec<String>,
+    pub popularity: Option<Popularity>,
+    /// Only what the model is confident about; usually empty for lesser-known albums.
+    pub charts: Vec<ChartPeak>,
+    pub certifications: Vec<Certification>,
+    /// HLS stream of Apple Music's animated cover, for albums that have one.
+    pub animated_artwork: Option<String>,
+}
+
+const ALBUM_TAGS: usize = 5;
+
+/// With `compilation`, `artist` is the track's: text searches (iTunes,
+/// Genius) still use it, while sources keyed on the album's credit
+/// (MusicBrainz, Last.fm, Wikipedia) look up Various Artists.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn info(config: &Config, cancel: &CancelToken, album: &str, artist: &str, compilation: bool) -> Result<AlbumInfo> {
+    let work = fetch_info(config, cancel, album, artist, compilation);
+    inflight::coalesce("album_info", &info_key(album, artist, compilation), work).await
+}
+
+/// Fetches [`info`] ahead of the track that will want it. Not tied to the
+/// current track's scope, which ends when that track starts.
+pub async fn prefetch_info(config: &Config, album: &str, artist: &str, compilation: bool) {
+    let cancel = CancelToken::default();
+    let work   = fetch_info(config, &cancel, album, artist, compilation);
+    inflight::prefetch("album_info", &info_key(album, artist, compilation), work).await;
+}
+
+fn info_key(album: &str, artist: &str, compilation: bool) -> String {
+    format!("{artist}\u{1f}{album}\u{1f}{compilation}")
+}
+
+async fn fetch_info(config: &Config, cancel: &CancelToken, album: &str, artist: &str, compilation: bool) -> Result<AlbumInfo> {
+    if !config.features.album_info {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if !config.has_keys() {
+        warn!("API keys missing — skipping");
+        return Err(Error::MissingKeys);
+    }
+
+    // Independent lookups — run them side by side rather than back to back
+    let album_artist = if compilation { itunes::VARIOUS_ARTISTS } else { artist };
+    let language     = config.language();
+    let wiki         = config.features.wikipedia;
+    let lastfm_key   = &config.api.lastfm_key;
+    let mb           = config.features.musicbrainz;
+    let (itunes, catalog, release, description, (wiki_album, wiki_artist), mut stats) = tokio::join!(
+        itunes::album_metadata(artist, album, compilation),
+        musickit::album_if_enabled(config, artist, album, compilation),
+        async {
+            if !mb {
+                return None;
+            }
+            musicbrainz::release(album_artist, album)
+                .await
+                .map_err(|e| warn!(album, artist, "MusicBrainz lookup failed: {e}"))
+                .ok()
+        },
+        genius::album_description(&config.api.genius_token, artist, album),
+        async {
+            if !wiki {
+                return (String::new(), String::new());
+            }
+            // The track's artist says nothing about a compilation
+            tokio::join!(wikipedia::album_summary(&language, album_artist, album), async {
+                if compilation {
+                    return String::new();
+                }
+                wikipedia::artist_summary(&language, artist).await
+            })
+        },
+        async {
+            if lastfm_key.is_empty() {
+                return lastfm::AlbumStats::default();
+            }
+            lastfm::album_info(lastfm_key, album_artist, album, &language).await.unwrap_or_else(|e| {
+                warn!(album, artist, "Last.fm album lookup failed: {e}");
+                lastfm::AlbumStats::default()
+            })
+        },
+    );
+    stats.tags.truncate(ALBUM_TAGS);
+
+    // MusicBrainz knows the original release; iTunes often lists the reissue.
+    // The Apple Music catalog, when set up, beats the iTunes search on both
+    // year and label, the latter being only guessed from the copyright there.
+    let catalog_year  = catalog.as_ref().map(|c| c.year().to_string()).filter(|y| !y.is_empty());
+    let catalog_label = catalog.as_ref().map(|c| c.record_label.clone()).filter(|l| !l.is_empty());
+    let release_year  = release.as_ref().map(|r| r.year.clone()).filter(|y| !y.is_empty()).or(catalog_year).unwrap_or(itunes.year);
+    let label         = release.as_ref().map(|r| r.label.clone()).filter(|l| !l.is_empty()).or(catalog_label).unwrap_or(itunes.label);
+    let length_ms    = match (release.as_ref().and_then(|r| r.length_ms), itunes.collection_id) {
+        (Some(ms), _) => Some(ms),
+        (None, Some(id)) => itunes::album_length_ms(id).await.ok().filter(|&ms| ms > 0),
+        (None, None) => None,
+    };
+    let genre     = catalog.as_ref().and_then(|c| c.genres.first().cloned()).unwrap_or(itunes.genre);
+    let editorial = catalog.as_ref().map(|c| c.editorial_notes.as_str()).unwrap_or("");
+    let grounding = AlbumGrounding {
+        apple_music: editorial,
+        genius:      &description,
+        wiki_album:  &wiki_album,
+        wiki_artist: &wiki_artist,
+        lastfm:      &stats.wiki,
+        tags:        &stats.tags,
+        label:       &label,
+    };
+    let prompt    = build_album_prompt(album, album_artist, &release_year, &genre, &grounding, &language);
+
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+    let tool     = album_tool();
+    let animated = async {
+        let id = itunes.collection_id?;
+        itunes::animated_artwork(id)
+            .await
+            .inspect_err(|e| debug!(album, "animated artwork lookup failed: {e}"))
+            .ok()
+            .flatten()
+    };
+    let (extracted, animated_artwork) = tokio::join!(
+        llm::complete_json(config, &config.album_info, 600, &prompt, &tool, cancel, None),
+        animated,
+    );
+    let extracted = extracted?;
+
+    let charts = extracted["charts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| {
+            Some(ChartPeak {
+                chart:    c["chart"].as_str()?.to_string(),
+                position: c["position"].as_u64().filter(|&p| p > 0)? as u32,
+            })
+        })
+        .collect();
+    let certifications = extracted["certifications"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| {
+            Some(Certification {
+                body:  c["body"].as_str()?.to_string(),
+                level: c["level"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+
+    let info = AlbumInfo {
+        release_year,
+        genre,
+        context:            extracted["context"].as_str().unwrap_or("").to_string(),
+        notable_fact:       extracted["notable_fact"].as_str().unwrap_or("").to_string(),
+        country:            release.as_ref().map(|r| r.country.clone()).unwrap_or_default(),
+        label,
+        producers:          release.as_ref().map(|r| r.producers.clone()).unwrap_or_default(),
+        length_secs:        length_ms.map(|ms| (ms + 500) / 1000),
+        release_mbid:       release.as_ref().map(|r| r.release_id.clone()).unwrap_or_default(),
+        release_group_mbid: release.map(|r| r.release_group_id).unwrap_or_default(),
+        popularity:         stats.listeners.map(|listeners| Popularity { listeners, playcount: stats.playcount.unwrap_or(0) }),
+        tags:               stats.tags,
+        charts,
+        certifications,
+        animated_artwork,
+    };
+    insights::record(Source::AlbumInfo, "", artist, album, &format!("{}\n\n{}", info.context, info.notable_fact));
+    vault::album_completed(config, artist, album, &info);
+    webhook::analysis_completed(config, "album_info", serde_json::json!({ "artist": artist, "album": album }), &info);
+    Ok(info)
+}
+
+/// Texts the album prompt is grounded on; any of them may be empty.
+struct AlbumGrounding<'a> {
+    apple_music: &'a str,
+    genius: &'a str,
+    wiki_album: &'a str,
+    wiki_artist: &'a str,
+    lastfm: &'a str,
+    tags: &'a [String],
+    label: &'a str,
+}
+
+fn build_album_prompt(
+    album: &str,
+    artist: &str,
+    year: &str,
+    genre: &str,
+    grounding: &AlbumGrounding,
+    language: &str,
+) -> String {
+    let lang = language_name(language);
+    let mut meta = if year.is_empty() {
+        String::new()
+    } else {
+        format!(" (sorti en {year}, genre : {genre})")
+    };
+    if !grounding.label.is_empty() {
+        meta.push_str(&format!(" chez {}", grounding.label));
+    }
+
+    let mut sources = String::new();
+    if !grounding.apple_music.is_empty() {
+        sources.push_str(&format!("Notes éditoriales Apple Music :\n{}\n", grounding.apple_music));
+    }
+    if !grounding.genius.is_empty() {
+        sources.push_str(&format!("Description Genius :\n{}\n", grounding.genius));
+    }
+    if !grounding.wiki_album.is_empty() {
+        sources.push_str(&format!("Wikipédia (album) :\n{}\n", grounding.wiki_album));
+    }
+    if !grounding.wiki_artist.is_empty() {
+        sources.push_str(&format!("Wikipédia (artiste), pour situer l'album :\n{}\n", grounding.wiki_artist));
+    }
+    if !grounding.lastfm.is_empty() {
+        sources.push_str(&format!("Résumé Last.fm :\n{}\n", grounding.lastfm));
+    }
+    if !grounding.tags.is_empty() {
+        sources.push_str(&format!("Tags des auditeurs Last.fm : {}\n", grounding.tags.join(", ")));
+    }
+
+    let base = if sources.is_empty() {
+        format!("En te basant sur tes connaissances, pour l'album \"{album}\" de {artist}{meta}, réponds en {lang}.")
+    } else {
+        format!("Pour l'album \"{album}\" de {artist}{meta}, basé sur ces sources :\n{sources}Réponds en {lang}.")
+    };
+
+    format!(
+        "{base}\n\nN'indique des classements et des certifications que si tu en es certain ; \
+         dans le doute, laisse ces listes vides.\n\nRéponds en appelant l'outil `album_info`."
+    )
+}
+
+fn album_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "album_info",
+        description: "Enregistre le contexte et une anecdote sur l'album",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "context": {
+                    "type": "string",
+                    "description": "2-3 phrases sur le contexte et la genèse de l'album"
+                },
+                "notable_fact": {
+                    "type": "string",
+                    "description": "Un fait marquant ou anecdote sur cet album"
+                },
+                "charts": {
+                    "type": "array",
+                    "description": "Meilleures positions dans les grands classements nationaux, seulement si connues avec certitude",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "chart":    { "type": "string", "description": "ex. \"Billboard 200\", \"UK Albums Chart\"" },
+                            "position": { "type": "integer" }
+                        },
+                        "required": ["chart", "position"]
+                    }
+                },
+                "certifications": {
+                    "type": "array",
+                    "description": "Certifications de ventes, seulement si connues avec certitude",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "body":  { "type": "string", "description": "Organisme, ex. \"RIAA\", \"SNEP\"" },
+                            "level": { "type": "string", "description": "ex. \"Disque d'or\", \"3× Platine\"" }
+                        },
+                        "required": ["body", "level"]
+                    }
+                }
+            },
+            "required": ["context", "notable_fact"]
+        }),
+    }
+}
+
+// ─── Album deep dive ──────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct StandoutTrack {
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct AlbumDeepDive {
+    pub overview: String,
+    pub themes: String,
+    pub sequencing: String,
+    pub standout_tracks: Vec<StandoutTrack>,
+}
+
+/// Tracks whose Genius description is fetched; the rest only contribute
+/// their title.
+const DEEP_DIVE_DESCRIBED_TRACKS: usize = 12;
+
+/// Long-form essay on the whole album. Several Genius calls plus a large
+/// completion, so results are cached on disk per album and language;
+/// `refresh` regenerates.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn deep_dive(
+    config: &Config,
+    cancel: &CancelToken,
+    album: &str,
+    artist: &str,
+    compilation: bool,
+    refresh: bool,
+) -> Result<AlbumDeepDive> {
+    let key = format!("{artist}\u{1f}{album}\u{1f}{compilation}\u{1f}{refresh}");
+    inflight::coalesce("deep_dive", &key, fetch_deep_dive(config, cancel, album, artist, compilation, refresh)).await
+}
+
+async fn fetch_deep_dive(
+    config: &Config,
+    cancel: &CancelToken,
+    album: &str,
+    artist: &str,
+    compilation: bool,
+    refresh: bool,
+) -> Result<AlbumDeepDive> {
+    if !config.features.album_deep_dive {
+        return Err(Error::Disabled);
+    }
+
+    // One dive per compilation, whichever of its tracks is playing
+    let album_artist = if compilation { itunes::VARIOUS_ARTISTS } else { artist };
+    let language     = config.language();
+    let cache_key    = format!("{album_artist}|||{album}|||{language}");
+    if !refresh {
+        if let Some(cached) = cache::read::<AlbumDeepDive>("deep_dive", &cache_key) {
+            return Ok(cached);
+        }
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if !config.has_keys() {
+        return Err(Error::MissingKeys);
+    }
+
+    let collection = itunes::album(artist, album, compilation).await?;
+    let tracks = match collection["collectionId"].as_i64() {
+        Some(id) => itunes::tracklist(id).await?,
+        None => Vec::new(),
+    };
+
+    // The tracklist has titles only; a compilation's artists are unknown
+    let token     = &config.api.genius_token;
+    let described = if compilation { 0 } else { DEEP_DIVE_DESCRIBED_TRACKS };
+    let descriptions = futures::future::join_all(
+        tracks
+            .iter()
+            .take(described)
+            .map(|t| genius::song_description(token, t, artist, 300)),
+    )
+    .await;
+    let album_description = genius::album_description(token, artist, album).await;
+
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    let year   = collection["releaseDate"].as_str().and_then(|d| d.get(..4)).unwrap_or("");
+    let prompt = build_deep_dive_prompt(album, album_artist, year, &album_description, &tracks, &descriptions, &language);
+    let value  = llm::complete_json(config, &config.album_deep_dive, 1500, &prompt, &deep_dive_tool(), cancel, None).await?;
+
+    let dive: AlbumDeepDive = serde_json::from_value(value).map_err(|e| Error::Llm(e.to_string()))?;
+    cache::write("deep_dive", &cache_key, &dive);
+    Ok(dive)
+}
+
+fn build_deep_dive_prompt(
+    album: &str,
+    artist: &str,
+    year: &str,
+    album_description: &str,
+    tracks: &[String],
+    descriptions: &[String],
+    language: &str,
+) -> String {
+    let lang = language_name(language);
+    let year = if year.is_empty() { String::new() } else { format!(" ({year})") };
+
+    let tracklist = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| match descriptions.get(i).filter(|d| !d.is_empty()) {
+            Some(d) => format!("{}. {t} — {d}", i + 1),
+            None    => format!("{}. {t}", i + 1),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut context = String::new();
+    if !album_description.is_empty() {
+        context.push_str(&format!("\nDescription Genius de l'album :\n{album_description}\n"));
+    }
+    if !tracklist.is_empty() {
+        context.push_str(&format!("\nListe des morceaux (avec leur description Genius quand elle existe) :\n{tracklist}\n"));
+    }
+
+    format!(
+        "Tu es critique musical. Écris une analyse approfondie de l'album \"{album}\" de {artist}{year}, en {lang}.\n\
+         {context}\n\
+         Couvre les thèmes, l'enchaînement des morceaux (ce que raconte l'ordre, les ruptures, \
+         l'ouverture et la clôture) et les morceaux marquants. Appuie-toi sur les éléments fournis \
+         et sur tes connaissances, sans inventer de faits.\n\n\
+         Réponds en appelant l'outil `album_deep_dive`."
+    )
+}
+
+fn deep_dive_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "album_deep_dive",
+        description: "Enregistre l'analyse approfondie de l'album",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "overview": {
+                    "type": "string",
+                    "description": "Un paragraphe de présentation : place de l'album dans la carrière, accueil, son"
+                },
+                "themes": {
+                    "type": "string",
+                    "description": "Un paragraphe sur les thèmes qui traversent l'album"
+                },
+                "sequencing": {
+                    "type": "string",
+                    "description": "Un paragraphe sur l'ordre des morceaux et l'arc de l'album"
+                },
+                "standout_tracks": {
+                    "type": "array",
+                    "description": "3 à 5 morceaux marquants",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "title":  { "type": "string" },
+                            "reason": { "type": "string", "description": "Une ou deux phrases" }
+                        },
+                        "required": ["title", "reason"]
+                    }
+                }
+            },
+            "required": ["overview", "themes", "sequencing", "standout_tracks"]
+        }),
+    }
+}
+
+// ─── Album analysis ───────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct TrackAnalysis {
+    pub title: String,
+    /// `None` when the track couldn't be analysed; `error` says why.
+    pub interpretation: Option<String>,
+    pub moods: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct AlbumAnalysis {
+    pub album: String,
+    pub artist: String,
+    /// In album order.
+    pub tracks: Vec<TrackAnalysis>,
+    /// Analyses made by this run; the others came from the cache.
+    pub analyzed: usize,
+}
+
+/// Breather between two tracks that need the model, so a long album doesn't
+/// hit Genius, the lyrics sources and the LLM in one burst.
+const ALBUM_ANALYSIS_PAUSE: Duration = Duration::from_secs(2);
+
+/// Lyrics analysis of every track of the album, in order: cached ones come
+/// back at once, the others are made one at a time. `on_progress` hears of
+/// each track done, with the total. A track that fails is reported in its
+/// entry; what would fail every other track (offline, budget spent) stops
+/// the run.
+#[tracing::instrument(skip(config, cancel, on_progress))]
+pub async fn analyze(
+    config: &Config,
+    cancel: &CancelToken,
+    album: &str,
+    artist: &str,
+    compilation: bool,
+    mut on_progress: Option<&mut (dyn FnMut(usize, usize) + Send)>,
+) -> Result<AlbumAnalysis> {
+    if !config.features.lyrics {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if !config.has_keys() {
+        return Err(Error::MissingKeys);
+    }
+
+    let collection = itunes::album(artist, album, compilation).await?;
+    let titles = match collection["collectionId"].as_i64() {
+        Some(id) => itunes::tracklist(id).await?,
+        None => Vec::new(),
+    };
+    if titles.is_empty() {
+        return Err(Error::NotFound("Aucun morceau trouvé pour cet album".into()));
+    }
+
+    let mut report = AlbumAnalysis { album: album.to_string(), artist: artist.to_string(), tracks: Vec::new(), analyzed: 0 };
+    for title in &titles {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let cached = lyrics::cached_analysis(config, title, artist);
+        let result = match cached {
+            Some(cached) => Ok(cached),
+            None => {
+                if report.analyzed > 0 {
+                    tokio::time::sleep(ALBUM_ANALYSIS_PAUSE).await;
+                }
+                report.analyzed += 1;
+                lyrics::analysis(config, cancel, title, artist, album, None).await
+            }
+        };
+        let (interpretation, moods, error) = match result {
+            Ok(a) => (Some(a.interpretation), a.moods, None),
+            Err(e @ (Error::Offline | Error::BudgetExceeded | Error::Cancelled | Error::MissingKeys)) => return Err(e),
+            Err(e) => {
+                debug!(title = %title, "track not analysed: {e}");
+                (None, Vec::new(), Some(e.to_string()))
+            }
+        };
+        report.tracks.push(TrackAnalysis { title: title.clone(), interpretation, moods, error });
+        if let Some(on_progress) = on_progress.as_mut() {
+            on_progress(report.tracks.len(), titles.len());
+        }
+    }
+    Ok(report)
+}
+
+// ─── Release details ──────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct Pressing {
+    pub label: String,
+    pub catalog_number: String,
+    pub format: String,
+    pub country: String,
+    pub year: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ReleaseDetails {
+    pub label: String,
+    pub catalog_number: String,
+    pub formats: Vec<String>,
+    pub pressings: Vec<Pressing>,
+    pub url: String,
+    pub marketplace_url: String,
+}
+
+/// Physical-release context from Discogs; needs `discogs_token`.
+#[tracing::instrument(skip(config))]
+pub async fn release_details(config: &Config, album: &str, artist: &str, compilation: bool) -> Result<ReleaseDetails> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.discogs_token.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let artist  = if compilation { discogs::VARIOUS } else { artist };
+    let release = discogs::release(&config.api.discogs_token, artist, album).await?;
+    Ok(ReleaseDetails {
+        label:          release.label,
+        catalog_number: release.catalog_number,
+        formats:        release.formats,
+        pressings: release
+            .pressings
+            .into_iter()
+            .map(|p| Pressing {
+                label:          p.label,
+                catalog_number: p.catalog_number,
+                format:         p.format,
+                country:        p.country,
+                year:           p.year,
+            })
+            .collect(),
+        url:             release.url,
+        marketplace_url: release.marketplace_url,
+    })
+}
@@ -0,0 +1,326 @@
+use tracing::{debug, warn};
+
+use crate::bandsintown;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::genius;
+use crate::inflight;
+use crate::lastfm;
+use crate::llm::{self, language_name};
+use crate::net;
+use crate::setlistfm;
+use crate::webhook;
+
+// ─── Artist info ──────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize, Clone)]
+pub struct ArtistInfo {
+    pub name: String,
+    pub image_url: String,
+    pub formation: String,
+    pub era: String,
+    pub signature_style: String,
+}
+
+#[tracing::instrument(skip(config, cancel))]
+pub async fn info(config: &Config, cancel: &CancelToken, artist: &str) -> Result<ArtistInfo> {
+    inflight::coalesce("artist_info", artist, fetch_info(config, cancel, artist)).await
+}
+
+async fn fetch_info(config: &Config, cancel: &CancelToken, artist: &str) -> Result<ArtistInfo> {
+    if !config.features.artist_info {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if !config.has_keys() {
+        return Err(Error::MissingKeys);
+    }
+
+    let genius      = genius::artist(&config.api.genius_token, artist).await?;
+    let name        = genius["name"].as_str().unwrap_or(artist).to_string();
+    let description = genius["description"]["plain"]
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "?")
+        .map(|s| s.chars().take(2000).collect::<String>())
+        .unwrap_or_default();
+
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+    let prompt    = build_artist_prompt(&name, &description, &config.language());
+    let extracted = llm::complete_json(config, &config.artist_info, 400, &prompt, &artist_tool(), cancel, None).await?;
+
+    let info = ArtistInfo {
+        name,
+        image_url:       genius["image_url"].as_str().unwrap_or("").to_string(),
+        formation:       extracted["formation"].as_str().unwrap_or("").to_string(),
+        era:             extracted["era"].as_str().unwrap_or("").to_string(),
+        signature_style: extracted["signature_style"].as_str().unwrap_or("").to_string(),
+    };
+    webhook::analysis_completed(config, "artist_info", serde_json::json!({ "artist": artist }), &info);
+    Ok(info)
+}
+
+fn build_artist_prompt(artist: &str, description: &str, language: &str) -> String {
+    let lang = language_name(language);
+    let base = if description.is_empty() {
+        format!("En te basant sur tes connaissances, présente l'artiste {artist}. Réponds en {lang}.")
+    } else {
+        format!("Présente l'artiste {artist}, en t'appuyant sur cette biographie :\n{description}\nRéponds en {lang}.")
+    };
+
+    format!("{base}\n\nRéponds en appelant l'outil `artist_info`.")
+}
+
+fn artist_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "artist_info",
+        description: "Enregistre une présentation courte de l'artiste",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "formation": {
+                    "type": "string",
+                    "description": "1-2 phrases : origine, formation ou débuts, membres s'il s'agit d'un groupe"
+                },
+                "era": {
+                    "type": "string",
+                    "description": "Période d'activité et moments clés de la carrière, 1-2 phrases"
+                },
+                "signature_style": {
+                    "type": "string",
+                    "description": "Ce qui caractérise le son ou l'écriture de l'artiste, 1-2 phrases"
+                }
+            },
+            "required": ["formation", "era", "signature_style"]
+        }),
+    }
+}
+
+// ─── Similar artists ──────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct SimilarArtist {
+    pub name: String,
+    pub reason: String,
+}
+
+const SIMILAR_COUNT: usize = 5;
+
+/// Last.fm supplies the candidates when a key is configured and Claude writes
+/// the one-line reasons; without Last.fm, Claude picks the artists too.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn similar(config: &Config, cancel: &CancelToken, artist: &str) -> Result<Vec<SimilarArtist>> {
+    if !config.features.similar_artists {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+
+    let candidates = if config.api.lastfm_key.is_empty() {
+        Vec::new()
+    } else {
+        match lastfm::similar_artists(&config.api.lastfm_key, artist, SIMILAR_COUNT).await {
+            Ok(similar) => similar,
+            Err(e) => {
+                warn!("Last.fm similar artists failed, falling back to the model: {e}");
+                Vec::new()
+            }
+        }
+    };
+
+    // Last.fm alone still gives a usable list, just without reasons
+    if config.llm_key().is_empty() {
+        if candidates.is_empty() {
+            return Err(Error::MissingKeys);
+        }
+        return Ok(candidates
+            .into_iter()
+            .map(|c| SimilarArtist {
+                reason: format!("Similarité Last.fm : {:.0} %", c.score * 100.0),
+                name:   c.name,
+            })
+            .collect());
+    }
+
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+    let names     = candidates.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    let prompt    = build_similar_prompt(artist, &names, &config.language());
+    let extracted = llm::complete_json(config, &config.similar_artists, 400, &prompt, &similar_tool(), cancel, None).await?;
+
+    let artists = extracted["artists"].as_array().cloned().unwrap_or_default();
+    Ok(artists
+        .iter()
+        .filter_map(|a| {
+            Some(SimilarArtist {
+                name:   a["name"].as_str()?.to_string(),
+                reason: a["reason"].as_str().unwrap_or("").to_string(),
+            })
+        })
+        .take(SIMILAR_COUNT)
+        .collect())
+}
+
+fn build_similar_prompt(artist: &str, candidates: &[&str], language: &str) -> String {
+    let lang = language_name(language);
+    let base = if candidates.is_empty() {
+        format!("Propose {SIMILAR_COUNT} artistes à découvrir si l'on aime {artist}.")
+    } else {
+        format!(
+            "Voici des artistes proches de {artist} selon Last.fm : {}. \
+             Pour chacun, garde le nom tel quel.",
+            candidates.join(", ")
+        )
+    };
+
+    format!(
+        "{base} Pour chaque artiste, donne une raison en une phrase courte, en {lang}, \
+         qui le relie concrètement à {artist} (son, époque, influences).\n\n\
+         Réponds en appelant l'outil `similar_artists`."
+    )
+}
+
+fn similar_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "similar_artists",
+        description: "Enregistre des artistes similaires avec une raison chacun",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "artists": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name":   { "type": "string" },
+                            "reason": { "type": "string", "description": "Une phrase" }
+                        },
+                        "required": ["name", "reason"]
+                    }
+                }
+            },
+            "required": ["artists"]
+        }),
+    }
+}
+
+// ─── Concerts ─────────────────────────────────────────────────────────────────
+
+const MAX_SHOWS: usize = 10;
+
+#[derive(serde::Serialize)]
+pub struct Show {
+    pub datetime: String,
+    pub venue: String,
+    pub city: String,
+    pub country: String,
+    /// From `[concerts]` coordinates; `None` when no location is configured.
+    pub distance_km: Option<f64>,
+    pub url: String,
+}
+
+/// Great-circle distance between two points, in kilometres.
+fn distance_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    6371.0 * 2.0 * a.sqrt().asin()
+}
+
+/// Next shows of the artist, limited to `[concerts] radius_km` around the
+/// configured location when there is one.
+#[tracing::instrument(skip(config))]
+pub async fn upcoming_shows(config: &Config, artist: &str) -> Result<Vec<Show>> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.bandsintown_app_id.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let home   = config.concerts.latitude.zip(config.concerts.longitude);
+    let events = bandsintown::upcoming_events(&config.api.bandsintown_app_id, artist).await?;
+
+    Ok(events
+        .into_iter()
+        .filter_map(|e| {
+            let distance = home.zip(e.latitude.zip(e.longitude)).map(|(h, v)| distance_km(h, v));
+            if distance.is_some_and(|d| d > config.concerts.radius_km) {
+                return None;
+            }
+            Some(Show {
+                datetime:    e.datetime,
+                venue:       e.venue,
+                city:        e.city,
+                country:     e.country,
+                distance_km: distance.map(f64::round),
+                url:         e.url,
+            })
+        })
+        .take(MAX_SHOWS)
+        .collect())
+}
+
+// ─── Setlists ─────────────────────────────────────────────────────────────────
+
+const MAX_SETLISTS: usize = 10;
+
+#[derive(serde::Serialize)]
+pub struct Setlist {
+    pub date: String,
+    pub venue: String,
+    pub city: String,
+    pub country: String,
+    pub tour: String,
+    pub songs: Vec<String>,
+    pub url: String,
+    /// Whether the track now playing was in this show.
+    pub includes_current: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct RecentSetlists {
+    pub setlists: Vec<Setlist>,
+    /// How many of `setlists` include the track now playing.
+    pub current_song_plays: usize,
+}
+
+fn same_song(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+#[tracing::instrument(skip(config))]
+pub async fn recent_setlists(config: &Config, artist: &str, title: Option<&str>) -> Result<RecentSetlists> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.setlistfm_key.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let setlists: Vec<Setlist> = setlistfm::recent(&config.api.setlistfm_key, artist, MAX_SETLISTS)
+        .await?
+        .into_iter()
+        .map(|s| Setlist {
+            includes_current: title.is_some_and(|t| s.songs.iter().any(|song| same_song(song, t))),
+            date:             s.date,
+            venue:            s.venue,
+            city:             s.city,
+            country:          s.country,
+            tour:             s.tour,
+            songs:            s.songs,
+            url:              s.url,
+        })
+        .collect();
+
+    Ok(RecentSetlists {
+        current_song_plays: setlists.iter().filter(|s| s.includes_current).count(),
+        setlists,
+    })
+}
@@ -0,0 +1,437 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+use tracing::debug;
+
+use crate::config::{ArtworkQuality, Config};
+use crate::error::{Error, Result};
+use crate::musicbrainz;
+use crate::musickit;
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+/// Cover Art Archive, like MusicBrainz, wants to know who is asking.
+const USER_AGENT: &str = "enhanced-music/0.1 (https://github.com/Fendry02/enhanced-music)";
+
+/// Side of the thumbnail colours are sampled from.
+pub const PALETTE_SIZE: u32 = 64;
+
+/// Track keys and bytes of the last artworks fetched, newest first.
+type Recent = VecDeque<(String, Arc<Vec<u8>>)>;
+
+/// The current track's artwork, and the next one's once prefetched.
+const RECENT: usize = 2;
+
+/// Kept so the palette (and anything else derived from the image) doesn't
+/// download it a second time.
+fn recent() -> &'static Mutex<Recent> {
+    static RECENT_ARTWORK: OnceLock<Mutex<Recent>> = OnceLock::new();
+    RECENT_ARTWORK.get_or_init(Default::default)
+}
+
+/// A temp file of its own for each call: a fetch left running after a skip,
+/// the palette and the share card must not read or delete each other's.
+fn scratch_path(name: &str) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("enhanced-music-{}-{n}-{name}", std::process::id()))
+}
+
+/// The current track's own artwork when it has one, else the best cover the
+/// online sources have at the configured quality (not available offline).
+pub async fn fetch(config: &Config, title: &str, artist: &str, album: &str) -> Result<Arc<Vec<u8>>> {
+    load(config, None, title, artist, album).await
+}
+
+/// Fetches the artwork of the library track with persistent ID `id`, so
+/// that [`fetch`] has it when that track starts.
+pub async fn prefetch(config: &Config, id: &str, title: &str, artist: &str, album: &str) -> Result<()> {
+    load(config, Some(id), title, artist, album).await.map(drop)
+}
+
+/// `id` names a library track; `None` is the current one.
+async fn load(config: &Config, id: Option<&str>, title: &str, artist: &str, album: &str) -> Result<Arc<Vec<u8>>> {
+    let quality = config.artwork.quality;
+    let key     = format!("{title}|||{artist}|||{quality:?}");
+    let cached  = recent().lock().ok().and_then(|r| r.iter().find(|(k, _)| *k == key).map(|(_, b)| b.clone()));
+    if let Some(bytes) = cached {
+        return Ok(bytes);
+    }
+
+    let scratch = scratch_path(if id.is_some() { "artwork-next" } else { "artwork" });
+    let bytes = match embedded(&scratch, id).await {
+        Some(bytes) => bytes,
+        None => match (online(config, title, artist, album).await, normalize::primary_artist(artist)) {
+            (Ok(bytes), _) => bytes,
+            // Guests in the artist field throw the searches off; retry without
+            (Err(Error::NotFound(_)), Some(primary)) => online(config, title, primary, album).await?,
+            (Err(e), _) => return Err(e),
+        },
+    };
+    let bytes = Arc::new(bytes);
+    if let Ok(mut r) = recent().lock() {
+        r.push_front((key, bytes.clone()));
+        r.truncate(RECENT);
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    /// The Apple Music catalog, when `[api]` has MusicKit credentials.
+    AppleMusic,
+    Itunes,
+    CoverArtArchive,
+    FanartTv,
+}
+
+fn sources(quality: ArtworkQuality) -> &'static [Source] {
+    match quality {
+        ArtworkQuality::Standard => &[Source::Itunes],
+        ArtworkQuality::High     => &[Source::AppleMusic, Source::Itunes, Source::CoverArtArchive, Source::FanartTv],
+        ArtworkQuality::Original => &[Source::AppleMusic, Source::CoverArtArchive, Source::FanartTv, Source::Itunes],
+    }
+}
+
+/// Tries the sources in preference order; the last error is reported when
+/// none has the cover.
+async fn online(config: &Config, title: &str, artist: &str, album: &str) -> Result<Vec<u8>> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    let quality = config.artwork.quality;
+    // Both archives are keyed by MusicBrainz release group; look it up once
+    let mut group: Option<Option<String>> = None;
+    let mut last_err = Error::NotFound("Pochette introuvable".into());
+
+    for &source in sources(quality) {
+        let found = match source {
+            Source::Itunes => from_itunes(title, artist, quality).await,
+            Source::AppleMusic => {
+                if album.is_empty() || !musickit::enabled(&config.api) {
+                    continue;
+                }
+                from_apple_music(config, artist, album, quality).await
+            }
+            Source::CoverArtArchive | Source::FanartTv => {
+                if album.is_empty() {
+                    continue;
+                }
+                if matches!(source, Source::FanartTv) && config.api.fanart_key.is_empty() {
+                    continue;
+                }
+                if group.is_none() {
+                    group = Some(musicbrainz::release_group(artist, album).await.ok().filter(|g| !g.is_empty()));
+                }
+                let Some(Some(rg)) = &group else { continue };
+                match source {
+                    Source::CoverArtArchive => from_cover_art_archive(rg, quality).await,
+                    _ => from_fanart(&config.api.fanart_key, rg).await,
+                }
+            }
+        };
+        match found {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) => {
+                debug!(?source, "no artwork: {e}");
+                last_err = e;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+async fn download(service: &str, url: &str) -> Result<Vec<u8>> {
+    let bytes = net::send(service, || http().get(url).header("User-Agent", USER_AGENT))
+        .await
+        .map_err(|e| Error::http(service, e))?
+        .bytes()
+        .await
+        .map_err(|e| Error::http(service, net::HttpError::Transport(e)))?;
+    if bytes.is_empty() {
+        return Err(Error::NotFound("Pochette introuvable".into()));
+    }
+    Ok(bytes.to_vec())
+}
+
+/// The catalog's cover, 1200 px or as large as Apple has it — usually
+/// 3000 px, the master's size.
+async fn from_apple_music(config: &Config, artist: &str, album: &str, quality: ArtworkQuality) -> Result<Vec<u8>> {
+    let found = musickit::album(config, artist, album, false).await?;
+    if found.artwork_url.is_empty() {
+        return Err(Error::NotFound("Pochette introuvable".into()));
+    }
+    let size = if quality == ArtworkQuality::Original { None } else { Some(1200) };
+    download("musickit", &found.artwork(size)).await
+}
+
+/// Cover Art Archive's front image: the 1200 px thumbnail, or the uploaded
+/// original.
+async fn from_cover_art_archive(release_group: &str, quality: ArtworkQuality) -> Result<Vec<u8>> {
+    let suffix = if quality == ArtworkQuality::Original { "" } else { "-1200" };
+    download("coverartarchive", &format!("https://coverartarchive.org/release-group/{release_group}/front{suffix}")).await
+}
+
+/// Most liked album cover on fanart.tv.
+async fn from_fanart(key: &str, release_group: &str) -> Result<Vec<u8>> {
+    let url  = format!("https://webservice.fanart.tv/v3/music/albums/{release_group}?api_key={}", url_encode(key));
+    let json = net::get_json("fanart.tv", || http().get(&url)).await?;
+    let cover = json["albums"][release_group]["albumcover"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .max_by_key(|c| c["likes"].as_str().and_then(|l| l.parse::<u32>().ok()).unwrap_or(0))
+        .and_then(|c| c["url"].as_str())
+        .ok_or_else(|| Error::NotFound("Pochette introuvable".into()))?
+        .to_string();
+    download("fanart.tv", &cover).await
+}
+
+/// Artwork embedded in the current track — or the library track `id` —
+/// written by Music to `path`: the only way for local files and releases
+/// iTunes Search doesn't know.
+async fn embedded(path: &Path, id: Option<&str>) -> Option<Vec<u8>> {
+    let script = r#"
+        on run argv
+            tell application "Music"
+                if (item 2 of argv) is "" then
+                    if player state is stopped then return ""
+                    set t to current track
+                else
+                    set t to first track of library playlist 1 whose persistent ID is (item 2 of argv)
+                end if
+                if (count of artworks of t) is 0 then return ""
+                set d to raw data of artwork 1 of t
+            end tell
+            set f to open for access (POSIX file (item 1 of argv)) with write permission
+            try
+                set eof f to 0
+                write d to f
+            end try
+            close access f
+            return "ok"
+        end run
+    "#;
+
+    let output = tokio::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .arg(path)
+        .arg(id.unwrap_or_default())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        debug!("embedded artwork unavailable: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    if String::from_utf8_lossy(&output.stdout).trim() != "ok" {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok();
+    let _ = std::fs::remove_file(path);
+    bytes.filter(|b| !b.is_empty())
+}
+
+/// iTunes artwork URLs encode their size; `100000x100000-999` asks for the
+/// original upload.
+async fn from_itunes(title: &str, artist: &str, quality: ArtworkQuality) -> Result<Vec<u8>> {
+    let query = url_encode(&format!("{} {}", artist, normalize::title(title)));
+    let url = format!(
+        "https://itunes.apple.com/search?term={}&media=music&entity=song&limit=1",
+        query
+    );
+    let json = net::get_json("itunes", || http().get(&url)).await?;
+
+    let artwork_url = json["results"][0]["artworkUrl100"]
+        .as_str()
+        .ok_or_else(|| Error::NotFound("Pochette introuvable".into()))?
+        .to_string();
+    let size = match quality {
+        ArtworkQuality::Standard => "600x600bb",
+        ArtworkQuality::High     => "1200x1200bb",
+        ArtworkQuality::Original => "100000x100000-999",
+    };
+    download("itunes", &artwork_url.replace("100x100bb", size)).await
+}
+
+pub fn data_url(bytes: &[u8]) -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    let mime = if bytes.starts_with(b"\x89PNG") { "image/png" } else { "image/jpeg" };
+    format!("data:{mime};base64,{}", STANDARD.encode(bytes))
+}
+
+/// Downscales the artwork to fit `size`×`size`. `sips` does the decoding, so
+/// JPEG covers work with the PNG-only `image` build.
+pub async fn thumbnail(bytes: &[u8], size: u32) -> Result<image::RgbImage> {
+    let src = scratch_path("thumb-src");
+    let out = scratch_path("thumb.png");
+    std::fs::write(&src, bytes).map_err(|e| Error::Io(e.to_string()))?;
+
+    let status = tokio::process::Command::new("sips")
+        .args(["-s", "format", "png", "-Z", &size.to_string()])
+        .arg(&src)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .await;
+    let _ = std::fs::remove_file(&src);
+    let output = status.map_err(|e| Error::Io(e.to_string()))?;
+    if !output.status.success() {
+        return Err(Error::Io(format!("sips : {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+
+    let png = std::fs::read(&out).map_err(|e| Error::Io(e.to_string()));
+    let _ = std::fs::remove_file(&out);
+    let img = image::load_from_memory_with_format(&png?, image::ImageFormat::Png)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    Ok(img.to_rgb8())
+}
+
+#[derive(Serialize)]
+pub struct Palette {
+    /// `#rrggbb`, the most common colour.
+    pub dominant: String,
+    /// `#rrggbb`, the most vivid colour that stands apart from the dominant one.
+    pub accent: String,
+    /// Whether the dominant colour is dark, i.e. light text reads better on it.
+    pub dark: bool,
+    #[serde(skip)]
+    pub dominant_rgb: [u8; 3],
+    #[serde(skip)]
+    pub accent_rgb: [u8; 3],
+}
+
+pub fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn saturation([r, g, b]: [u8; 3]) -> f64 {
+    let max = r.max(g).max(b) as f64;
+    let min = r.min(g).min(b) as f64;
+    if max == 0.0 { 0.0 } else { (max - min) / max }
+}
+
+/// Relative luminance, 0–1.
+fn luminance([r, g, b]: [u8; 3]) -> f64 {
+    (0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64) / 255.0
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> f64 {
+    a.iter().zip(b).map(|(&x, y)| (x as f64 - y as f64).powi(2)).sum::<f64>().sqrt()
+}
+
+/// Buckets pixels on a 16-level grid per channel and averages each bucket;
+/// the biggest is the dominant colour, the accent is the most saturated of the
+/// sizeable ones far enough from it.
+pub fn palette(img: &image::RgbImage) -> Palette {
+    let mut buckets: HashMap<[u8; 3], (u64, [u64; 3])> = HashMap::new();
+    for px in img.pixels() {
+        let [r, g, b] = px.0;
+        let entry = buckets.entry([r >> 4, g >> 4, b >> 4]).or_default();
+        entry.0 += 1;
+        for (sum, c) in entry.1.iter_mut().zip([r, g, b]) {
+            *sum += c as u64;
+        }
+    }
+
+    let mut colours: Vec<(u64, [u8; 3])> = buckets
+        .into_values()
+        .map(|(n, sum)| (n, sum.map(|s| (s / n) as u8)))
+        .collect();
+    colours.sort_by_key(|c| std::cmp::Reverse(c.0));
+
+    let total    = colours.iter().map(|c| c.0).sum::<u64>().max(1);
+    let dominant = colours.first().map(|c| c.1).unwrap_or([128, 128, 128]);
+    let accent   = colours
+        .iter()
+        .filter(|(n, c)| *n * 100 >= total && distance(*c, dominant) > 60.0)
+        .max_by(|a, b| {
+            let score = |(n, c): &(u64, [u8; 3])| saturation(*c) * (*n as f64).sqrt();
+            score(a).total_cmp(&score(b))
+        })
+        .map(|c| c.1)
+        .unwrap_or(dominant);
+
+    Palette {
+        dominant:     hex(dominant),
+        accent:       hex(accent),
+        dark:         luminance(dominant) < 0.5,
+        dominant_rgb: dominant,
+        accent_rgb:   accent,
+    }
+}
+
+/// Side of the thumbnail the blurhash is computed from; more detail would be
+/// lost in the blur anyway.
+pub const BLURHASH_SIZE: u32 = 32;
+
+const BASE83: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn base83(value: u32, digits: u32, out: &mut String) {
+    for i in (0..digits).rev() {
+        out.push(BASE83[(value / 83u32.pow(i) % 83) as usize] as char);
+    }
+}
+
+pub fn srgb_to_linear(c: u8) -> f64 {
+    let v = c as f64 / 255.0;
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(v: f64) -> u32 {
+    let v = v.clamp(0.0, 1.0);
+    let s = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    (s * 255.0 + 0.5) as u32
+}
+
+fn sign_pow(v: f64, exp: f64) -> f64 {
+    v.abs().powf(exp).copysign(v)
+}
+
+/// [BlurHash](https://blurha.sh) of the image with 4×3 components, for an
+/// instant blurred placeholder in the UI.
+pub fn blurhash(img: &image::RgbImage) -> String {
+    const NX: usize = 4;
+    const NY: usize = 3;
+    let (w, h) = (img.width() as usize, img.height() as usize);
+
+    let mut factors = [[0.0f64; 3]; NX * NY];
+    for (x, y, px) in img.enumerate_pixels() {
+        let linear = px.0.map(srgb_to_linear);
+        for j in 0..NY {
+            for i in 0..NX {
+                let basis = (std::f64::consts::PI * i as f64 * x as f64 / w as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * y as f64 / h as f64).cos();
+                for (f, c) in factors[j * NX + i].iter_mut().zip(linear) {
+                    *f += basis * c;
+                }
+            }
+        }
+    }
+    for (k, f) in factors.iter_mut().enumerate() {
+        let norm = if k == 0 { 1.0 } else { 2.0 };
+        *f = f.map(|c| c * norm / (w * h).max(1) as f64);
+    }
+
+    let (dc, ac) = factors.split_first().expect("at least one component");
+    let mut hash = String::with_capacity(4 + 2 * ac.len() + 2);
+    base83(((NX - 1) + (NY - 1) * 9) as u32, 1, &mut hash);
+
+    let actual_max = ac.iter().flatten().fold(0.0f64, |m, c| m.max(c.abs()));
+    let quantised  = ((actual_max * 166.0 - 0.5).floor()).clamp(0.0, 82.0) as u32;
+    let max_value  = (quantised + 1) as f64 / 166.0;
+    base83(quantised, 1, &mut hash);
+
+    let [r, g, b] = dc.map(linear_to_srgb);
+    base83((r << 16) + (g << 8) + b, 4, &mut hash);
+    for f in ac {
+        let [qr, qg, qb] = f.map(|c| (sign_pow(c / max_value, 0.5) * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32);
+        base83(qr * 19 * 19 + qg * 19 + qb, 2, &mut hash);
+    }
+    hash
+}
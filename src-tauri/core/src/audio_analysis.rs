@@ -0,0 +1,328 @@
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{debug, info};
+
+use crate::cache;
+use crate::error::{Error, Result};
+use crate::music;
+use crate::spotify::key_name;
+
+// Files are decoded in process by symphonia (AAC, ALAC, MP3, FLAC…), mixed
+// down to mono and brought down to a low rate, plenty for beats and pitches
+// up to B6.
+
+const RATE: u32 = 11_025;
+
+/// The part of the track analysed; past that, tempo and key rarely change
+/// and the wait grows.
+const MAX_SECS: usize = 240;
+
+/// Energy frames for the onset curve: 23 ms apart at [`RATE`].
+const HOP: usize = 256;
+
+/// Tempi considered, in BPM; [`PREFERRED_BPM`] breaks the half/double
+/// ambiguity every beat tracker has.
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Chroma frames: 0.37 s at [`RATE`], fine enough to tell neighbouring
+/// semitones apart from C3 up.
+const CHROMA_FRAME: usize = 4096;
+
+/// MIDI notes the chroma is summed over: C3 to B6.
+const NOTES: std::ops::RangeInclusive<u8> = 48..=95;
+
+/// Krumhansl–Kessler key profiles, from the tonic up.
+const MAJOR: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAnalysis {
+    /// Beats per minute, to a tenth.
+    pub bpm: f64,
+    /// e.g. "C♯ mineur", as [`crate::spotify::AudioFeatures`] names keys.
+    pub key: String,
+    /// e.g. "12A".
+    pub camelot: String,
+    /// How well the notes heard fit the key, from 0 to 1: below 0.5 or
+    /// so, take the key as a guess.
+    pub key_confidence: f64,
+    pub analyzed_secs: u32,
+}
+
+fn unreadable(e: DecodeError) -> Error {
+    debug!("decoding failed: {e}");
+    Error::Invalid("format audio illisible".into())
+}
+
+/// The file's first [`MAX_SECS`], mono at [`RATE`]. Each output sample is
+/// the mean of the input ones it stands for, which filters out most of what
+/// the lower rate couldn't hold.
+fn decode(path: &Path) -> Result<Vec<f32>> {
+    let file = std::fs::File::open(path).map_err(|e| Error::NotFound(format!("Fichier introuvable : {e}")))?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut format = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(unreadable)?
+        .format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| Error::Invalid("aucune piste audio".into()))?;
+    let (track_id, source_rate) = (track.id, track.codec_params.sample_rate.unwrap_or(44_100));
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(unreadable)?;
+
+    let wanted = MAX_SECS * RATE as usize;
+    let mut samples = Vec::with_capacity(wanted);
+    let (mut sum, mut count, mut phase) = (0f32, 0u32, 0u32);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    while samples.len() < wanted {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // The end of the file, or a stream that changes midway: what
+            // came before is enough
+            Err(DecodeError::IoError(_) | DecodeError::ResetRequired) => break,
+            Err(e) => return Err(unreadable(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet costs a few milliseconds, not the analysis
+            Err(DecodeError::DecodeError(e)) => {
+                debug!("packet skipped: {e}");
+                continue;
+            }
+            Err(e) => return Err(unreadable(e)),
+        };
+        let channels = decoded.spec().channels.count().max(1);
+        let buffer   = buffer.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        if buffer.capacity() < decoded.capacity() * channels {
+            *buffer = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        }
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples().chunks_exact(channels) {
+            sum   += frame.iter().sum::<f32>() / channels as f32;
+            count += 1;
+            phase += RATE;
+            if phase >= source_rate {
+                phase -= source_rate;
+                samples.push(sum / count as f32);
+                (sum, count) = (0.0, 0);
+            }
+        }
+    }
+    samples.truncate(wanted);
+    Ok(samples)
+}
+
+/// Beats per minute, from the autocorrelation of the onset curve: how
+/// sharply the sound's energy rises, frame after frame.
+fn tempo(samples: &[f32]) -> Option<f64> {
+    let energy: Vec<f32> = samples.chunks(HOP).map(|c| (c.iter().map(|s| s * s).sum::<f32>() + 1e-9).ln()).collect();
+    let mut onset: Vec<f32> = energy.windows(2).map(|w| (w[1] - w[0]).max(0.0)).collect();
+    let mean = onset.iter().sum::<f32>() / onset.len().max(1) as f32;
+    onset.iter_mut().for_each(|o| *o -= mean);
+
+    let fps = f64::from(RATE) / HOP as f64;
+    let lag = |bpm: f64| (60.0 * fps / bpm).round() as usize;
+    let autocorrelation = |lag: usize| -> f64 {
+        onset.iter().zip(&onset[lag.min(onset.len())..]).map(|(a, b)| f64::from(a * b)).sum()
+    };
+    let (min_lag, max_lag) = (lag(MAX_BPM), lag(MIN_BPM));
+    if onset.len() <= max_lag * 4 {
+        return None;
+    }
+
+    let scores: Vec<f64> = (min_lag - 1..=max_lag + 1).map(autocorrelation).collect();
+    let weighted = |i: usize| {
+        let bpm = 60.0 * fps / (min_lag - 1 + i) as f64;
+        scores[i] * (-0.5 * (bpm / PREFERRED_BPM).log2().powi(2)).exp()
+    };
+    let best = (1..scores.len() - 1).max_by(|&a, &b| weighted(a).total_cmp(&weighted(b)))?;
+    if scores[best] <= 0.0 {
+        return None;
+    }
+    // The peak between two lags, for a tempo finer than whole frames
+    let (l, c, r) = (scores[best - 1], scores[best], scores[best + 1]);
+    let shift = if l - 2.0 * c + r < 0.0 { 0.5 * (l - r) / (l - 2.0 * c + r) } else { 0.0 };
+    let bpm = 60.0 * fps / ((min_lag - 1 + best) as f64 + shift);
+    Some((bpm * 10.0).round() / 10.0)
+}
+
+/// Energy of each pitch class over the track, from Goertzel filters on the
+/// notes of [`NOTES`].
+fn chroma(samples: &[f32]) -> [f32; 12] {
+    let coefficients: Vec<(usize, f32)> = NOTES
+        .map(|note| {
+            let freq = 440.0 * 2f32.powf((f32::from(note) - 69.0) / 12.0);
+            (usize::from(note % 12), 2.0 * (2.0 * PI * freq / RATE as f32).cos())
+        })
+        .collect();
+    let window: Vec<f32> = (0..CHROMA_FRAME).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / CHROMA_FRAME as f32).cos()).collect();
+
+    let mut total = [0f32; 12];
+    for frame in samples.chunks_exact(CHROMA_FRAME) {
+        let mut bins = [0f32; 12];
+        for &(pitch, coeff) in &coefficients {
+            let (mut s1, mut s2) = (0f32, 0f32);
+            for (x, w) in frame.iter().zip(&window) {
+                let s0 = x * w + coeff * s1 - s2;
+                s2 = s1;
+                s1 = s0;
+            }
+            bins[pitch] += (s1 * s1 + s2 * s2 - coeff * s1 * s2).max(0.0).sqrt();
+        }
+        // Each frame counts the same, loud or quiet
+        let sum: f32 = bins.iter().sum();
+        if sum > 0.0 {
+            total.iter_mut().zip(bins).for_each(|(t, b)| *t += b / sum);
+        }
+    }
+    total
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean = |v: &[f32; 12]| v.iter().sum::<f32>() / 12.0;
+    let (ma, mb) = (mean(a), mean(b));
+    let (mut num, mut da, mut db) = (0f32, 0f32, 0f32);
+    for (x, y) in a.iter().zip(b) {
+        num += (x - ma) * (y - mb);
+        da  += (x - ma) * (x - ma);
+        db  += (y - mb) * (y - mb);
+    }
+    if da == 0.0 || db == 0.0 { 0.0 } else { num / (da * db).sqrt() }
+}
+
+/// The key whose profile best matches the chroma: its tonic's pitch class,
+/// whether it is minor, and the correlation.
+fn key(chroma: &[f32; 12]) -> Option<(u8, bool, f32)> {
+    (0..12u8)
+        .flat_map(|tonic| [(tonic, false), (tonic, true)])
+        .map(|(tonic, minor)| {
+            let profile = if minor { &MINOR } else { &MAJOR };
+            let rotated: [f32; 12] = std::array::from_fn(|i| profile[(i + 12 - usize::from(tonic)) % 12]);
+            (tonic, minor, correlation(chroma, &rotated))
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .filter(|k| k.2 > 0.0)
+}
+
+fn analyze(samples: &[f32]) -> Result<AudioAnalysis> {
+    let samples = &samples[..samples.len().min(MAX_SECS * RATE as usize)];
+    let bpm = tempo(samples).ok_or_else(|| Error::NotFound("Tempo introuvable : morceau trop court ou sans rythme".into()))?;
+    let (key, camelot, key_confidence) = match key(&chroma(samples)) {
+        Some((tonic, minor, r)) => {
+            let (name, camelot) = key_name(tonic, minor);
+            (name, camelot, (f64::from(r) * 100.0).round() / 100.0)
+        }
+        None => (String::new(), String::new(), 0.0),
+    };
+    Ok(AudioAnalysis { bpm, key, camelot, key_confidence, analyzed_secs: (samples.len() / RATE as usize) as u32 })
+}
+
+/// Tempo and key of an audio file, worked out locally. Cached by path,
+/// size and modification date, so a retagged or replaced file is analysed
+/// again.
+#[tracing::instrument]
+pub async fn analyze_file(path: &str) -> Result<AudioAnalysis> {
+    let meta = std::fs::metadata(path).map_err(|e| Error::NotFound(format!("Fichier introuvable : {e}")))?;
+    let modified = meta.modified().ok().and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok()).map_or(0, |d| d.as_secs());
+    let key      = format!("{path}|||{}|||{modified}", meta.len());
+    if let Some(cached) = cache::read::<AudioAnalysis>("audio_analysis", &key) {
+        return Ok(cached);
+    }
+
+    let path     = PathBuf::from(path);
+    let analysis = tokio::task::spawn_blocking(move || analyze(&decode(&path)?))
+        .await
+        .map_err(|e| Error::Io(e.to_string()))??;
+    info!(bpm = analysis.bpm, key = %analysis.key, "audio analysed");
+    cache::write("audio_analysis", &key, &analysis);
+    Ok(analysis)
+}
+
+/// [`analyze_file`] on the current track, when it is a file on this Mac.
+pub async fn analyze_current() -> Result<AudioAnalysis> {
+    let info = music::get_track_file_info().await?;
+    let path = info.location().filter(|p| Path::new(p).is_file());
+    match path {
+        Some(path) => analyze_file(path).await,
+        None => Err(Error::NotFound("Pas de fichier local pour ce morceau".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_beat_and_key_of_a_synthetic_loop() {
+        // 20 s of an A minor triad with a click at 128 BPM
+        let beat = 60.0 / 128.0;
+        let samples: Vec<f32> = (0..20 * RATE as usize)
+            .map(|i| {
+                let t     = i as f32 / RATE as f32;
+                let chord = [220.0, 261.63, 329.63].iter().map(|f| (2.0 * PI * f * t).sin()).sum::<f32>() * 0.1;
+                let since = t % beat;
+                let click = if since < 0.02 { (2.0 * PI * 1000.0 * t).sin() * (1.0 - since / 0.02) * 0.8 } else { 0.0 };
+                chord + click
+            })
+            .collect();
+        let analysis = analyze(&samples).unwrap();
+        assert!((analysis.bpm - 128.0).abs() < 1.5, "bpm {}", analysis.bpm);
+        assert_eq!((analysis.key.as_str(), analysis.camelot.as_str()), ("A mineur", "8A"));
+    }
+
+    #[test]
+    fn decodes_a_stereo_file_down_to_mono_at_the_analysis_rate() {
+        // 2 s of 16-bit stereo at 48 kHz, a 440 Hz tone on the left only
+        let (rate, frames) = (48_000u32, 96_000u32);
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + frames * 4).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * 4).to_le_bytes());
+        wav.extend_from_slice(&4u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(frames * 4).to_le_bytes());
+        for i in 0..frames {
+            let left = ((2.0 * PI * 440.0 * i as f32 / rate as f32).sin() * 16_000.0) as i16;
+            wav.extend_from_slice(&left.to_le_bytes());
+            wav.extend_from_slice(&0i16.to_le_bytes());
+        }
+        let path = std::env::temp_dir().join(format!("enhanced-music-decode-{}.wav", std::process::id()));
+        std::fs::write(&path, wav).unwrap();
+        let samples = decode(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let samples = samples.unwrap();
+        assert!(samples.len().abs_diff(2 * RATE as usize) <= 1, "{} samples", samples.len());
+        // Half the left channel's amplitude once mixed down
+        let peak = samples.iter().fold(0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.24).abs() < 0.02, "peak {peak}");
+    }
+}
@@ -0,0 +1,48 @@
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::url_encode;
+
+pub struct Event {
+    /// Local date and time, `YYYY-MM-DDTHH:MM:SS`.
+    pub datetime: String,
+    pub venue: String,
+    pub city: String,
+    pub country: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Bandsintown event page, with ticket links.
+    pub url: String,
+}
+
+fn coordinate(v: &serde_json::Value) -> Option<f64> {
+    // Sent as strings, sometimes as numbers
+    v.as_f64().or_else(|| v.as_str()?.parse().ok())
+}
+
+pub async fn upcoming_events(app_id: &str, artist: &str) -> Result<Vec<Event>> {
+    // Slashes and question marks must be double-encoded in the artist segment
+    let name = url_encode(artist).replace("%2F", "%252F").replace("%3F", "%253F");
+    let url  = format!("https://rest.bandsintown.com/artists/{name}/events?app_id={app_id}&date=upcoming");
+    let json = net::get_json("bandsintown", || http().get(&url)).await?;
+
+    // Unknown artists come back as `{"error": "..."}` or an empty string body
+    let Some(events) = json.as_array() else {
+        return Err(Error::NotFound("Artiste introuvable sur Bandsintown".into()));
+    };
+
+    Ok(events
+        .iter()
+        .map(|e| {
+            let venue = &e["venue"];
+            Event {
+                datetime:  e["datetime"].as_str().unwrap_or("").to_string(),
+                venue:     venue["name"].as_str().unwrap_or("").to_string(),
+                city:      venue["city"].as_str().unwrap_or("").to_string(),
+                country:   venue["country"].as_str().unwrap_or("").to_string(),
+                latitude:  coordinate(&venue["latitude"]),
+                longitude: coordinate(&venue["longitude"]),
+                url:       e["url"].as_str().unwrap_or("").to_string(),
+            }
+        })
+        .collect())
+}
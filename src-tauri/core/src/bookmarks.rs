@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config;
+use crate::error::{Error, Result};
+use crate::normalize::fold;
+
+/// The analyses that can be starred (or exported, see [`crate::vault`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    AlbumInfo,
+    LyricsAnalysis,
+}
+
+/// A starred analysis, kept as it was shown: album info isn't cached, and a
+/// lyrics analysis is cached per language, so neither could be fetched back
+/// as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: u64,
+    pub kind: Kind,
+    /// The track's title; empty for album info.
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// The analysis, as its command returned it.
+    pub content: serde_json::Value,
+    /// RFC 3339, local time.
+    pub starred_at: String,
+}
+
+impl Bookmark {
+    fn is(&self, kind: Kind, title: &str, artist: &str, album: &str) -> bool {
+        self.kind == kind && fold(&self.title) == fold(title) && fold(&self.artist) == fold(artist) && fold(&self.album) == fold(album)
+    }
+}
+
+/// `~/Library/Application Support/enhanced-music/bookmarks.json`, oldest
+/// first.
+fn path() -> PathBuf {
+    config::data_dir().join("bookmarks.json")
+}
+
+fn bookmarks() -> &'static Mutex<Vec<Bookmark>> {
+    static BOOKMARKS: OnceLock<Mutex<Vec<Bookmark>>> = OnceLock::new();
+    BOOKMARKS.get_or_init(|| {
+        let bookmarks = std::fs::read_to_string(path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).map_err(|e| warn!("corrupt bookmarks file, starting fresh: {e}")).ok())
+            .unwrap_or_default();
+        Mutex::new(bookmarks)
+    })
+}
+
+fn save(bookmarks: &[Bookmark]) -> Result<()> {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    let json = serde_json::to_string_pretty(bookmarks).map_err(|e| Error::Io(e.to_string()))?;
+    std::fs::write(&path, json).map_err(|e| Error::Io(e.to_string()))
+}
+
+fn poisoned() -> Error {
+    Error::Io("favoris indisponibles".into())
+}
+
+/// Stars an analysis. Starring the same track or album again replaces the
+/// content kept, under the same id.
+pub fn star(kind: Kind, title: &str, artist: &str, album: &str, content: serde_json::Value) -> Result<Bookmark> {
+    if content.is_null() {
+        return Err(Error::Invalid("rien à mettre en favori".into()));
+    }
+    let mut bookmarks = bookmarks().lock().map_err(|_| poisoned())?;
+    let previous = bookmarks.iter().position(|b| b.is(kind, title, artist, album)).map(|i| bookmarks.remove(i));
+    let bookmark = Bookmark {
+        id:         previous.as_ref().map_or_else(|| bookmarks.iter().map(|b| b.id).max().unwrap_or(0) + 1, |b| b.id),
+        kind,
+        title:      title.to_string(),
+        artist:     artist.to_string(),
+        album:      album.to_string(),
+        content,
+        starred_at: Local::now().to_rfc3339(),
+    };
+    bookmarks.push(bookmark.clone());
+    if let Err(e) = save(&bookmarks) {
+        bookmarks.pop();
+        bookmarks.extend(previous);
+        return Err(e);
+    }
+    info!(id = bookmark.id, ?kind, "analysis starred");
+    Ok(bookmark)
+}
+
+/// Starred analyses, newest first, of one kind or all.
+pub fn list(kind: Option<Kind>) -> Vec<Bookmark> {
+    let Ok(bookmarks) = bookmarks().lock() else { return Vec::new() };
+    bookmarks.iter().rev().filter(|b| kind.is_none_or(|k| b.kind == k)).cloned().collect()
+}
+
+pub fn unstar(id: u64) -> Result<()> {
+    let mut bookmarks = bookmarks().lock().map_err(|_| poisoned())?;
+    let Some(index) = bookmarks.iter().position(|b| b.id == id) else {
+        return Err(Error::NotFound("Favori introuvable".into()));
+    };
+    let removed = bookmarks.remove(index);
+    if let Err(e) = save(&bookmarks) {
+        bookmarks.insert(index, removed);
+        return Err(e);
+    }
+    info!(id, "analysis unstarred");
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, warn};
+
+use crate::config;
+use crate::error::{Error, Result};
+
+/// `~/Library/Application Support/enhanced-music/cache/<namespace>/`.
+fn dir(namespace: &str) -> PathBuf {
+    config::data_dir().join("cache").join(namespace)
+}
+
+/// FNV-1a: stable across builds, unlike `DefaultHasher`, so cached files
+/// survive an app update.
+fn file_name(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3));
+    format!("{hash:016x}.json")
+}
+
+/// Keys are normalised (trimmed, lowercased) so "Abbey Road" and "abbey road"
+/// share an entry.
+fn path(namespace: &str, key: &str) -> PathBuf {
+    dir(namespace).join(file_name(&key.trim().to_lowercase()))
+}
+
+pub fn read<T: DeserializeOwned>(namespace: &str, key: &str) -> Option<T> {
+    let content = std::fs::read_to_string(path(namespace, key)).ok()?;
+    serde_json::from_str(&content)
+        .map_err(|e| warn!(namespace, "corrupt cache entry, ignoring: {e}"))
+        .ok()
+}
+
+pub fn write<T: Serialize>(namespace: &str, key: &str, value: &T) {
+    let path = path(namespace, key);
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                warn!(path = %path.display(), "cannot write cache entry: {e}");
+            }
+        }
+        Err(e) => warn!(namespace, "cannot serialize cache entry: {e}"),
+    }
+}
+
+// ─── Misses ───────────────────────────────────────────────────────────────────
+
+/// How long a lookup that found nothing is trusted: long enough that an
+/// unknown or local track isn't searched for again on every visit, short
+/// enough that a release indexed since turns up the same day.
+const MISS_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Lookups that came back `NotFound`, with when and the sentence to repeat.
+/// Kept in memory only: a restart is a fair reason to try again.
+fn misses() -> &'static Mutex<HashMap<String, (Instant, String)>> {
+    static MISSES: OnceLock<Mutex<HashMap<String, (Instant, String)>>> = OnceLock::new();
+    MISSES.get_or_init(Default::default)
+}
+
+/// Runs `lookup`, unless it found nothing for `key` within [`MISS_TTL`]. Only
+/// `NotFound` is remembered; a network or HTTP error is retried next time.
+pub async fn unless_missed<T>(namespace: &str, key: &str, lookup: impl Future<Output = Result<T>>) -> Result<T> {
+    let id = format!("{namespace}/{}", key.trim().to_lowercase());
+    if let Some((at, what)) = misses().lock().ok().and_then(|m| m.get(&id).cloned()) {
+        if at.elapsed() < MISS_TTL {
+            debug!(namespace, key, "known miss, not looked up");
+            return Err(Error::NotFound(what));
+        }
+    }
+
+    let result = lookup.await;
+    if let (Err(Error::NotFound(what)), Ok(mut misses)) = (&result, misses().lock()) {
+        misses.retain(|_, (at, _)| at.elapsed() < MISS_TTL);
+        misses.insert(id, (Instant::now(), what.clone()));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_not_found_is_remembered() {
+        let not_found = unless_missed("test", "gone", async { Err::<(), _>(Error::NotFound("Rien".into())) }).await;
+        assert!(matches!(not_found, Err(Error::NotFound(_))));
+        let again = unless_missed("test", "Gone ", async { Ok(()) }).await;
+        assert!(matches!(again, Err(Error::NotFound(what)) if what == "Rien"));
+
+        let _ = unless_missed("test", "down", async { Err::<(), _>(Error::Offline) }).await;
+        assert!(unless_missed("test", "down", async { Ok(()) }).await.is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cheap, clonable flag checked between enrichment stages and while reading
+/// streamed responses.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Ties enrichment work to the track that is playing. `get_current_track`
+/// reports what it sees; when the track changes, every token handed out for
+/// the previous one is cancelled so its requests stop spending tokens.
+#[derive(Default)]
+pub struct TrackScope(Mutex<(String, CancelToken)>);
+
+impl TrackScope {
+    /// Token for work started now, on behalf of the current track.
+    pub fn token(&self) -> CancelToken {
+        self.0.lock().map(|s| s.1.clone()).unwrap_or_default()
+    }
+
+    /// Records the track seen by the poller (empty when nothing plays);
+    /// `true` when it differs from the previous one.
+    pub fn observe(&self, key: &str) -> bool {
+        let Ok(mut scope) = self.0.lock() else { return false };
+        if scope.0 == key {
+            return false;
+        }
+        scope.1.cancel();
+        *scope = (key.to_string(), CancelToken::default());
+        true
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use tracing::debug;
+
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::inflight;
+use crate::llm::{self, language_name};
+use crate::net;
+use crate::wikipedia;
+
+/// Word beginnings that give a classical work's title away: catalogue
+/// numbers and forms.
+const WORK_MARKERS: [&str; 22] = [
+    "op.", "bwv", "k.", "kv", "hob.", "d.", "rv", "woo", "no.", "symphon", "concert", "sonat", "quartet",
+    "quintet", "suite", "prelude", "prélude", "fugue", "requiem", "nocturne", "étude", "variation",
+];
+
+/// Tempo and form markings a movement title starts with.
+const MOVEMENT_WORDS: [&str; 20] = [
+    "allegro", "allegretto", "adagio", "adagietto", "andante", "andantino", "largo", "larghetto", "lento",
+    "presto", "prestissimo", "moderato", "vivace", "grave", "scherzo", "menuetto", "minuet", "rondo", "finale",
+    "aria",
+];
+
+/// Composer, work and movement of a classical track: Music's `artist` is the
+/// performer and its `name` usually "Work: I. Movement".
+#[derive(Debug, Clone, Serialize)]
+pub struct Work {
+    pub composer: String,
+    pub work: String,
+    pub movement: Option<String>,
+}
+
+fn has_marker(title: &str) -> bool {
+    let title = title.to_lowercase();
+    title.split_whitespace().any(|word| WORK_MARKERS.iter().any(|m| word.starts_with(m)))
+}
+
+/// "I. Allegro", "IV - Finale", "Adagio sostenuto"…
+fn is_movement(s: &str) -> bool {
+    let s     = s.trim();
+    let first = s.split([' ', '.', ':']).next().unwrap_or("");
+    let roman = !first.is_empty() && first.chars().all(|c| "IVXL".contains(c)) && s.len() > first.len();
+    let lower = s.to_lowercase();
+    roman || MOVEMENT_WORDS.iter().any(|w| lower.starts_with(w))
+}
+
+/// Splits "Work: Movement" (or "Work - Movement") when the tail reads like a
+/// movement; otherwise the whole title is the work.
+fn split_movement(title: &str) -> (String, Option<String>) {
+    for sep in [": ", " - ", " – "] {
+        if let Some((work, movement)) = title.rsplit_once(sep) {
+            if is_movement(movement) {
+                return (work.trim().to_string(), Some(movement.trim().to_string()));
+            }
+        }
+    }
+    (title.trim().to_string(), None)
+}
+
+/// `None` unless the track looks classical: a Classical genre, or — for
+/// players that don't report one — a title naming a work. The composer comes
+/// from Music's `composer` field, or else a "Beethoven: Symphony No. 5…"
+/// prefix in the title.
+pub fn parse(title: &str, composer: &str, genre: &str) -> Option<Work> {
+    let lower        = genre.to_lowercase();
+    let genre        = (!genre.is_empty()).then(|| lower.contains("classi"));
+    let mut title    = title.trim();
+    let mut composer = composer.trim().to_string();
+
+    if let Some((prefix, rest)) = title.split_once(": ") {
+        let surname = composer.rsplit(' ').next().unwrap_or("").to_lowercase();
+        let named   = !surname.is_empty() && prefix.to_lowercase().ends_with(&surname);
+        let guessed = composer.is_empty()
+            && prefix.split_whitespace().count() <= 3
+            && !prefix.chars().any(|c| c.is_ascii_digit())
+            && !has_marker(prefix)
+            && has_marker(rest);
+        if named || guessed {
+            if composer.is_empty() {
+                composer = prefix.trim().to_string();
+            }
+            title = rest;
+        }
+    }
+
+    if composer.is_empty() || !genre.unwrap_or_else(|| has_marker(title)) {
+        return None;
+    }
+    let (work, movement) = split_movement(title);
+    Some(Work { composer, work, movement })
+}
+
+// ─── Work and performance ─────────────────────────────────────────────────────
+
+#[derive(serde::Serialize, Clone)]
+pub struct ClassicalInfo {
+    /// When it was written, e.g. "1804-1808".
+    pub composed: String,
+    /// The work itself: context, form, reception.
+    pub work: String,
+    /// The movement playing, when the title names one.
+    pub movement: Option<String>,
+    /// The performers and this recording, kept apart from the work.
+    pub performance: String,
+}
+
+/// Classical counterpart of the lyrics card: the work and the performance
+/// discussed separately, since `artist` is the performer, not the composer.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn info(
+    config: &Config,
+    cancel: &CancelToken,
+    composer: &str,
+    work: &str,
+    movement: Option<&str>,
+    performer: &str,
+    album: &str,
+) -> Result<ClassicalInfo> {
+    let key = format!("{composer}\u{1f}{work}\u{1f}{}\u{1f}{performer}\u{1f}{album}", movement.unwrap_or_default());
+    inflight::coalesce("classical_info", &key, fetch_info(config, cancel, composer, work, movement, performer, album)).await
+}
+
+async fn fetch_info(
+    config: &Config,
+    cancel: &CancelToken,
+    composer: &str,
+    work: &str,
+    movement: Option<&str>,
+    performer: &str,
+    album: &str,
+) -> Result<ClassicalInfo> {
+    if !config.features.classical_info {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let language = config.language();
+    let summary  = if config.features.wikipedia {
+        wikipedia::work_summary(&language, composer, work).await
+    } else {
+        String::new()
+    };
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+
+    let prompt = build_classical_prompt(composer, work, movement, performer, album, &summary, &language);
+    let info   = llm::complete_json(config, &config.classical_info, 700, &prompt, &classical_tool(), cancel, None).await?;
+    let text   = |key: &str| info[key].as_str().unwrap_or("").trim().to_string();
+    Ok(ClassicalInfo {
+        composed:    text("composed"),
+        work:        text("work"),
+        movement:    movement.map(|_| text("movement")).filter(|m| !m.is_empty()),
+        performance: text("performance"),
+    })
+}
+
+fn build_classical_prompt(
+    composer: &str,
+    work: &str,
+    movement: Option<&str>,
+    performer: &str,
+    album: &str,
+    summary: &str,
+    language: &str,
+) -> String {
+    let movement  = movement.map(|m| format!(", mouvement « {m} »")).unwrap_or_default();
+    let grounding = if summary.is_empty() {
+        String::new()
+    } else {
+        format!("\n\nWikipédia, sur l'œuvre :\n{summary}")
+    };
+    format!(
+        "On écoute « {work} » de {composer}{movement}, interprété par {performer} (album « {album} »).{grounding}\n\n\
+         Parle séparément de l'œuvre (contexte de composition, forme, place dans le catalogue du compositeur), \
+         du mouvement s'il y en a un, puis de cette interprétation. Ne confonds pas le compositeur et les interprètes, \
+         et n'invente rien sur l'enregistrement si tu ne le connais pas. Réponds en {}.\n\n\
+         Réponds en appelant l'outil `classical_info`.",
+        language_name(language)
+    )
+}
+
+fn classical_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "classical_info",
+        description: "Enregistre la présentation d'une œuvre classique et de son interprétation",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "composed": {
+                    "type": "string",
+                    "description": "Date ou période de composition, ex. « 1804-1808 »"
+                },
+                "work": {
+                    "type": "string",
+                    "description": "L'œuvre : contexte, forme, réception, 3-4 phrases"
+                },
+                "movement": {
+                    "type": "string",
+                    "description": "Le mouvement en cours : caractère, tempo, rôle dans l'œuvre, 1-2 phrases ; vide s'il n'y en a pas"
+                },
+                "performance": {
+                    "type": "string",
+                    "description": "Les interprètes et cet enregistrement, 2-3 phrases"
+                }
+            },
+            "required": ["composed", "work", "performance"]
+        }),
+    }
+}
@@ -0,0 +1,819 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use toml_edit::{value, Document};
+use tracing::{info, warn};
+
+use crate::error::{Error, Result};
+
+/// Format of `config.toml` this build writes; older files are migrated on
+/// load, newer ones refused.
+pub const CONFIG_VERSION: i64 = 1;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiConfig {
+    #[serde(default)]
+    pub genius_token: String,
+    #[serde(default)]
+    pub anthropic_key: String,
+    /// Key for the OpenAI-compatible provider (OpenAI, Groq, a local gateway…).
+    #[serde(default)]
+    pub openai_key: String,
+    #[serde(default = "default_openai_base_url")]
+    pub openai_base_url: String,
+    /// Last.fm API key (similar artists); optional, Claude is used without it.
+    #[serde(default)]
+    pub lastfm_key: String,
+    /// Discogs personal access token (release details); optional.
+    #[serde(default)]
+    pub discogs_token: String,
+    /// Bandsintown `app_id` (upcoming shows); optional.
+    #[serde(default)]
+    pub bandsintown_app_id: String,
+    /// setlist.fm API key (recent setlists); optional.
+    #[serde(default)]
+    pub setlistfm_key: String,
+    /// fanart.tv API key (high-resolution album covers); optional.
+    #[serde(default)]
+    pub fanart_key: String,
+    /// Musixmatch API key (lyrics through the official API); optional.
+    #[serde(default)]
+    pub musixmatch_key: String,
+    /// Apple Music API (exact catalog metadata, editorial notes, large
+    /// covers): a developer token, or what signs one — team ID, key ID and
+    /// the `.p8` key file from the developer account; optional.
+    #[serde(default)]
+    pub musickit_token: String,
+    #[serde(default)]
+    pub musickit_team_id: String,
+    #[serde(default)]
+    pub musickit_key_id: String,
+    #[serde(default)]
+    pub musickit_key_path: String,
+    /// Catalog storefront, e.g. `fr`; `us` when empty.
+    #[serde(default)]
+    pub musickit_storefront: String,
+    /// Spotify Web API app (audio features of the track: energy, tempo,
+    /// key…), through the client-credentials flow; optional.
+    #[serde(default)]
+    pub spotify_client_id: String,
+    #[serde(default)]
+    pub spotify_client_secret: String,
+    /// Genius API client, for signing in from the app instead of pasting
+    /// `genius_token`; its redirect URI must be `http://127.0.0.1:53682/callback`.
+    #[serde(default)]
+    pub genius_client_id: String,
+    #[serde(default)]
+    pub genius_client_secret: String,
+}
+
+fn default_openai_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProviderKind {
+    #[default]
+    Anthropic,
+    OpenAi,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LlmConfig {
+    #[serde(default)]
+    pub provider: LlmProviderKind,
+    /// Primary model, used unless a feature overrides it. Empty means the
+    /// provider's default.
+    #[serde(default)]
+    pub model: String,
+    /// Tried in order when the chosen model is unknown (404) or overloaded.
+    #[serde(default)]
+    pub fallback_models: Vec<String>,
+    /// Sampling temperature shared by all features; the API default if unset.
+    #[serde(default)]
+    pub temperature: Option<f64>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            genius_token:          String::new(),
+            anthropic_key:         String::new(),
+            openai_key:            String::new(),
+            openai_base_url:       default_openai_base_url(),
+            lastfm_key:            String::new(),
+            discogs_token:         String::new(),
+            bandsintown_app_id:    String::new(),
+            setlistfm_key:         String::new(),
+            fanart_key:            String::new(),
+            musixmatch_key:        String::new(),
+            musickit_token:        String::new(),
+            musickit_team_id:      String::new(),
+            musickit_key_id:       String::new(),
+            musickit_key_path:     String::new(),
+            musickit_storefront:   String::new(),
+            spotify_client_id:     String::new(),
+            spotify_client_secret: String::new(),
+            genius_client_id:      String::new(),
+            genius_client_secret:  String::new(),
+        }
+    }
+}
+
+impl LlmProviderKind {
+    pub fn default_model(self) -> &'static str {
+        match self {
+            Self::Anthropic => "claude-haiku-4-5-20251001",
+            Self::OpenAi    => "gpt-4o-mini",
+        }
+    }
+}
+
+/// Per-feature overrides (`[album_info]`, `[lyrics]`, `[artist_info]`,
+/// `[similar_artists]`, `[album_deep_dive]`, `[lyrics_translation]`,
+/// `[episode_info]`, `[classical_info]`, `[wrapped]`, `[playlist]`,
+/// `[suggestion]`, `[taste_profile]`).
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FeatureConfig {
+    #[serde(default)]
+    pub model: String,
+    /// Response length cap; each feature has its own default.
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    /// Overrides `llm.temperature` for this feature.
+    #[serde(default)]
+    pub temperature: Option<f64>,
+}
+
+/// `[digest]`: the listening digest, asked for or sent as a notification.
+/// Model overrides as in [`FeatureConfig`].
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DigestConfig {
+    /// `day` or `week` for a notification at `time`; unset, only on demand.
+    pub schedule: Option<crate::digest::Period>,
+    /// `HH:MM`.
+    pub time: String,
+    /// The day weekly digests go out (`mon`…`sun`).
+    pub weekday: String,
+    #[serde(flatten)]
+    pub llm: FeatureConfig,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self { schedule: None, time: "21:00".into(), weekday: "sun".into(), llm: FeatureConfig::default() }
+    }
+}
+
+/// Where the user lives, to keep only nearby shows (`[concerts]`).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ConcertsConfig {
+    /// Both unset: every upcoming show is listed.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub radius_km: f64,
+}
+
+impl Default for ConcertsConfig {
+    fn default() -> Self {
+        Self { latitude: None, longitude: None, radius_km: 300.0 }
+    }
+}
+
+/// Artwork resolution to aim for (`[artwork] quality`).
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtworkQuality {
+    /// 600×600 from iTunes.
+    Standard,
+    /// 1200×1200 from iTunes, Cover Art Archive as a fallback.
+    #[default]
+    High,
+    /// Full-size scans: Cover Art Archive and fanart.tv first, then the
+    /// largest iTunes variant.
+    Original,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ArtworkConfig {
+    #[serde(default)]
+    pub quality: ArtworkQuality,
+}
+
+/// A player the popover can follow.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Player {
+    Music,
+    /// The Spotify desktop app, through AppleScript.
+    Spotify,
+    /// The system's Now Playing info (Safari, podcasts…).
+    System,
+}
+
+/// `[player]`: which player to follow when several are active.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// The first one playing wins; failing that, the first one paused.
+    pub sources: Vec<Player>,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self { sources: vec![Player::Music, Player::Spotify, Player::System] }
+    }
+}
+
+/// `[webhook]`: URLs POSTed a JSON event on every track change.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    /// Also post album, artist and lyrics analyses once they complete.
+    pub on_analysis: bool,
+}
+
+/// `[vault]`: the folder (an Obsidian vault, say) album info and lyrics
+/// analyses are written to as Markdown notes.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct VaultConfig {
+    pub path: String,
+    /// Write each analysis there as soon as it completes.
+    pub auto_export: bool,
+}
+
+/// `[mqtt]`: broker the now-playing state is published to, for Home
+/// Assistant and the like. An empty `host` turns it off.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: String,
+    pub password: String,
+    /// Topics are `<prefix>/state`, `<prefix>/track` and `<prefix>/artwork`.
+    pub topic_prefix: String,
+    /// Retained, so a subscriber connecting later gets the current state.
+    pub retain: bool,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host:         String::new(),
+            port:         1883,
+            client_id:    "enhanced-music".to_string(),
+            username:     String::new(),
+            password:     String::new(),
+            topic_prefix: "enhanced-music".to_string(),
+            retain:       true,
+        }
+    }
+}
+
+/// `[lights]`: where the artwork's colours go on each track change — Hue
+/// lights through the bridge's local API, and/or REST endpoints POSTed the
+/// colours as JSON.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LightsConfig {
+    /// Bridge address, e.g. `192.168.1.20`.
+    pub hue_bridge: String,
+    /// Key the bridge hands out once its link button is pressed.
+    pub hue_username: String,
+    /// Light ids; the first gets the dominant colour, the second the accent…
+    pub hue_lights: Vec<String>,
+    /// 1–254.
+    pub brightness: u8,
+    pub transition_ms: u32,
+    pub rest_urls: Vec<String>,
+}
+
+impl Default for LightsConfig {
+    fn default() -> Self {
+        Self {
+            hue_bridge:    String::new(),
+            hue_username:  String::new(),
+            hue_lights:    Vec::new(),
+            brightness:    200,
+            transition_ms: 1000,
+            rest_urls:     Vec::new(),
+        }
+    }
+}
+
+impl LightsConfig {
+    pub fn hue_enabled(&self) -> bool {
+        !self.hue_bridge.is_empty() && !self.hue_username.is_empty() && !self.hue_lights.is_empty()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.hue_enabled() || !self.rest_urls.is_empty()
+    }
+}
+
+/// `[server]`: the local HTTP API of headless mode, on `127.0.0.1` only.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub port: u16,
+    /// Bearer token every request must carry; generated into `config.toml`
+    /// on the first headless start.
+    pub token: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { port: 7373, token: String::new() }
+    }
+}
+
+/// `[clipboard]`: how `copy_now_playing` formats the track.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// Placeholders: `{title}`, `{artist}`, `{album}`, `{year}`.
+    pub format: String,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self { format: "{artist} – {title} ({album}, {year})".to_string() }
+    }
+}
+
+/// Where lyrics come from (`[lyrics_sources]`), tried in order.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LyricsSourcesConfig {
+    pub providers: Vec<crate::lyrics::Provider>,
+}
+
+impl Default for LyricsSourcesConfig {
+    fn default() -> Self {
+        use crate::lyrics::Provider;
+        Self { providers: vec![Provider::Lrclib, Provider::Musixmatch, Provider::Genius] }
+    }
+}
+
+/// A wake-up alarm (`[[alarms]]`): starts a playlist at a local time,
+/// fading the volume in.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AlarmConfig {
+    /// `HH:MM`, local time.
+    pub time: String,
+    pub playlist: String,
+    /// `mon` … `sun`; empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Volume reached at the end of the ramp, 0–100.
+    #[serde(default = "default_alarm_volume")]
+    pub volume: u8,
+    /// Length of the fade-in; 0 starts at `volume` straight away.
+    #[serde(default = "default_alarm_ramp")]
+    pub ramp_secs: u64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_alarm_volume() -> u8 {
+    60
+}
+
+fn default_alarm_ramp() -> u64 {
+    120
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct UsageConfig {
+    /// Input + output tokens allowed per calendar month; unlimited if unset.
+    #[serde(default)]
+    pub monthly_token_budget: Option<u64>,
+}
+
+/// Retry, rate-limit and timeout policy for outgoing requests (`[http]`).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Extra attempts after the first failure; 0 disables retries.
+    pub retries: u32,
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub jitter: bool,
+    /// Per-host limits, keyed by host name. Entries override the built-in
+    /// limits for Genius and iTunes.
+    pub rate_limits: HashMap<String, RateLimit>,
+    pub connect_timeout_ms: u64,
+    /// Whole-request timeout, body included — a streamed completion counts
+    /// until its last token.
+    pub timeout_ms: u64,
+    /// Per-host `timeout_ms` overrides, keyed by host name. LLM hosts default
+    /// to 60s when not listed.
+    pub timeouts: HashMap<String, u64>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            retries:            2,
+            backoff_ms:         400,
+            max_backoff_ms:     5000,
+            jitter:             true,
+            rate_limits:        HashMap::new(),
+            connect_timeout_ms: 5000,
+            timeout_ms:         20_000,
+            timeouts:           HashMap::new(),
+        }
+    }
+}
+
+/// Token bucket: `burst` requests at once, refilled at `per_minute`.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+fn default_burst() -> u32 {
+    1
+}
+
+/// On/off switches (`[features]`); everything is on by default.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FeaturesConfig {
+    pub artwork: bool,
+    pub album_info: bool,
+    pub lyrics: bool,
+    pub artist_info: bool,
+    pub similar_artists: bool,
+    pub album_deep_dive: bool,
+    /// Wikipedia intros as extra grounding for the album prompt.
+    pub wikipedia: bool,
+    /// MusicBrainz as the authoritative source for year, label and country.
+    pub musicbrainz: bool,
+    pub lyrics_translation: bool,
+    /// Show notes summary and chapters for podcast episodes.
+    pub episode_info: bool,
+    /// Work and performance notes for classical tracks.
+    pub classical_info: bool,
+    /// Follow other players (Safari, Spotify…) through the system's Now
+    /// Playing info when Music is idle.
+    pub system_now_playing: bool,
+    /// Artwork and album card of the next track in Up Next, fetched while
+    /// the current one plays.
+    pub prefetch: bool,
+    /// Keep a local record of what was heard (`history.jsonl`), for the
+    /// digests.
+    pub history: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            artwork:            true,
+            album_info:         true,
+            lyrics:             true,
+            artist_info:        true,
+            similar_artists:    true,
+            album_deep_dive:    true,
+            wikipedia:          true,
+            musicbrainz:        true,
+            lyrics_translation: true,
+            episode_info:       true,
+            classical_info:     true,
+            system_now_playing: true,
+            prefetch:           true,
+            history:            true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Config {
+    /// [`CONFIG_VERSION`] once the file has been migrated; absent in files
+    /// written before versioning.
+    #[serde(default)]
+    pub version: i64,
+    /// ISO 639-1 code for generated analyses (e.g. "fr", "en"). Empty means
+    /// the system locale.
+    #[serde(default)]
+    pub language: String,
+    /// No Genius, iTunes or LLM calls at all — only what AppleScript reports.
+    /// The tray toggle overrides it until the file is next reloaded.
+    #[serde(default)]
+    pub offline: bool,
+    /// No window: only the tray, the background poller (webhook, MQTT,
+    /// lights) and the `[server]` API. `--headless` does the same.
+    #[serde(default)]
+    pub headless: bool,
+    #[serde(default)]
+    pub features: FeaturesConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub album_info: FeatureConfig,
+    #[serde(default)]
+    pub lyrics: FeatureConfig,
+    #[serde(default)]
+    pub artist_info: FeatureConfig,
+    #[serde(default)]
+    pub similar_artists: FeatureConfig,
+    #[serde(default)]
+    pub album_deep_dive: FeatureConfig,
+    #[serde(default)]
+    pub lyrics_translation: FeatureConfig,
+    #[serde(default)]
+    pub episode_info: FeatureConfig,
+    #[serde(default)]
+    pub classical_info: FeatureConfig,
+    #[serde(default)]
+    pub digest: DigestConfig,
+    #[serde(default)]
+    pub wrapped: FeatureConfig,
+    #[serde(default)]
+    pub playlist: FeatureConfig,
+    #[serde(default)]
+    pub suggestion: FeatureConfig,
+    #[serde(default)]
+    pub taste_profile: FeatureConfig,
+    #[serde(default)]
+    pub artwork: ArtworkConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub player: PlayerConfig,
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub vault: VaultConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub lights: LightsConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub concerts: ConcertsConfig,
+    #[serde(default)]
+    pub lyrics_sources: LyricsSourcesConfig,
+    #[serde(default)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub alarms: Vec<AlarmConfig>,
+}
+
+impl Config {
+    /// Reads `config.toml`, migrating it first when it predates
+    /// [`CONFIG_VERSION`]; a missing `genius_token` is taken from the token
+    /// stored by signing in to Genius. No file means the defaults; a file
+    /// that can't be read, migrated or parsed is an error, so its keys don't
+    /// silently vanish.
+    pub fn load() -> Result<Self> {
+        Ok(Self::read()?.with_stored_token())
+    }
+
+    /// What runs when `config.toml` is unusable: the defaults, still signed
+    /// in to Genius.
+    pub fn fallback() -> Self {
+        Self::default().with_stored_token()
+    }
+
+    fn with_stored_token(mut self) -> Self {
+        if self.api.genius_token.is_empty() {
+            if let Some(token) = stored_genius_token() {
+                self.api.genius_token = token;
+            }
+        }
+        self
+    }
+
+    fn read() -> Result<Self> {
+        let path = config_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                warn!(path = %path.display(), "config not found");
+                return Ok(Self::default());
+            }
+            Err(e) => return Err(Error::Config(e.to_string())),
+        };
+        let mut doc: Document = content.parse().map_err(|e| Error::Config(format!("TOML invalide : {e}")))?;
+
+        if migrate(&mut doc)? {
+            // Kept as it was, in case the migration guessed wrong
+            let backup = path.with_extension("toml.bak");
+            match std::fs::write(&backup, &content).and_then(|()| std::fs::write(&path, doc.to_string())) {
+                Ok(()) => info!(path = %path.display(), version = CONFIG_VERSION, "config migrated"),
+                Err(e) => warn!(path = %path.display(), "migrated config not saved: {e}"),
+            }
+        }
+        let migrated = doc.to_string();
+        toml::from_str(&migrated).map_err(|e| {
+            let line = e.span().map(|span| migrated[..span.start].lines().count().max(1));
+            match line {
+                Some(line) => Error::Config(format!("ligne {line} : {}", e.message())),
+                None => Error::Config(e.message().to_string()),
+            }
+        })
+    }
+
+    /// Whether the analyses can run: they need the LLM's key. Genius, like
+    /// every other source, only adds to them when its token is set.
+    pub fn has_keys(&self) -> bool {
+        !self.llm_key().is_empty()
+    }
+
+    /// API key of the selected LLM provider.
+    pub fn llm_key(&self) -> &str {
+        match self.llm.provider {
+            LlmProviderKind::Anthropic => &self.api.anthropic_key,
+            LlmProviderKind::OpenAi    => &self.api.openai_key,
+        }
+    }
+
+    /// Models to try for a feature: its override (or the primary model),
+    /// then the fallbacks, without duplicates.
+    pub fn models_for<'a>(&'a self, feature: &'a FeatureConfig) -> Vec<&'a str> {
+        let primary = [feature.model.as_str(), self.llm.model.as_str()]
+            .into_iter()
+            .find(|m| !m.is_empty())
+            .unwrap_or(self.llm.provider.default_model());
+        let mut models: Vec<&str> = vec![primary];
+        for m in &self.llm.fallback_models {
+            if !m.is_empty() && !models.contains(&m.as_str()) {
+                models.push(m);
+            }
+        }
+        models
+    }
+
+    pub fn temperature_for(&self, feature: &FeatureConfig) -> Option<f64> {
+        feature.temperature.or(self.llm.temperature)
+    }
+
+    /// Configured output language, falling back to the system locale.
+    pub fn language(&self) -> String {
+        let lang = self.language.trim();
+        if lang.is_empty() {
+            system_language().to_string()
+        } else {
+            lang.to_lowercase()
+        }
+    }
+}
+
+/// `MIGRATIONS[n]` takes a file from version `n` to `n + 1`, moving or
+/// renaming keys in place so the user's comments survive.
+const MIGRATIONS: [fn(&mut Document); CONFIG_VERSION as usize] = [to_v1];
+
+/// `openai-compatible`, accepted as a provider name before versioning, is
+/// now spelt `openai`.
+fn to_v1(doc: &mut Document) {
+    if let Some(provider) = doc.get_mut("llm").and_then(|llm| llm.get_mut("provider")) {
+        if provider.as_str() == Some("openai-compatible") {
+            *provider = value("openai");
+        }
+    }
+}
+
+/// Brings `doc` up to [`CONFIG_VERSION`]; `true` when anything changed.
+fn migrate(doc: &mut Document) -> Result<bool> {
+    let version = match doc.get("version") {
+        None => 0,
+        Some(item) => item.as_integer().ok_or_else(|| Error::Config("`version` doit être un entier".into()))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(Error::Config(format!(
+            "fichier au format {version}, plus récent que celui de cette version de l'app ({CONFIG_VERSION})"
+        )));
+    }
+    if version == CONFIG_VERSION {
+        return Ok(false);
+    }
+    for step in MIGRATIONS.iter().skip(version.max(0) as usize) {
+        step(doc);
+    }
+    doc["version"] = value(CONFIG_VERSION);
+    Ok(true)
+}
+
+/// Primary language of the user's macOS locale (`fr_FR` → `fr`), read once.
+/// GUI apps don't inherit `LANG`, so `AppleLocale` is checked as well.
+fn system_language() -> &'static str {
+    static LANG: OnceLock<String> = OnceLock::new();
+    LANG.get_or_init(|| {
+        let from_env = ["LC_ALL", "LANG"]
+            .iter()
+            .filter_map(|k| std::env::var(k).ok())
+            .find(|v| !v.is_empty() && v != "C" && v != "POSIX");
+
+        let locale = from_env.or_else(|| {
+            std::process::Command::new("defaults")
+                .args(["read", "-g", "AppleLocale"])
+                .output()
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        });
+
+        locale
+            .and_then(|l| {
+                l.split(['_', '-', '.'])
+                    .next()
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.to_lowercase())
+            })
+            .unwrap_or_else(|| "en".to_string())
+    })
+}
+
+/// Managed config, swapped in place when the file on disk changes.
+pub struct SharedConfig(RwLock<Arc<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+
+    /// Returns a snapshot; holding it never blocks a reload.
+    pub fn get(&self) -> Arc<Config> {
+        self.0.read().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    pub fn set(&self, config: Config) {
+        if let Ok(mut c) = self.0.write() {
+            *c = Arc::new(config);
+        }
+    }
+}
+
+/// Where the app keeps its own files (usage ledger, caches…).
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home)
+        .join("Library")
+        .join("Application Support")
+        .join("enhanced-music")
+}
+
+pub fn config_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home)
+        .join(".config")
+        .join("enhanced-music")
+        .join("config.toml")
+}
+
+/// Where the token obtained by signing in to Genius is kept; `[api]
+/// genius_token`, when set, takes precedence.
+pub fn genius_token_path() -> PathBuf {
+    data_dir().join("genius_token")
+}
+
+pub fn stored_genius_token() -> Option<String> {
+    let token = std::fs::read_to_string(genius_token_path()).ok()?;
+    Some(token.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_file_is_migrated_with_its_comments() {
+        let mut doc: Document = "# mine\n[llm]\nprovider = \"openai-compatible\" # Groq\n".parse().unwrap();
+        assert!(migrate(&mut doc).unwrap());
+        assert_eq!(doc["version"].as_integer(), Some(CONFIG_VERSION));
+        assert_eq!(doc["llm"]["provider"].as_str(), Some("openai"));
+        assert!(doc.to_string().contains("# mine"));
+
+        let config: Config = toml::from_str(&doc.to_string()).unwrap();
+        assert_eq!(config.llm.provider, LlmProviderKind::OpenAi);
+    }
+
+    #[test]
+    fn current_file_is_left_alone_and_newer_one_refused() {
+        let mut doc: Document = format!("version = {CONFIG_VERSION}\n").parse().unwrap();
+        assert!(!migrate(&mut doc).unwrap());
+
+        let mut doc: Document = format!("version = {}\n", CONFIG_VERSION + 1).parse().unwrap();
+        assert!(matches!(migrate(&mut doc), Err(Error::Config(_))));
+    }
+}
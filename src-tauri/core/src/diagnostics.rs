@@ -0,0 +1,33 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+
+const KEPT_ERRORS: usize = 20;
+
+#[derive(Clone, Serialize)]
+pub struct ApiError {
+    service: String,
+    message: String,
+    /// RFC 3339, local time.
+    at: String,
+}
+
+pub fn recent_errors() -> &'static Mutex<VecDeque<ApiError>> {
+    static ERRORS: OnceLock<Mutex<VecDeque<ApiError>>> = OnceLock::new();
+    ERRORS.get_or_init(|| Mutex::new(VecDeque::with_capacity(KEPT_ERRORS)))
+}
+
+/// Remembers a request that failed for good (after retries), for the
+/// diagnostics panel.
+pub fn record_error(service: &str, err: &dyn std::fmt::Display) {
+    let Ok(mut errors) = recent_errors().lock() else { return };
+    if errors.len() == KEPT_ERRORS {
+        errors.pop_front();
+    }
+    errors.push_back(ApiError {
+        service: service.to_string(),
+        message: err.to_string(),
+        at:      chrono::Local::now().to_rfc3339(),
+    });
+}
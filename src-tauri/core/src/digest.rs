@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::cache;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history::{self, Count, Play};
+use crate::llm::{self, language_name};
+use crate::net;
+
+const TOP: usize = 5;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    /// Since midnight.
+    Day,
+    /// The last seven days, today included.
+    Week,
+}
+
+impl Period {
+    fn start(self, now: DateTime<Local>) -> DateTime<Local> {
+        let days     = if self == Period::Day { 0 } else { 6 };
+        let midnight = (now - Duration::days(days)).date_naive().and_time(NaiveTime::MIN);
+        midnight.and_local_timezone(Local).earliest().unwrap_or(now)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Period::Day  => "aujourd'hui",
+            Period::Week => "ces sept derniers jours",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Digest {
+    pub period: Period,
+    /// RFC 3339 bounds of the period.
+    pub from: String,
+    pub to: String,
+    pub plays: usize,
+    pub listening_minutes: u64,
+    pub top_artists: Vec<Count>,
+    pub top_tracks: Vec<Count>,
+    pub top_genres: Vec<Count>,
+    /// Artists heard for the first time, as far as the history goes back.
+    pub discoveries: Vec<String>,
+    /// A few words for the period's moods, by the model.
+    pub moods: Vec<String>,
+    /// A short paragraph on the period's listening, by the model.
+    pub summary: String,
+}
+
+/// Artists of `plays` absent from every play before `from`.
+fn discoveries(plays: &[Play], from: DateTime<Local>) -> Vec<String> {
+    let known: HashSet<String> = history::all()
+        .into_iter()
+        .filter(|p| p.started().is_some_and(|s| s < from))
+        .map(|p| p.artist.to_lowercase())
+        .collect();
+    let mut seen = HashSet::new();
+    plays
+        .iter()
+        .filter(|p| !p.artist.is_empty() && !known.contains(&p.artist.to_lowercase()))
+        .filter(|p| seen.insert(p.artist.to_lowercase()))
+        .map(|p| p.artist.clone())
+        .collect()
+}
+
+/// The listening of the day or week, from the local history: what was
+/// played most, what was new, and the model's take on it. Cached until a new
+/// play comes in.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn get(config: &Config, cancel: &CancelToken, period: Period) -> Result<Digest> {
+    let now   = Local::now();
+    let from  = period.start(now);
+    let plays = history::since(from);
+    if plays.is_empty() {
+        return Err(Error::NotFound("Aucune écoute enregistrée sur cette période".into()));
+    }
+
+    let language  = config.language();
+    let cache_key = format!("{period:?}|||{}|||{}|||{language}", from.date_naive(), plays.len());
+    if let Some(cached) = cache::read::<Digest>("digest", &cache_key) {
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let mut digest = Digest {
+        period,
+        from:              from.to_rfc3339(),
+        to:                now.to_rfc3339(),
+        plays:             plays.len(),
+        listening_minutes: plays.iter().map(|p| p.listened_secs).sum::<u64>() / 60,
+        top_artists:       history::top(&plays, |p| p.artist.clone(), TOP),
+        top_tracks:        history::top(&plays, |p| format!("{} — {}", p.artist, p.title), TOP),
+        top_genres:        history::top(&plays, |p| p.genre.clone(), TOP),
+        discoveries:       discoveries(&plays, from),
+        moods:             Vec::new(),
+        summary:           String::new(),
+    };
+
+    let prompt = build_digest_prompt(&digest, &plays, &language);
+    let value  = llm::complete_json(config, &config.digest.llm, 500, &prompt, &digest_tool(), cancel, None).await?;
+    digest.summary = value["summary"].as_str().unwrap_or("").to_string();
+    digest.moods   = serde_json::from_value(value["moods"].clone()).unwrap_or_default();
+    debug!(plays = digest.plays, "digest written");
+
+    cache::write("digest", &cache_key, &digest);
+    Ok(digest)
+}
+
+fn counts(counts: &[Count]) -> String {
+    counts.iter().map(|c| format!("{} ({} écoutes)", c.name, c.plays)).collect::<Vec<_>>().join(", ")
+}
+
+fn build_digest_prompt(digest: &Digest, plays: &[Play], language: &str) -> String {
+    let lang = language_name(language);
+    // The whole list for a day; a week is summed up by its tops
+    let tracks = if digest.period == Period::Day {
+        let list: Vec<String> = plays.iter().map(|p| format!("- {} — {} ({})", p.artist, p.title, p.album)).collect();
+        format!("\nMorceaux écoutés, dans l'ordre :\n{}\n", list.join("\n"))
+    } else {
+        String::new()
+    };
+    let discoveries = if digest.discoveries.is_empty() { "aucune".to_string() } else { digest.discoveries.join(", ") };
+    format!(
+        "Voici l'écoute musicale d'une personne {period} : {plays} morceaux, {minutes} minutes.\n\
+         Artistes les plus écoutés : {artists}\n\
+         Morceaux les plus écoutés : {top_tracks}\n\
+         Genres : {genres}\n\
+         Découvertes : {discoveries}\n{tracks}\n\
+         Fais-en un bilan bref et chaleureux, en {lang}, en t'adressant à elle : les humeurs qui s'en dégagent, \
+         les découvertes, ce qui a tourné en boucle. N'invente rien qui ne soit pas dans ces données.\n\n\
+         Réponds en appelant l'outil `listening_digest`.",
+        period     = digest.period.label(),
+        plays      = digest.plays,
+        minutes    = digest.listening_minutes,
+        artists    = counts(&digest.top_artists),
+        top_tracks = counts(&digest.top_tracks),
+        genres     = counts(&digest.top_genres),
+    )
+}
+
+fn digest_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "listening_digest",
+        description: "Enregistre le bilan d'écoute de la période",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "Le bilan, 3-4 phrases"
+                },
+                "moods": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "2 à 4 mots ou courtes expressions pour les humeurs de la période"
+                }
+            },
+            "required": ["summary", "moods"]
+        }),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+const API: &str = "https://api.discogs.com";
+
+/// Discogs rejects requests without a User-Agent.
+const USER_AGENT: &str = "enhanced-music/0.1 (https://github.com/Fendry02/enhanced-music)";
+
+/// Pressings listed under the master release, at most.
+const MAX_PRESSINGS: usize = 8;
+
+async fn get(url: &str, token: &str) -> Result<serde_json::Value> {
+    net::get_json("discogs", || {
+        http()
+            .get(url)
+            .header("User-Agent", USER_AGENT)
+            .header("Authorization", format!("Discogs token={token}"))
+    })
+    .await
+}
+
+pub struct Pressing {
+    pub label: String,
+    pub catalog_number: String,
+    /// e.g. "Vinyl, LP, Album" or "CD, Album, Reissue".
+    pub format: String,
+    pub country: String,
+    pub year: String,
+}
+
+pub struct Release {
+    pub label: String,
+    pub catalog_number: String,
+    pub formats: Vec<String>,
+    /// Other pressings of the same master, oldest first; empty when the
+    /// release has no master.
+    pub pressings: Vec<Pressing>,
+    pub url: String,
+    pub marketplace_url: String,
+}
+
+fn strings(list: &serde_json::Value) -> Vec<String> {
+    list.as_array()
+        .map(|a| a.iter().filter_map(|x| x.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// First Discogs release matching the album, with the pressings of its master.
+/// How Discogs credits compilations.
+pub const VARIOUS: &str = "Various";
+
+pub async fn release(token: &str, artist: &str, album: &str) -> Result<Release> {
+    let url = format!(
+        "{API}/database/search?type=release&artist={}&release_title={}&per_page=5",
+        url_encode(artist),
+        url_encode(&normalize::title(album))
+    );
+    let json = get(&url, token).await?;
+    let hit  = json["results"]
+        .get(0)
+        .ok_or_else(|| Error::NotFound("Album introuvable sur Discogs".into()))?;
+
+    let id        = hit["id"].as_i64().unwrap_or(0);
+    let master_id = hit["master_id"].as_i64().unwrap_or(0);
+
+    let pressings = if master_id > 0 {
+        let url = format!("{API}/masters/{master_id}/versions?per_page={MAX_PRESSINGS}&sort=released&sort_order=asc");
+        get(&url, token)
+            .await?["versions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|v| Pressing {
+                label:          v["label"].as_str().unwrap_or("").to_string(),
+                catalog_number: v["catno"].as_str().unwrap_or("").to_string(),
+                format:         v["format"].as_str().unwrap_or("").to_string(),
+                country:        v["country"].as_str().unwrap_or("").to_string(),
+                year:           v["released"].as_str().and_then(|d| d.get(..4)).unwrap_or("").to_string(),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let marketplace_url = if master_id > 0 {
+        format!("https://www.discogs.com/sell/list?master_id={master_id}")
+    } else {
+        format!("https://www.discogs.com/sell/release/{id}")
+    };
+
+    Ok(Release {
+        label:          hit["label"][0].as_str().unwrap_or("").to_string(),
+        catalog_number: hit["catno"].as_str().unwrap_or("").to_string(),
+        formats:        strings(&hit["format"]),
+        pressings,
+        url:            format!("https://www.discogs.com/release/{id}"),
+        marketplace_url,
+    })
+}
@@ -0,0 +1,135 @@
+use tracing::debug;
+
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::inflight;
+use crate::itunes;
+use crate::llm::{self, language_name};
+use crate::net;
+
+#[derive(serde::Serialize, Clone)]
+pub struct Chapter {
+    pub start_secs: u64,
+    pub title: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct EpisodeInfo {
+    pub show: String,
+    pub title: String,
+    pub release_date: String,
+    pub duration_secs: Option<u64>,
+    pub summary: String,
+    pub key_points: Vec<String>,
+    /// Timestamps listed in the show notes; empty when there are none.
+    pub chapters: Vec<Chapter>,
+    pub url: String,
+}
+
+/// `1:02:03` or `12:34` as seconds.
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let parts: Vec<u64> = s.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    match parts[..] {
+        [m, sec] if sec < 60 => Some(m * 60 + sec),
+        [h, m, sec] if m < 60 && sec < 60 => Some(h * 3600 + m * 60 + sec),
+        _ => None,
+    }
+}
+
+/// Chapters from show notes lines such as `(12:34) Topic` or
+/// `1:02:03 - Topic`.
+fn parse_chapters(notes: &str) -> Vec<Chapter> {
+    notes
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['(', '[']);
+            let (stamp, rest) = line.split_once(|c: char| c.is_whitespace() || c == ')' || c == ']')?;
+            let start_secs = parse_timestamp(stamp)?;
+            let title = rest.trim_start_matches(|c: char| c.is_whitespace() || "-–—:)]".contains(c)).trim();
+            (!title.is_empty()).then(|| Chapter { start_secs, title: title.to_string() })
+        })
+        .collect()
+}
+
+/// Podcast counterpart of the album and lyrics cards: show notes from the
+/// iTunes podcast directory, summarised by the model, and their chapters.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn info(config: &Config, cancel: &CancelToken, show: &str, title: &str) -> Result<EpisodeInfo> {
+    let key = format!("{show}\u{1f}{title}");
+    inflight::coalesce("episode_info", &key, fetch_info(config, cancel, show, title)).await
+}
+
+async fn fetch_info(config: &Config, cancel: &CancelToken, show: &str, title: &str) -> Result<EpisodeInfo> {
+    if !config.features.episode_info {
+        return Err(Error::Disabled);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let hit   = itunes::episode(show, title).await?;
+    let notes = hit["description"].as_str().unwrap_or("").to_string();
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+
+    let (summary, key_points) = if notes.trim().is_empty() {
+        (String::new(), Vec::new())
+    } else {
+        let prompt    = build_episode_prompt(show, title, &notes, &config.language());
+        let extracted = llm::complete_json(config, &config.episode_info, 500, &prompt, &episode_tool(), cancel, None).await?;
+        let points    = extracted["key_points"]
+            .as_array()
+            .map(|a| a.iter().filter_map(|p| p.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        (extracted["summary"].as_str().unwrap_or("").to_string(), points)
+    };
+
+    Ok(EpisodeInfo {
+        show:          hit["collectionName"].as_str().unwrap_or(show).to_string(),
+        title:         hit["trackName"].as_str().unwrap_or(title).to_string(),
+        release_date:  hit["releaseDate"].as_str().unwrap_or("").chars().take(10).collect(),
+        duration_secs: hit["trackTimeMillis"].as_u64().map(|ms| ms / 1000),
+        chapters:      parse_chapters(&notes),
+        url:           hit["trackViewUrl"].as_str().unwrap_or("").to_string(),
+        summary,
+        key_points,
+    })
+}
+
+fn build_episode_prompt(show: &str, title: &str, notes: &str, language: &str) -> String {
+    let notes: String = notes.chars().take(4000).collect();
+    format!(
+        "Voici les notes de l'épisode « {title} » du podcast {show} :\n{notes}\n\n\
+         Résume l'épisode et ses principaux sujets, sans inventer ce que les notes ne disent pas. \
+         Réponds en {}.\n\nRéponds en appelant l'outil `episode_info`.",
+        language_name(language)
+    )
+}
+
+fn episode_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "episode_info",
+        description: "Enregistre le résumé d'un épisode de podcast",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "De quoi parle l'épisode, 2-3 phrases"
+                },
+                "key_points": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "3 à 5 sujets abordés, une courte phrase chacun"
+                }
+            },
+            "required": ["summary", "key_points"]
+        }),
+    }
+}
@@ -0,0 +1,110 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::net::HttpError;
+
+/// Why a command produced nothing. Serialized as `{ kind, message }`: the UI
+/// switches on `kind` and can show `message` (French, like the rest of the UI)
+/// as-is.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Genius or LLM credentials are not configured.
+    MissingKeys,
+    /// The feature is switched off in `[features]`.
+    Disabled,
+    /// The service could not be reached (offline, DNS, timeout).
+    Network { service: String },
+    /// The service answered with a non-2xx status after retries.
+    Http { service: String, status: u16 },
+    /// The lookup succeeded but found nothing; carries the sentence to show.
+    NotFound(String),
+    /// The model answered with something unusable, or no model was available.
+    Llm(String),
+    /// `[usage] monthly_token_budget` is spent for this month.
+    BudgetExceeded,
+    /// The track changed while the command was running.
+    Cancelled,
+    /// AppleScript / Music.app could not be queried.
+    Script(String),
+    /// macOS refused the app's Apple Events: Automation isn't allowed for
+    /// the player (`-1743`).
+    Permission,
+    /// Reading or writing one of the app's own files failed.
+    Io(String),
+    /// Offline mode is on, so the data would need a network call.
+    Offline,
+    /// Signing in to a service failed or was abandoned.
+    Auth(String),
+    /// A value passed to a command was rejected; carries what was wrong.
+    Invalid(String),
+    /// `config.toml` exists but can't be read, migrated or parsed.
+    Config(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Attaches the service name to a transport-level failure. Callers log the
+    /// underlying error; only its category reaches the UI.
+    pub fn http(service: &str, err: HttpError) -> Self {
+        match err {
+            HttpError::Status(status) => Self::Http { service: service.to_string(), status },
+            HttpError::Transport(_)   => Self::Network { service: service.to_string() },
+            HttpError::Offline        => Self::Offline,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::MissingKeys    => "missing_keys",
+            Self::Disabled       => "disabled",
+            Self::Network { .. } => "network",
+            Self::Http { .. }    => "http",
+            Self::NotFound(_)    => "not_found",
+            Self::Llm(_)         => "llm",
+            Self::BudgetExceeded => "budget_exceeded",
+            Self::Cancelled      => "cancelled",
+            Self::Script(_)      => "script",
+            Self::Permission     => "permission",
+            Self::Io(_)          => "io",
+            Self::Offline        => "offline",
+            Self::Auth(_)        => "auth",
+            Self::Invalid(_)     => "invalid",
+            Self::Config(_)      => "config",
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingKeys => write!(f, "Clés API manquantes — renseignez-les dans config.toml"),
+            Self::Disabled => write!(f, "Fonction désactivée"),
+            Self::Network { service } => write!(f, "{service} injoignable — vérifiez votre connexion"),
+            Self::Http { service, status: 401 | 403 } => write!(f, "Clé {service} refusée"),
+            Self::Http { service, status: 429 } => write!(f, "Trop de requêtes vers {service}, réessayez plus tard"),
+            Self::Http { service, status } => write!(f, "Erreur {service} (HTTP {status})"),
+            Self::NotFound(what) => write!(f, "{what}"),
+            Self::Llm(detail) => write!(f, "Réponse du modèle inutilisable : {detail}"),
+            Self::BudgetExceeded => write!(f, "Budget mensuel de tokens atteint"),
+            Self::Cancelled => write!(f, "Piste changée"),
+            Self::Script(detail) => write!(f, "Impossible d'interroger Music : {detail}"),
+            Self::Permission => write!(f, "Enhanced Music n'est pas autorisé à piloter le lecteur — autorisez-le dans Réglages Système → Confidentialité et sécurité → Automatisation"),
+            Self::Io(detail) => write!(f, "Erreur de fichier : {detail}"),
+            Self::Offline => write!(f, "Mode hors ligne"),
+            Self::Auth(detail) => write!(f, "Connexion impossible : {detail}"),
+            Self::Invalid(detail) => write!(f, "Valeur invalide : {detail}"),
+            Self::Config(detail) => write!(f, "config.toml inutilisable : {detail}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Error", 2)?;
+        s.serialize_field("kind", self.kind())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
@@ -0,0 +1,301 @@
+use scraper::{ElementRef, Html, Node, Selector};
+use tracing::info;
+
+use crate::cache;
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+pub async fn get(url: &str, token: &str) -> Result<serde_json::Value> {
+    if token.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+    net::get_json("genius", || http().get(url).bearer_auth(token)).await
+}
+
+/// Fetches an album description from Genius via song search → song → album.
+/// Uses an inner `Option`-returning function so `?` can be used freely.
+pub async fn album_description(token: &str, artist: &str, album: &str) -> String {
+    if let Some(description) = album_description_inner(token, artist, album).await {
+        return description;
+    }
+    // "Artist feat. Guest" rarely finds the album; the main artist alone might
+    match normalize::primary_artist(artist) {
+        Some(primary) => album_description_inner(token, primary, album).await.unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+async fn album_description_inner(token: &str, artist: &str, album: &str) -> Option<String> {
+    let query   = url_encode(&format!("{} {}", artist, normalize::title(album)));
+    let song_id = cache::unless_missed("genius_album", &format!("{artist}\u{1f}{album}"), async {
+        let search = get(&format!("https://api.genius.com/search?q={}", query), token).await?;
+        search["response"]["hits"][0]["result"]["id"].as_i64().ok_or_else(|| {
+            info!(album, artist, "no Genius hits for album");
+            Error::NotFound("Album introuvable sur Genius".into())
+        })
+    })
+    .await
+    .ok()?;
+
+    let song     = get(&format!("https://api.genius.com/songs/{}", song_id), token).await.ok()?;
+    let album_id = song["response"]["song"]["album"]["id"].as_i64()?;
+    let album_v  = get(&format!("https://api.genius.com/albums/{}", album_id), token).await.ok()?;
+
+    album_v["response"]["album"]["description_preview"]
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "?")
+        .map(|s| s.to_string())
+}
+
+/// First search hit for the track (a song summary with `id`, `url`…).
+pub async fn search_song(token: &str, title: &str, artist: &str) -> Result<serde_json::Value> {
+    let query = url_encode(&format!("{} {}", artist, normalize::title(title)));
+    cache::unless_missed("genius_song", &format!("{artist}\u{1f}{title}"), async {
+        let search = get(&format!("https://api.genius.com/search?q={}", query), token).await?;
+
+        let hit = &search["response"]["hits"][0]["result"];
+        if hit.is_null() {
+            info!(title, artist, "no Genius hits for track");
+            return Err(Error::NotFound("Morceau introuvable sur Genius".into()));
+        }
+        Ok(hit.clone())
+    })
+    .await
+}
+
+/// The song's Genius description ("About"), trimmed to `max_chars`. Empty
+/// when Genius has none; errors are swallowed since it is only context.
+pub async fn song_description(token: &str, title: &str, artist: &str, max_chars: usize) -> String {
+    let Ok(hit) = search_song(token, title, artist).await else { return String::new() };
+    let Some(id) = hit["id"].as_i64() else { return String::new() };
+    let Ok(song) = song(token, id).await else { return String::new() };
+
+    song["description"]["plain"]
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "?")
+        .map(|s| s.chars().take(max_chars).collect())
+        .unwrap_or_default()
+}
+
+/// Full song object: credits, album, relationships, description.
+pub async fn song(token: &str, id: i64) -> Result<serde_json::Value> {
+    let json = get(&format!("https://api.genius.com/songs/{id}?text_format=plain"), token).await?;
+    Ok(json["response"]["song"].clone())
+}
+
+/// A community annotation and the lyric fragment it explains.
+pub struct Annotation {
+    pub fragment: String,
+    pub body: String,
+    pub votes: i64,
+}
+
+/// The song's most upvoted accepted annotations, best first. Long bodies are
+/// trimmed: the point is grounding for the prompt, not the full essay.
+pub async fn annotations(token: &str, song_id: i64, limit: usize) -> Result<Vec<Annotation>> {
+    let url  = format!("https://api.genius.com/referents?song_id={song_id}&text_format=plain&per_page=50");
+    let json = get(&url, token).await?;
+
+    let mut found: Vec<Annotation> = json["response"]["referents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|r| {
+            let fragment = r["fragment"].as_str().unwrap_or("").to_string();
+            r["annotations"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|a| a["state"].as_str().is_none_or(|s| s == "accepted" || s == "verified"))
+                .filter_map(move |a| {
+                    let body = a["body"]["plain"].as_str()?.trim();
+                    (!body.is_empty()).then(|| Annotation {
+                        fragment: fragment.clone(),
+                        body:     body.chars().take(400).collect(),
+                        votes:    a["votes_total"].as_i64().unwrap_or(0),
+                    })
+                })
+        })
+        .collect();
+
+    found.sort_by_key(|a| std::cmp::Reverse(a.votes));
+    found.truncate(limit);
+    Ok(found)
+}
+
+/// Genius artist matching `artist`, found through a song search (Genius has
+/// no artist search endpoint). Returns the artist JSON object.
+pub async fn artist(token: &str, artist: &str) -> Result<serde_json::Value> {
+    let id = cache::unless_missed("genius_artist", artist, async {
+        let search = get(&format!("https://api.genius.com/search?q={}", url_encode(artist)), token).await?;
+        let wanted = artist.to_lowercase();
+
+        let hits = search["response"]["hits"].as_array().cloned().unwrap_or_default();
+        hits
+            .iter()
+            .map(|h| &h["result"]["primary_artist"])
+            .find(|a| a["name"].as_str().is_some_and(|n| n.to_lowercase() == wanted))
+            .or_else(|| hits.first().map(|h| &h["result"]["primary_artist"]))
+            .and_then(|a| a["id"].as_i64())
+            .ok_or_else(|| {
+                info!(artist, "no Genius artist");
+                Error::NotFound("Artiste introuvable sur Genius".into())
+            })
+    })
+    .await?;
+
+    let url  = format!("https://api.genius.com/artists/{id}?text_format=plain");
+    let json = get(&url, token).await?;
+    Ok(json["response"]["artist"].clone())
+}
+
+// ─── Lyrics scraping ──────────────────────────────────────────────────────────
+
+/// Scrapes the lyrics from a song page, capped at 3000 characters for the
+/// prompt. `None` when the page can't be fetched or has no lyrics block.
+pub async fn fetch_lyrics(url: &str) -> Option<String> {
+    const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) \
+                              AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    let html = net::send("genius", || http().get(url).header("User-Agent", USER_AGENT))
+        .await
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+
+    let lyrics = extract_lyrics_from_html(&html);
+    if lyrics.trim().is_empty() {
+        None
+    } else {
+        Some(lyrics.chars().take(3000).collect())
+    }
+}
+
+/// Extracts plain-text lyrics from the `data-lyrics-container="true"` divs.
+/// Genius puts the "Contributors" /
+/// "Translations" header inside the first container, marked
+/// `data-exclude-from-selection`; it is skipped.
+fn extract_lyrics_from_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse(r#"div[data-lyrics-container="true"]"#) else {
+        return String::new();
+    };
+
+    let mut lyrics = String::new();
+    for container in document.select(&selector) {
+        let mut text = String::new();
+        collect_text(container, &mut text);
+        let section = tidy_lines(&text);
+        if section.is_empty() {
+            continue;
+        }
+        if !lyrics.is_empty() {
+            // Containers split wherever Genius inserts an ad; a blank line
+            // only when the next one opens a new part ("[Chorus]")
+            lyrics.push_str(if section.starts_with('[') { "\n\n" } else { "\n" });
+        }
+        lyrics.push_str(&section);
+    }
+    lyrics
+}
+
+/// Text of `node`'s subtree, with `<br>` as the only line break — newlines in
+/// the markup itself are just whitespace. Entities are already decoded by the
+/// parser.
+fn collect_text(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.extend(text.chars().map(|c| if c == '\n' { ' ' } else { c })),
+            Node::Element(e) if e.name() == "br" => out.push('\n'),
+            Node::Element(e) if matches!(e.name(), "script" | "style") || e.attr("data-exclude-from-selection") == Some("true") => {}
+            _ => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_text(child, out);
+                }
+            }
+        }
+    }
+}
+
+/// Trims each line and collapses runs of blank lines into one.
+fn tidy_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(|l| l.trim_matches(|c: char| c.is_whitespace())) {
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Plain text of an HTML fragment, `<br>` turned into newlines.
+pub fn html_to_text(fragment: &str) -> String {
+    let document = Html::parse_fragment(fragment);
+    let mut text = String::new();
+    collect_text(document.root_element(), &mut text);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/genius/{name}", env!("CARGO_MANIFEST_DIR"));
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+    }
+
+    #[test]
+    fn extracts_every_container_and_skips_the_header() {
+        let lyrics = extract_lyrics_from_html(&fixture("two_containers.html"));
+        assert_eq!(
+            lyrics,
+            "[Verse 1]\n\
+             I've been walkin' these streets\n\
+             Rock & roll \"all night\"\n\
+             \n\
+             [Chorus]\n\
+             Hold on, hold on\n\
+             \n\
+             [Verse 2]\n\
+             It's a long way down"
+        );
+        assert!(!lyrics.contains("Contributors"));
+        assert!(!lyrics.contains("Translations"));
+    }
+
+    #[test]
+    fn keeps_text_inside_links_and_inline_formatting() {
+        let lyrics = extract_lyrics_from_html(&fixture("annotated.html"));
+        assert_eq!(
+            lyrics,
+            "[Intro: Both]\n\
+             Oh, là là, c\u{2019}est l\u{2019}été\n\
+             She said, \"don't look back\"\n\
+             Caf\u{e9} au lait, nine to five"
+        );
+    }
+
+    #[test]
+    fn nested_divs_do_not_end_the_container() {
+        let lyrics = extract_lyrics_from_html(&fixture("nested_divs.html"));
+        assert_eq!(lyrics, "First line\nSecond line\nThird line");
+    }
+
+    #[test]
+    fn page_without_lyrics_gives_nothing() {
+        assert_eq!(extract_lyrics_from_html(&fixture("instrumental.html")), "");
+    }
+
+    #[test]
+    fn html_to_text_decodes_entities_and_breaks() {
+        assert_eq!(html_to_text("a&nbsp;b<br>c &amp; d&#x27;e <a href=\"x\">f</a>"), "a\u{a0}b\nc & d'e f");
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::cancel::CancelToken;
+use crate::config::{self, Config, Player};
+use crate::error::{Error, Result};
+use crate::lastfm::{self, Scrobble};
+use crate::net;
+use crate::normalize::track_key;
+use crate::player::Track;
+
+/// Below this, a track was skipped rather than heard, and isn't recorded.
+const MIN_LISTEN: Duration = Duration::from_secs(30);
+
+/// Longest stretch between two polls counted as listening: a Mac that slept
+/// mid-track didn't play for the whole night.
+const MAX_POLL_GAP: Duration = Duration::from_secs(10);
+
+/// One track heard, as kept in `history.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Play {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    #[serde(default)]
+    pub genre: String,
+    pub player: Player,
+    /// RFC 3339, local time.
+    pub started_at: String,
+    /// Time actually spent playing, pauses left out.
+    pub listened_secs: u64,
+    /// Brought in from Last.fm's scrobbles ([`import_lastfm`]): `player` is
+    /// then a guess, and `listened_secs` an estimate.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub imported: bool,
+}
+
+impl Play {
+    pub fn started(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.started_at).ok().map(|d| d.with_timezone(&Local))
+    }
+}
+
+/// `~/Library/Application Support/enhanced-music/history.jsonl`: one play
+/// per line, appended as each track ends.
+fn path() -> PathBuf {
+    config::data_dir().join("history.jsonl")
+}
+
+/// The track under way: what it will be recorded as, and how long it has
+/// played so far.
+struct Current {
+    play: Play,
+    key: String,
+    listened: Duration,
+    seen: Instant,
+    playing: bool,
+}
+
+static CURRENT: Mutex<Option<Current>> = Mutex::new(None);
+
+/// Held while `history.jsonl` is written, so an import rewriting it doesn't
+/// lose a play appended meanwhile.
+static WRITING: Mutex<()> = Mutex::new(());
+
+fn append(play: &Play) {
+    let _writing = WRITING.lock();
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let line = match serde_json::to_string(play) {
+        Ok(line) => line,
+        Err(e) => return warn!("cannot serialize play: {e}"),
+    };
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| writeln!(f, "{line}"));
+    if let Err(e) = written {
+        warn!(path = %path.display(), "cannot record play: {e}");
+    }
+}
+
+/// Follows the poller: counts the time the track plays and, once another
+/// starts (or playback stops), records it if it was heard long enough.
+/// Podcasts and `[features] history = false` are left out.
+pub fn observe(config: &Config, track: Option<&Track>) {
+    let Ok(mut current) = CURRENT.lock() else { return };
+    let now = Instant::now();
+    let key = track.map(Track::key);
+
+    if let Some(c) = current.as_mut() {
+        if c.playing {
+            c.listened += now.duration_since(c.seen).min(MAX_POLL_GAP);
+        }
+        c.seen = now;
+        if Some(&c.key) == key.as_ref() {
+            c.playing = track.is_some_and(|t| t.is_playing);
+            return;
+        }
+    }
+    if let Some(finished) = current.take() {
+        if finished.listened >= MIN_LISTEN {
+            let play = Play { listened_secs: finished.listened.as_secs(), ..finished.play };
+            debug!(title = %play.title, secs = play.listened_secs, "play recorded");
+            append(&play);
+        }
+    }
+
+    let Some(track) = track.filter(|t| config.features.history && !t.is_podcast && !t.title.is_empty()) else { return };
+    *current = Some(Current {
+        play: Play {
+            title:         track.title.clone(),
+            artist:        track.artist.clone(),
+            album:         track.album.clone(),
+            genre:         track.genre.clone(),
+            player:        track.player,
+            started_at:    Local::now().to_rfc3339(),
+            listened_secs: 0,
+            imported:      false,
+        },
+        key:      track.key(),
+        listened: Duration::ZERO,
+        seen:     now,
+        playing:  track.is_playing,
+    });
+}
+
+/// Plays started at or after `from`, oldest first. Unreadable lines are
+/// skipped rather than losing the rest.
+pub fn since(from: DateTime<Local>) -> Vec<Play> {
+    let Ok(content) = std::fs::read_to_string(path()) else { return Vec::new() };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Play>(line).ok())
+        .filter(|p| p.started().is_some_and(|s| s >= from))
+        .collect()
+}
+
+/// Every play recorded, oldest first.
+pub fn all() -> Vec<Play> {
+    since(DateTime::<Local>::MIN_UTC.with_timezone(&Local))
+}
+
+/// Plays of each track in the whole history, by
+/// [`normalize::track_key`](crate::normalize::track_key).
+pub fn plays_by_track() -> HashMap<String, usize> {
+    let mut plays: HashMap<String, usize> = HashMap::new();
+    for play in all() {
+        *plays.entry(track_key(&play.title, &play.artist)).or_default() += 1;
+    }
+    plays
+}
+
+/// Something counted in a set of plays: an artist, a track, a genre.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Count {
+    pub name: String,
+    pub plays: usize,
+    pub minutes: u64,
+}
+
+/// The `limit` most played values of `key`, most played first; ties go to
+/// the longest listened. Empty names are left out.
+pub fn top(plays: &[Play], key: impl Fn(&Play) -> String, limit: usize) -> Vec<Count> {
+    let mut counts: HashMap<String, (usize, u64)> = HashMap::new();
+    for play in plays {
+        let name = key(play);
+        if name.is_empty() {
+            continue;
+        }
+        let entry = counts.entry(name).or_default();
+        entry.0 += 1;
+        entry.1 += play.listened_secs;
+    }
+    let mut counts: Vec<Count> = counts
+        .into_iter()
+        .map(|(name, (plays, secs))| Count { name, plays, minutes: secs / 60 })
+        .collect();
+    counts.sort_by(|a, b| b.plays.cmp(&a.plays).then(b.minutes.cmp(&a.minutes)).then(a.name.cmp(&b.name)));
+    counts.truncate(limit);
+    counts
+}
+
+// ─── Export ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Plays started between two local dates (`YYYY-MM-DD`), both included;
+/// either end may be left open.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Range {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Range {
+    fn dates(&self) -> Result<(Option<NaiveDate>, Option<NaiveDate>)> {
+        let parse = |d: &Option<String>| {
+            d.as_deref()
+                .filter(|d| !d.trim().is_empty())
+                .map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| Error::Invalid(format!("date invalide : {d}"))))
+                .transpose()
+        };
+        Ok((parse(&self.from)?, parse(&self.to)?))
+    }
+}
+
+/// A CSV field, quoted when it holds a separator, a quote or a line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn to_csv(plays: &[Play]) -> String {
+    let mut csv = String::from("started_at,title,artist,album,genre,player,listened_secs\n");
+    for p in plays {
+        let player = serde_json::to_value(p.player).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        let fields = [&p.started_at, &p.title, &p.artist, &p.album, &p.genre, &player];
+        let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+        csv.push_str(&format!("{fields},{}\n", p.listened_secs));
+    }
+    csv
+}
+
+/// Writes the plays of `range`, oldest first, to `path`, and returns how
+/// many there were. The CSV has a header row and opens as is in Numbers or
+/// a spreadsheet; the JSON is an array of plays as kept in `history.jsonl`.
+pub fn export(format: ExportFormat, range: &Range, path: &Path) -> Result<usize> {
+    let (from, to) = range.dates()?;
+    let plays: Vec<Play> = all()
+        .into_iter()
+        .filter(|p| {
+            let Some(day) = p.started().map(|s| s.date_naive()) else { return false };
+            from.is_none_or(|f| day >= f) && to.is_none_or(|t| day <= t)
+        })
+        .collect();
+    let content = match format {
+        ExportFormat::Csv  => to_csv(&plays),
+        ExportFormat::Json => serde_json::to_string_pretty(&plays).map_err(|e| Error::Io(e.to_string()))?,
+    };
+    std::fs::write(path, content).map_err(|e| Error::Io(e.to_string()))?;
+    info!(path = %path.display(), plays = plays.len(), ?format, "history exported");
+    Ok(plays.len())
+}
+
+// ─── Last.fm import ───────────────────────────────────────────────────────────
+
+/// Time counted for an imported play of a track never heard here: Last.fm
+/// doesn't say how long a track played.
+const IMPORTED_SECS: u64 = 210;
+
+/// A scrobble started this close to a play of the same track already in the
+/// history is that play, recorded here and scrobbled by Music as well.
+const SAME_PLAY_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Import {
+    pub imported: usize,
+    /// Scrobbles already in the history, left out.
+    pub duplicates: usize,
+}
+
+/// `plays` with the `scrobbles` not already among them, oldest first.
+/// Imported plays count the time the track was listened to on average here,
+/// or [`IMPORTED_SECS`].
+fn merge(mut plays: Vec<Play>, scrobbles: Vec<Scrobble>) -> (Vec<Play>, Import) {
+    let mut starts: HashMap<String, Vec<i64>> = HashMap::new();
+    let mut listened: HashMap<String, (u64, u64)> = HashMap::new();
+    for play in &plays {
+        let key = track_key(&play.title, &play.artist);
+        if let Some(start) = play.started() {
+            starts.entry(key.clone()).or_default().push(start.timestamp());
+        }
+        if !play.imported {
+            let entry = listened.entry(key).or_default();
+            entry.0 += play.listened_secs;
+            entry.1 += 1;
+        }
+    }
+
+    let mut import = Import { imported: 0, duplicates: 0 };
+    for scrobble in scrobbles {
+        let Some(started) = Local.timestamp_opt(scrobble.at, 0).single() else { continue };
+        let key   = track_key(&scrobble.title, &scrobble.artist);
+        let known = starts.entry(key.clone()).or_default();
+        if known.iter().any(|s| (s - scrobble.at).abs() < SAME_PLAY_SECS) {
+            import.duplicates += 1;
+            continue;
+        }
+        known.push(scrobble.at);
+        plays.push(Play {
+            title:         scrobble.title,
+            artist:        scrobble.artist,
+            album:         scrobble.album,
+            genre:         String::new(),
+            player:        Player::Music,
+            started_at:    started.to_rfc3339(),
+            listened_secs: listened.get(&key).map_or(IMPORTED_SECS, |(secs, n)| secs / n),
+            imported:      true,
+        });
+        import.imported += 1;
+    }
+    plays.sort_by_key(|p| p.started().map_or(i64::MIN, |s| s.timestamp()));
+    (plays, import)
+}
+
+/// Backfills the history with `user`'s scrobbles, so the stats go back
+/// further than the install. Plays already recorded (here, or by an earlier
+/// import) are recognised and left as they are.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn import_lastfm(config: &Config, cancel: &CancelToken, user: &str) -> Result<Import> {
+    let user = user.trim();
+    if user.is_empty() {
+        return Err(Error::Invalid("nom d'utilisateur Last.fm manquant".into()));
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.lastfm_key.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let mut scrobbles = Vec::new();
+    let mut page = 1;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let (batch, pages) = lastfm::recent_tracks(&config.api.lastfm_key, user, page).await?;
+        debug!(page, pages, "scrobbles fetched");
+        scrobbles.extend(batch);
+        if page >= pages {
+            break;
+        }
+        page += 1;
+    }
+
+    let _writing = WRITING.lock();
+    let (plays, import) = merge(all(), scrobbles);
+    if import.imported > 0 {
+        let mut content = String::new();
+        for play in &plays {
+            content.push_str(&serde_json::to_string(play).map_err(|e| Error::Io(e.to_string()))?);
+            content.push('\n');
+        }
+        // Written aside first, so a failure halfway leaves the history whole
+        let path = path();
+        let tmp  = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, content).map_err(|e| Error::Io(e.to_string()))?;
+        std::fs::rename(&tmp, &path).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    info!(user, imported = import.imported, duplicates = import.duplicates, "Last.fm history imported");
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(artist: &str, secs: u64) -> Play {
+        Play {
+            title:         "t".into(),
+            artist:        artist.into(),
+            album:         String::new(),
+            genre:         String::new(),
+            player:        Player::Music,
+            started_at:    Local::now().to_rfc3339(),
+            listened_secs: secs,
+            imported:      false,
+        }
+    }
+
+    #[test]
+    fn top_counts_plays_then_minutes() {
+        let plays = [play("A", 60), play("B", 600), play("A", 60), play("", 900), play("C", 120)];
+        let top   = top(&plays, |p| p.artist.clone(), 2);
+        assert_eq!(top.iter().map(|c| (c.name.as_str(), c.plays)).collect::<Vec<_>>(), [("A", 2), ("B", 1)]);
+        assert_eq!(top[1].minutes, 10);
+    }
+
+    #[test]
+    fn merge_skips_scrobbles_already_recorded() {
+        let heard = play("A", 100);
+        let at    = heard.started().unwrap().timestamp();
+        let scrobble = |artist: &str, at: i64| Scrobble { title: "t".into(), artist: artist.into(), album: String::new(), at };
+        let (plays, import) = merge(vec![heard], vec![scrobble("a", at + 30), scrobble("A", at - 86_400), scrobble("B", at - 60)]);
+        assert_eq!((import.imported, import.duplicates), (2, 1));
+        assert_eq!(plays.iter().map(|p| (p.artist.as_str(), p.listened_secs)).collect::<Vec<_>>(), [("A", 100), ("B", 210), ("A", 100)]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let mut p = play("Simon & Garfunkel", 180);
+        p.title = "Bridge Over Troubled Water, \"live\"".into();
+        let csv = to_csv(&[p]);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.ends_with(",\"Bridge Over Troubled Water, \"\"live\"\"\",Simon & Garfunkel,,,music,180"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::error::{Error, Result};
+
+type Flights = HashMap<String, Box<dyn Any + Send>>;
+
+/// One result sender per lookup under way, keyed by namespace and
+/// arguments; the `Any` is a `broadcast::Sender<Result<T>>` of that
+/// namespace's `T`.
+fn flights() -> &'static Mutex<Flights> {
+    static FLIGHTS: OnceLock<Mutex<Flights>> = OnceLock::new();
+    FLIGHTS.get_or_init(Default::default)
+}
+
+/// How long a prefetched result waits for the call that wants it: a track
+/// or two, not the whole session.
+const PREFETCH_TTL: Duration = Duration::from_secs(15 * 60);
+
+type Prefetched = HashMap<String, (Instant, Box<dyn Any + Send>)>;
+
+/// Results fetched ahead of time, for the next track; each is handed out
+/// once.
+fn prefetched() -> &'static Mutex<Prefetched> {
+    static PREFETCHED: OnceLock<Mutex<Prefetched>> = OnceLock::new();
+    PREFETCHED.get_or_init(Default::default)
+}
+
+fn take_prefetched<T: 'static>(id: &str) -> Option<T> {
+    let (at, value) = prefetched().lock().ok()?.remove(id)?;
+    if at.elapsed() >= PREFETCH_TTL {
+        return None;
+    }
+    value.downcast::<T>().ok().map(|v| *v)
+}
+
+/// Clears a lookup's entry, including when its future is dropped halfway:
+/// callers waiting on it then see the channel close and run it themselves.
+struct Flight(String);
+
+impl Drop for Flight {
+    fn drop(&mut self) {
+        if let Ok(mut flights) = flights().lock() {
+            flights.remove(&self.0);
+        }
+    }
+}
+
+/// Runs `work`, unless the same lookup is already running: then waits for
+/// that one and returns a copy of its result. A popover that asks twice for
+/// the same album (a re-render, two windows) costs one model call. A result
+/// left by [`prefetch`] is returned without running anything.
+pub async fn coalesce<T>(namespace: &str, key: &str, work: impl Future<Output = Result<T>>) -> Result<T>
+where
+    T: Clone + Send + 'static,
+{
+    let id = format!("{namespace}/{}", key.trim().to_lowercase());
+    if let Some(value) = take_prefetched::<T>(&id) {
+        debug!(namespace, key, "prefetched");
+        return Ok(value);
+    }
+    run(namespace, key, id, work).await.0
+}
+
+/// [`coalesce`] past the prefetched results, also telling whether another
+/// caller took part in the run: joined it, or was handed its result.
+async fn run<T>(namespace: &str, key: &str, id: String, work: impl Future<Output = Result<T>>) -> (Result<T>, bool)
+where
+    T: Clone + Send + 'static,
+{
+    let joined = {
+        let Ok(mut flights) = flights().lock() else { return (work.await, false) };
+        match flights.get(&id).and_then(|f| f.downcast_ref::<broadcast::Sender<Result<T>>>()) {
+            Some(sender) => Some(sender.subscribe()),
+            None => {
+                flights.insert(id.clone(), Box::new(broadcast::channel::<Result<T>>(1).0));
+                None
+            }
+        }
+    };
+
+    if let Some(mut receiver) = joined {
+        debug!(namespace, key, "joining the lookup under way");
+        return match receiver.recv().await {
+            // The first caller's track changed, not necessarily ours: our own
+            // `work` checks our own token
+            Ok(Err(Error::Cancelled)) => (work.await, true),
+            Ok(result) => (result, true),
+            // The first caller was dropped before it finished
+            Err(_) => (work.await, false),
+        };
+    }
+
+    let flight = Flight(id);
+    let result = work.await;
+    let sender = flights().lock().ok().and_then(|mut f| f.remove(&flight.0));
+    let shared = match sender.and_then(|s| s.downcast::<broadcast::Sender<Result<T>>>().ok()) {
+        // No receivers is fine: nobody else asked
+        Some(sender) => sender.send(result.clone()).is_ok_and(|receivers| receivers > 0),
+        None => false,
+    };
+    (result, shared)
+}
+
+/// Runs `work` like [`coalesce`], keeping a success for the next call of the
+/// same lookup. Errors are dropped: the real call will report its own. A
+/// result a caller already got isn't kept either, or the next track's call
+/// would be served this one's leftover twice.
+pub async fn prefetch<T>(namespace: &str, key: &str, work: impl Future<Output = Result<T>>)
+where
+    T: Clone + Send + 'static,
+{
+    let id = format!("{namespace}/{}", key.trim().to_lowercase());
+    let (Ok(value), false) = run(namespace, key, id.clone(), work).await else { return };
+    if let Ok(mut prefetched) = prefetched().lock() {
+        prefetched.retain(|_, (at, _)| at.elapsed() < PREFETCH_TTL);
+        prefetched.insert(id, (Instant::now(), Box::new(value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_lookups_share_one_run() {
+        let runs = AtomicUsize::new(0);
+        let work = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(42)
+        };
+        let (a, b) = tokio::join!(coalesce("test", "album", work()), coalesce("test", "Album", work()));
+        assert_eq!((a.unwrap(), b.unwrap()), (42, 42));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Finished lookups aren't remembered
+        coalesce("test", "album", work()).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_run_is_redone_for_its_joiner() {
+        let cancelled = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err::<i32, _>(Error::Cancelled)
+        };
+        let (a, b) = tokio::join!(coalesce("test", "cancelled", cancelled), coalesce("test", "cancelled", async { Ok(7) }));
+        assert!(matches!(a, Err(Error::Cancelled)));
+        assert_eq!(b.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn a_prefetch_someone_joined_is_not_kept() {
+        let work = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(1)
+        };
+        let ((), joined) = tokio::join!(prefetch("test", "joined", work()), coalesce("test", "joined", work()));
+        assert_eq!(joined.unwrap(), 1);
+        assert!(take_prefetched::<i32>("test/joined").is_none());
+
+        prefetch("test", "alone", work()).await;
+        assert_eq!(take_prefetched::<i32>("test/alone"), Some(1));
+    }
+}
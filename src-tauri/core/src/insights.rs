@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config;
+use crate::normalize::{fold, track_key};
+use crate::notes::{self, Note, Subject};
+
+// Analyses and notes go into an SQLite table as they are written, its text
+// indexed by FTS5: words compared without accents, queried by their first
+// letters, ranked by bm25.
+
+const MAX_HITS: usize = 20;
+
+/// Words shorter than this are left out of a query ("a", "the", "de"…).
+const MIN_TERM: usize = 3;
+
+/// Words are looked up by this many first letters (or all of a shorter
+/// one): "leaving" finds "leaves", "partir" finds "partira".
+const STEM: usize = 4;
+
+/// Words of text shown around the matches.
+const SNIPPET_WORDS: usize = 30;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        id         INTEGER PRIMARY KEY,
+        key        TEXT NOT NULL UNIQUE,
+        source     TEXT NOT NULL,
+        note_id    INTEGER,
+        title      TEXT NOT NULL,
+        artist     TEXT NOT NULL,
+        album      TEXT NOT NULL,
+        text       TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+        text,
+        content = 'entries', content_rowid = 'id',
+        prefix = '3 4', tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER IF NOT EXISTS entries_insert AFTER INSERT ON entries BEGIN
+        INSERT INTO entries_fts (rowid, text) VALUES (new.id, new.text);
+    END;
+    CREATE TRIGGER IF NOT EXISTS entries_delete AFTER DELETE ON entries BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, text) VALUES ('delete', old.id, old.text);
+    END;
+    CREATE TRIGGER IF NOT EXISTS entries_update AFTER UPDATE ON entries BEGIN
+        INSERT INTO entries_fts (entries_fts, rowid, text) VALUES ('delete', old.id, old.text);
+        INSERT INTO entries_fts (rowid, text) VALUES (new.id, new.text);
+    END;
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    LyricsAnalysis,
+    AlbumInfo,
+    Note,
+}
+
+impl Source {
+    fn name(self) -> &'static str {
+        match self {
+            Self::LyricsAnalysis => "lyrics_analysis",
+            Self::AlbumInfo      => "album_info",
+            Self::Note           => "note",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Self::LyricsAnalysis, Self::AlbumInfo, Self::Note].into_iter().find(|s| s.name() == name)
+    }
+}
+
+/// One analysis or note as indexed.
+struct Entry<'a> {
+    source: Source,
+    note_id: Option<u64>,
+    /// The track's title; empty for album info.
+    title: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    text: &'a str,
+    /// RFC 3339.
+    updated_at: String,
+}
+
+/// `~/Library/Application Support/enhanced-music/insights.db`.
+fn path() -> PathBuf {
+    config::data_dir().join("insights.db")
+}
+
+/// Opens the index, creating it if needed; `true` when it was just created.
+fn open(path: &Path) -> rusqlite::Result<(Connection, bool)> {
+    let conn    = Connection::open(path)?;
+    let created = conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE name = 'entries'", [], |_| Ok(()))
+        .optional()?
+        .is_none();
+    conn.execute_batch(SCHEMA)?;
+    Ok((conn, created))
+}
+
+fn db() -> &'static Mutex<Option<Connection>> {
+    static DB: OnceLock<Mutex<Option<Connection>>> = OnceLock::new();
+    DB.get_or_init(|| {
+        let path = path();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let conn = match open(&path) {
+            Ok((conn, created)) => {
+                // Notes written before the index existed
+                if created {
+                    let notes = notes::get(None, None, None);
+                    for note in &notes {
+                        put_note(&conn, note);
+                    }
+                    info!(notes = notes.len(), "search index created");
+                }
+                Some(conn)
+            }
+            Err(e) => {
+                error!(path = %path.display(), "cannot open search index: {e}");
+                None
+            }
+        };
+        Mutex::new(conn)
+    })
+}
+
+/// Runs `f` on the index, if it could be opened.
+fn with_db<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Option<T> {
+    let db = db().lock().ok()?;
+    f(db.as_ref()?).map_err(|e| warn!("search index: {e}")).ok()
+}
+
+/// Adds or updates an entry. Unchanged text isn't written again, so a cached
+/// analysis shown once more costs nothing.
+fn put(conn: &Connection, key: &str, entry: &Entry) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO entries (key, source, note_id, title, artist, album, text, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT (key) DO UPDATE SET
+             title = excluded.title, artist = excluded.artist, album = excluded.album,
+             text = excluded.text, updated_at = excluded.updated_at
+         WHERE text <> excluded.text",
+        params![key, entry.source.name(), entry.note_id, entry.title, entry.artist, entry.album, entry.text, entry.updated_at],
+    )?;
+    Ok(())
+}
+
+fn note_key(id: u64) -> String {
+    format!("note\u{1f}{id}")
+}
+
+fn put_note(conn: &Connection, note: &Note) {
+    let (title, album) = match note.subject {
+        Subject::Track => (note.title.as_str(), ""),
+        Subject::Album => ("", note.title.as_str()),
+    };
+    let entry = Entry {
+        source:     Source::Note,
+        note_id:    Some(note.id),
+        title,
+        artist:     &note.artist,
+        album,
+        text:       &note.text,
+        updated_at: note.created_at.clone(),
+    };
+    if let Err(e) = put(conn, &note_key(note.id), &entry) {
+        warn!(id = note.id, "note not indexed: {e}");
+    }
+}
+
+/// Adds or updates an analysis in the index.
+pub fn record(source: Source, title: &str, artist: &str, album: &str, text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    let key   = format!("{}\u{1f}{}\u{1f}{}", source.name(), track_key(title, artist), fold(album));
+    let entry = Entry { source, note_id: None, title, artist, album, text, updated_at: Local::now().to_rfc3339() };
+    with_db(|conn| put(conn, &key, &entry));
+}
+
+/// Indexes a note just added.
+pub fn record_note(note: &Note) {
+    with_db(|conn| {
+        put_note(conn, note);
+        Ok(())
+    });
+}
+
+/// Takes a deleted note out of the index.
+pub fn forget_note(id: u64) {
+    with_db(|conn| conn.execute("DELETE FROM entries WHERE key = ?1", [note_key(id)]));
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hit {
+    pub source: Source,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// The text around the matches, "…" where it was cut.
+    pub snippet: String,
+    /// For a note, to open or delete it.
+    pub note_id: Option<u64>,
+    /// bm25 relevance, higher is better.
+    pub score: f64,
+}
+
+fn terms(query: &str) -> Vec<String> {
+    // Each word once, in the query's order, so a repeated one can't count twice
+    let mut seen = HashSet::new();
+    fold(query)
+        .split(' ')
+        .filter(|t| t.chars().count() >= MIN_TERM && seen.insert(*t))
+        .map(str::to_string)
+        .collect()
+}
+
+fn stem(word: &str) -> &str {
+    word.char_indices().nth(STEM).map_or(word, |(i, _)| &word[..i])
+}
+
+/// An FTS5 query matching any of `terms` by its first letters. Folded words
+/// are letters and digits only, so quoting them is enough.
+fn match_query(terms: &[String]) -> String {
+    terms.iter().map(|t| format!("\"{}\"*", stem(t))).collect::<Vec<_>>().join(" OR ")
+}
+
+fn search_in(conn: &Connection, terms: &[String]) -> rusqlite::Result<Vec<Hit>> {
+    let mut statement = conn.prepare_cached(
+        "SELECT e.source, e.title, e.artist, e.album, e.note_id,
+                snippet(entries_fts, 0, '', '', '…', ?2), -bm25(entries_fts)
+         FROM entries_fts JOIN entries e ON e.id = entries_fts.rowid
+         WHERE entries_fts MATCH ?1
+         ORDER BY bm25(entries_fts)
+         LIMIT ?3",
+    )?;
+    let rows = statement.query_map(params![match_query(terms), SNIPPET_WORDS, MAX_HITS], |row| {
+        Ok(Hit {
+            source:  Source::parse(&row.get::<_, String>(0)?).unwrap_or(Source::Note),
+            title:   row.get(1)?,
+            artist:  row.get(2)?,
+            album:   row.get(3)?,
+            note_id: row.get(4)?,
+            snippet: row.get(5)?,
+            score:   row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Analyses and notes that talk about `query` ("une chanson sur le départ"),
+/// best matches first: those with the most of the query's words, and the
+/// rarer ones, rank higher.
+pub fn search(query: &str) -> Vec<Hit> {
+    let terms = terms(query);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    with_db(|conn| search_in(conn, &terms)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_match_by_their_first_letters() {
+        let terms = terms("a song about leaving home");
+        assert_eq!(terms, ["song", "about", "leaving", "home"]);
+        assert_eq!(super::terms("home sweet home"), ["home", "sweet"]);
+        assert_eq!(match_query(&terms), r#""song"* OR "abou"* OR "leav"* OR "home"*"#);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let entry = |text| Entry {
+            source: Source::LyricsAnalysis,
+            note_id: None,
+            title: "Title",
+            artist: "Artist",
+            album: "",
+            text,
+            updated_at: String::new(),
+        };
+        put(&conn, "a", &entry("He sings of leaves and the home he left.")).unwrap();
+        put(&conn, "b", &entry("Il parle de départ.")).unwrap();
+        put(&conn, "c", &entry("Nothing to see.")).unwrap();
+
+        let hits = search_in(&conn, &terms).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].snippet, "He sings of leaves and the home he left.");
+        assert_eq!(search_in(&conn, &super::terms("départs")).unwrap()[0].snippet, "Il parle de départ.");
+
+        // Updated in place, the old text no longer found
+        put(&conn, "a", &entry("Nothing at all.")).unwrap();
+        assert!(search_in(&conn, &terms).unwrap().is_empty());
+        assert_eq!(search_in(&conn, &super::terms("nothing")).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,275 @@
+use tracing::warn;
+
+use crate::cache;
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+/// How iTunes, MusicBrainz and Last.fm credit compilations.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Highest-scoring result at or above [`normalize::MATCH_THRESHOLD`]; the
+/// earlier result wins a tie, iTunes having ranked them by relevance.
+fn best_match(results: &[serde_json::Value], score: impl Fn(&serde_json::Value) -> f64) -> Option<&serde_json::Value> {
+    results
+        .iter()
+        .map(|r| (score(r), r))
+        .filter(|(s, _)| *s >= normalize::MATCH_THRESHOLD)
+        .fold(None, |best: Option<(f64, &serde_json::Value)>, (s, r)| match best {
+            Some((b, _)) if b >= s => best,
+            _ => Some((s, r)),
+        })
+        .map(|(_, r)| r)
+}
+
+/// How well a result's artist matches: main artists only, as guests are
+/// credited inconsistently.
+fn artist_score(result: &serde_json::Value, artist: &str, compilation: bool) -> f64 {
+    let credited = result["artistName"].as_str().unwrap_or("");
+    if compilation && credited == VARIOUS_ARTISTS {
+        return 1.0;
+    }
+    normalize::similarity(normalize::split_featuring(credited).primary, normalize::split_featuring(artist).primary)
+}
+
+/// Album and artist must both match; edition suffixes are ignored on both
+/// sides.
+fn album_score(result: &serde_json::Value, artist: &str, album: &str, compilation: bool) -> f64 {
+    let name = normalize::title(result["collectionName"].as_str().unwrap_or(""));
+    normalize::similarity(&name, album).min(artist_score(result, artist, compilation))
+}
+
+fn song_score(result: &serde_json::Value, title: &str, artist: &str) -> f64 {
+    let name = normalize::title(result["trackName"].as_str().unwrap_or(""));
+    normalize::similarity(&name, title).min(artist_score(result, artist, false))
+}
+
+/// Best matching album from the iTunes Search API, scored on the album and
+/// artist names Music reports. For a compilation, `artist` is the track's
+/// artist — it helps the search find the album — and an album credited to
+/// Various Artists matches too.
+pub async fn album(artist: &str, album: &str, compilation: bool) -> Result<serde_json::Value> {
+    let album = normalize::title(album);
+    let query = url_encode(&format!("{} {}", artist, album));
+    let url = format!(
+        "https://itunes.apple.com/search?term={}&media=music&entity=album&limit=10",
+        query
+    );
+    let key = format!("{artist}\u{1f}{album}\u{1f}{compilation}");
+    cache::unless_missed("itunes_album", &key, async {
+        let json    = net::get_json("itunes", || http().get(&url)).await?;
+        let results = json["results"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        best_match(results, |r| album_score(r, artist, &album, compilation))
+            .cloned()
+            .ok_or_else(|| Error::NotFound("Album introuvable sur iTunes".into()))
+    })
+    .await
+}
+
+/// Best matching song, scored like [`album`].
+pub async fn song(title: &str, artist: &str) -> Result<serde_json::Value> {
+    let title   = normalize::title(title);
+    let query   = url_encode(&format!("{} {}", artist, title));
+    let url     = format!("https://itunes.apple.com/search?term={query}&media=music&entity=song&limit=10");
+    cache::unless_missed("itunes_song", &format!("{artist}\u{1f}{title}"), async {
+        let json    = net::get_json("itunes", || http().get(&url)).await?;
+        let results = json["results"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        best_match(results, |r| song_score(r, &title, artist))
+            .cloned()
+            .ok_or_else(|| Error::NotFound("Morceau introuvable sur iTunes".into()))
+    })
+    .await
+}
+
+/// A podcast episode: the result whose episode title contains `title`,
+/// preferring one from the show `show`.
+pub async fn episode(show: &str, title: &str) -> Result<serde_json::Value> {
+    let query = url_encode(&format!("{show} {title}"));
+    let url   = format!("https://itunes.apple.com/search?term={query}&media=podcast&entity=podcastEpisode&limit=10");
+    cache::unless_missed("itunes_episode", &format!("{show}\u{1f}{title}"), async {
+        let json = net::get_json("itunes", || http().get(&url)).await?;
+
+        let title_lc = title.to_lowercase();
+        let show_lc  = show.to_lowercase();
+        let results  = json["results"].as_array().cloned().unwrap_or_default();
+        let titled   = |r: &&serde_json::Value| r["trackName"].as_str().unwrap_or("").to_lowercase().contains(&title_lc);
+
+        results
+            .iter()
+            .filter(titled)
+            .find(|r| r["collectionName"].as_str().unwrap_or("").to_lowercase().contains(&show_lc))
+            .or_else(|| results.iter().find(titled))
+            .cloned()
+            .ok_or_else(|| Error::NotFound("Épisode introuvable".into()))
+    })
+    .await
+}
+
+/// music.apple.com link of a song, without the affiliate `uo` parameter.
+pub async fn track_url(title: &str, artist: &str) -> Result<String> {
+    let hit = song(title, artist).await?;
+    let url = hit["trackViewUrl"]
+        .as_str()
+        .ok_or_else(|| Error::NotFound("Morceau introuvable sur iTunes".into()))?;
+    Ok(url.replace("&uo=4", "").replace("?uo=4", ""))
+}
+
+#[derive(Default)]
+pub struct AlbumMetadata {
+    pub year: String,
+    pub genre: String,
+    /// Read off the copyright line ("℗ 1977 Warner Records Inc."), so only a
+    /// best guess.
+    pub label: String,
+    pub collection_id: Option<i64>,
+}
+
+/// Year, genre and label from the iTunes Search API; empty when not found.
+pub async fn album_metadata(artist: &str, album_name: &str, compilation: bool) -> AlbumMetadata {
+    let hit = match album(artist, album_name, compilation).await {
+        Ok(hit) => hit,
+        Err(e) => {
+            warn!(album = album_name, artist, "iTunes album lookup failed: {e}");
+            return AlbumMetadata::default();
+        }
+    };
+
+    AlbumMetadata {
+        year:          hit["releaseDate"].as_str().and_then(|d| d.get(..4)).unwrap_or("").to_string(),
+        genre:         hit["primaryGenreName"].as_str().unwrap_or("").to_string(),
+        label:         label_from_copyright(hit["copyright"].as_str().unwrap_or("")),
+        collection_id: hit["collectionId"].as_i64(),
+    }
+}
+
+/// "℗ 2019 Columbia Records, a Division of Sony Music" → "Columbia Records".
+fn label_from_copyright(copyright: &str) -> String {
+    let rest = copyright.trim_start_matches(['℗', '©', ' ']);
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == ' ');
+    rest.split(',').next().unwrap_or("").trim().to_string()
+}
+
+/// Running time of an iTunes collection, in milliseconds.
+pub async fn album_length_ms(collection_id: i64) -> Result<u64> {
+    let url  = format!("https://itunes.apple.com/lookup?id={collection_id}&entity=song&limit=200");
+    let json = net::get_json("itunes", || http().get(&url)).await?;
+
+    Ok(json["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["wrapperType"] == "track")
+        .filter_map(|r| r["trackTimeMillis"].as_u64())
+        .sum())
+}
+
+/// Track titles of an iTunes collection, in disc/track order.
+pub async fn tracklist(collection_id: i64) -> Result<Vec<String>> {
+    let url  = format!("https://itunes.apple.com/lookup?id={collection_id}&entity=song&limit=200");
+    let json = net::get_json("itunes", || http().get(&url)).await?;
+
+    let mut tracks: Vec<(i64, i64, String)> = json["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["wrapperType"] == "track")
+        .filter_map(|r| {
+            Some((
+                r["discNumber"].as_i64().unwrap_or(1),
+                r["trackNumber"].as_i64().unwrap_or(0),
+                r["trackName"].as_str()?.to_string(),
+            ))
+        })
+        .collect();
+    tracks.sort();
+    Ok(tracks.into_iter().map(|(_, _, name)| name).collect())
+}
+
+/// Editorial video keys of the music.apple.com page data, preferred first:
+/// the square loops fit behind the artwork.
+const MOTION_KEYS: [&str; 3] = ["motionDetailSquare", "motionSquareVideo1x1", "motionDetailTall"];
+
+/// HLS URL of the album's animated cover, when Apple Music has one. There is
+/// no public API for it — the URL is read off the album page's embedded data.
+pub async fn animated_artwork(collection_id: i64) -> Result<Option<String>> {
+    let url  = format!("https://music.apple.com/us/album/{collection_id}");
+    let page = net::send("apple music", || http().get(&url))
+        .await
+        .map_err(|e| Error::http("apple music", e))?
+        .text()
+        .await
+        .map_err(|e| Error::http("apple music", net::HttpError::Transport(e)))?;
+    Ok(motion_video_url(&page.replace("\\u002F", "/")))
+}
+
+fn motion_video_url(page: &str) -> Option<String> {
+    MOTION_KEYS.iter().find_map(|key| {
+        let after = &page[page.find(&format!("\"{key}\""))?..];
+        let start = after.find("https://")?;
+        let end   = start + after[start..].find(".m3u8")? + ".m3u8".len();
+        let url   = &after[start..end];
+        // Stay inside the key's own value
+        (!url.contains('"')).then(|| url.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn albums() -> Vec<serde_json::Value> {
+        vec![
+            json!({ "collectionName": "Ágætis byrjun - Live", "artistName": "Sigur Rós" }),
+            json!({ "collectionName": "Ágætis byrjun (Deluxe Edition)", "artistName": "Sigur Rós" }),
+            json!({ "collectionName": "What's Going On", "artistName": "Marvin Gaye" }),
+            json!({ "collectionName": "Now That's What I Call Music! 100", "artistName": "Various Artists" }),
+        ]
+    }
+
+    fn best_album<'a>(results: &'a [serde_json::Value], artist: &str, album: &str, compilation: bool) -> Option<&'a str> {
+        best_match(results, |r| album_score(r, artist, album, compilation)).and_then(|r| r["collectionName"].as_str())
+    }
+
+    #[test]
+    fn matches_accented_album_typed_without_accents() {
+        let results = albums();
+        assert_eq!(best_album(&results, "Sigur Ros", "Agaetis byrjun", false), Some("Ágætis byrjun (Deluxe Edition)"));
+    }
+
+    #[test]
+    fn matches_punctuated_title() {
+        let results = albums();
+        assert_eq!(best_album(&results, "Marvin Gaye", "Whats Going On", false), Some("What's Going On"));
+        assert_eq!(best_album(&results, "Marvin Gaye", "What’s Going On (Remastered)", false), Some("What's Going On"));
+    }
+
+    #[test]
+    fn compilations_match_various_artists() {
+        let results = albums();
+        let album   = "Now That's What I Call Music 100";
+        assert_eq!(best_album(&results, "Dua Lipa", album, false), None);
+        assert_eq!(best_album(&results, "Dua Lipa", album, true), Some("Now That's What I Call Music! 100"));
+    }
+
+    #[test]
+    fn rejects_weak_matches() {
+        let results = albums();
+        assert_eq!(best_album(&results, "Marvin Gaye", "Let's Get It On", false), None);
+        assert_eq!(best_album(&results, "Sigur Rós", "What's Going On", false), None);
+    }
+
+    #[test]
+    fn songs_ignore_featured_artists() {
+        let results = vec![
+            json!({ "trackName": "Get Lucky (feat. Pharrell Williams)", "artistName": "Daft Punk" }),
+            json!({ "trackName": "Get Lucky", "artistName": "Daft Punk feat. Pharrell Williams" }),
+        ];
+        let hit = best_match(&results, |r| song_score(r, "Get Lucky", "Daft Punk feat. Pharrell Williams, Nile Rodgers"));
+        assert_eq!(hit.and_then(|r| r["trackName"].as_str()), Some("Get Lucky (feat. Pharrell Williams)"));
+    }
+}
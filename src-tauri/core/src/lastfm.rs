@@ -0,0 +1,117 @@
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+const API: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Calls a read-only Last.fm method. Last.fm reports failures as `200` with
+/// an `error` field, so those are turned into errors here.
+pub async fn call(key: &str, method: &str, params: &[(&str, &str)]) -> Result<serde_json::Value> {
+    let mut url = format!("{API}?method={method}&api_key={key}&format=json");
+    for (name, value) in params {
+        url.push_str(&format!("&{name}={}", url_encode(value)));
+    }
+
+    let json = net::get_json("lastfm", || http().get(&url)).await?;
+    match json["error"].as_i64() {
+        // 6: artist/track not found
+        Some(6) => Err(Error::NotFound("Introuvable sur Last.fm".into())),
+        Some(code) => Err(Error::Http { service: "lastfm".into(), status: code as u16 }),
+        None => Ok(json),
+    }
+}
+
+pub struct Similar {
+    pub name: String,
+    /// 0.0–1.0, as computed by Last.fm.
+    pub score: f64,
+}
+
+pub async fn similar_artists(key: &str, artist: &str, limit: usize) -> Result<Vec<Similar>> {
+    let limit   = limit.to_string();
+    let json    = call(key, "artist.getsimilar", &[("artist", artist), ("limit", &limit), ("autocorrect", "1")]).await?;
+    let artists = json["similarartists"]["artist"].as_array().cloned().unwrap_or_default();
+
+    Ok(artists
+        .iter()
+        .filter_map(|a| {
+            Some(Similar {
+                name:  a["name"].as_str()?.to_string(),
+                score: a["match"].as_str().and_then(|m| m.parse().ok()).unwrap_or(0.0),
+            })
+        })
+        .collect())
+}
+
+#[derive(Default)]
+pub struct AlbumStats {
+    /// Plain-text wiki summary, without the trailing "Read more on Last.fm" link.
+    pub wiki: String,
+    pub tags: Vec<String>,
+    pub listeners: Option<u64>,
+    pub playcount: Option<u64>,
+}
+
+pub async fn album_info(key: &str, artist: &str, album: &str, language: &str) -> Result<AlbumStats> {
+    let album = normalize::title(album);
+    let json  = call(key, "album.getinfo", &[("artist", artist), ("album", &album), ("lang", language), ("autocorrect", "1")]).await?;
+    let album = &json["album"];
+
+    let summary = album["wiki"]["summary"].as_str().unwrap_or("");
+    let summary = summary.split("<a href").next().unwrap_or("");
+    // Empty tag lists come back as `"tags": ""` rather than an empty array
+    let tags = album["tags"]["tag"]
+        .as_array()
+        .map(|t| t.iter().filter_map(|t| t["name"].as_str().map(str::to_lowercase)).collect())
+        .unwrap_or_default();
+    let count = |field: &str| album[field].as_str().and_then(|n| n.parse().ok());
+
+    Ok(AlbumStats {
+        wiki:      crate::genius::html_to_text(summary).trim().to_string(),
+        tags,
+        listeners: count("listeners"),
+        playcount: count("playcount"),
+    })
+}
+
+/// A track scrobbled by a Last.fm user.
+pub struct Scrobble {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// When it started playing, in seconds since the epoch.
+    pub at: i64,
+}
+
+/// Scrobbles per page of `user.getrecenttracks`, the most it allows.
+pub const SCROBBLES_PER_PAGE: usize = 200;
+
+/// One page (from 1) of `user`'s scrobbles, newest first, and the number of
+/// pages there are. The track playing now, not scrobbled yet, is left out.
+pub async fn recent_tracks(key: &str, user: &str, page: usize) -> Result<(Vec<Scrobble>, usize)> {
+    let page  = page.to_string();
+    let limit = SCROBBLES_PER_PAGE.to_string();
+    let json  = call(key, "user.getrecenttracks", &[("user", user), ("page", &page), ("limit", &limit)]).await?;
+    let recent = &json["recenttracks"];
+    let pages  = recent["@attr"]["totalPages"].as_str().and_then(|n| n.parse().ok()).unwrap_or(0);
+
+    // A single track comes back as an object rather than a one-item array
+    let tracks = match &recent["track"] {
+        serde_json::Value::Array(tracks) => tracks.clone(),
+        track @ serde_json::Value::Object(_) => vec![track.clone()],
+        _ => Vec::new(),
+    };
+    let scrobbles = tracks
+        .iter()
+        .filter_map(|t| {
+            Some(Scrobble {
+                title:  t["name"].as_str()?.to_string(),
+                artist: t["artist"]["#text"].as_str()?.to_string(),
+                album:  t["album"]["#text"].as_str().unwrap_or("").to_string(),
+                at:     t["date"]["uts"].as_str()?.parse().ok()?,
+            })
+        })
+        .collect();
+    Ok((scrobbles, pages))
+}
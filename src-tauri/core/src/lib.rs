@@ -0,0 +1,110 @@
+//! Everything Enhanced Music knows how to look up, without the window: the
+//! desktop app, its headless mode and the `emusic` CLI all sit on top of it.
+
+pub mod album;
+pub mod artist;
+pub mod artwork;
+pub mod audio_analysis;
+pub mod bandsintown;
+pub mod bookmarks;
+pub mod cache;
+pub mod cancel;
+pub mod classical;
+pub mod config;
+pub mod diagnostics;
+pub mod digest;
+pub mod discogs;
+pub mod episode;
+pub mod error;
+pub mod genius;
+pub mod history;
+pub mod inflight;
+pub mod insights;
+pub mod itunes;
+pub mod lastfm;
+pub mod lights;
+pub mod llm;
+pub mod moods;
+pub mod lyrics;
+pub mod mqtt;
+pub mod music;
+pub mod musicbrainz;
+pub mod musickit;
+pub mod musixmatch;
+pub mod net;
+pub mod normalize;
+pub mod notes;
+pub mod now_playing;
+pub mod odesli;
+pub mod player;
+pub mod playlist;
+pub mod prefetch;
+pub mod radio;
+pub mod scripting_bridge;
+pub mod setlistfm;
+pub mod settings;
+pub mod song;
+pub mod spotify;
+pub mod suggestion;
+pub mod taste;
+pub mod usage;
+pub mod vault;
+pub mod webhook;
+pub mod wikipedia;
+pub mod wrapped;
+
+// ─── URL encoding ─────────────────────────────────────────────────────────────
+
+/// `s` as a query-string value: UTF-8 bytes percent-encoded, spaces as `+`.
+pub fn url_encode(s: &str) -> String {
+    encode(s, "+")
+}
+
+/// `s` as a URL path segment, where a `+` would be taken literally.
+pub fn path_encode(s: &str) -> String {
+    encode(s, "%20")
+}
+
+/// The reverse of [`url_encode`], for the query strings the app's local
+/// servers receive.
+pub fn url_decode(s: &str) -> String {
+    let bytes   = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i   = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 2;
+            }
+            (b'+', _) => out.push(b' '),
+            (b, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn encode(s: &str, space: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => char::from(b).to_string(),
+            b' ' => space.to_string(),
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_utf8_bytes() {
+        assert_eq!(url_encode("Café Noir"), "Caf%C3%A9+Noir");
+        assert_eq!(url_encode("坂本龍一"), "%E5%9D%82%E6%9C%AC%E9%BE%8D%E4%B8%80");
+        assert_eq!(url_decode("Caf%C3%A9+Noir"), "Café Noir");
+        assert_eq!(path_encode("Björk Guðmundsdóttir"), "Bj%C3%B6rk%20Gu%C3%B0mundsd%C3%B3ttir");
+    }
+}
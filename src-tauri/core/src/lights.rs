@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::artwork;
+use crate::config::{Config, LightsConfig};
+use crate::net::{self, http};
+
+/// Bumped on every track change, so a slow artwork download doesn't repaint
+/// the room with a track that's already been skipped.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Posted to each `[lights] rest_urls` entry.
+#[derive(Serialize)]
+struct Colours<'a> {
+    title: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    /// `#rrggbb`.
+    dominant: String,
+    accent: String,
+    dominant_rgb: [u8; 3],
+    accent_rgb: [u8; 3],
+}
+
+/// CIE xy of an sRGB colour, as Hue bulbs take it (Philips' wide-gamut
+/// conversion). Black has no chromaticity; it maps to white's.
+fn xy([r, g, b]: [u8; 3]) -> [f64; 2] {
+    let [r, g, b] = [r, g, b].map(artwork::srgb_to_linear);
+    let x   = r * 0.664511 + g * 0.154324 + b * 0.162028;
+    let y   = r * 0.283881 + g * 0.668433 + b * 0.047685;
+    let z   = r * 0.000088 + g * 0.072310 + b * 0.986039;
+    let sum = x + y + z;
+    if sum == 0.0 {
+        return xy([255, 255, 255]);
+    }
+    [(x / sum * 10_000.0).round() / 10_000.0, (y / sum * 10_000.0).round() / 10_000.0]
+}
+
+/// What the bridge refused: it answers 200 even to an unknown username or
+/// light, with `[{"error": {"description": …}}]`.
+fn hue_errors(reply: &serde_json::Value) -> Vec<String> {
+    reply
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| r["error"]["description"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Sets the Hue lights through the bridge's local API, alternating dominant
+/// and accent colours so a room with several bulbs shows both.
+async fn hue(config: &LightsConfig, palette: &artwork::Palette) {
+    for (i, light) in config.hue_lights.iter().enumerate() {
+        let colour = if i % 2 == 0 { palette.dominant_rgb } else { palette.accent_rgb };
+        let body   = serde_json::json!({
+            "on": true,
+            "xy": xy(colour),
+            "bri": config.brightness.clamp(1, 254),
+            // In tenths of a second
+            "transitiontime": config.transition_ms / 100,
+        });
+        let url = format!("http://{}/api/{}/lights/{light}/state", config.hue_bridge, config.hue_username);
+        let reply = match net::send("hue", || http().put(&url).json(&body)).await {
+            Ok(resp) => resp.json::<serde_json::Value>().await.unwrap_or_default(),
+            Err(e) => {
+                warn!(light, "Hue light failed: {e}");
+                continue;
+            }
+        };
+        match hue_errors(&reply)[..] {
+            [] => debug!(light, "Hue light set"),
+            ref errors => warn!(light, "Hue light refused: {}", errors.join(" ; ")),
+        }
+    }
+}
+
+/// Paints the lights with the new track's artwork colours, in the
+/// background. Nothing happens without a `[lights]` target, or when the
+/// artwork can't be had; failures are only logged.
+pub fn track_changed(config: &Config, title: &str, artist: &str, album: &str) {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if !config.lights.enabled() {
+        return;
+    }
+    let config = config.clone();
+    let (title, artist, album) = (title.to_string(), artist.to_string(), album.to_string());
+    tokio::spawn(async move {
+        let palette = match artwork::fetch(&config, &title, &artist, &album).await {
+            Ok(bytes) => match artwork::thumbnail(&bytes, artwork::PALETTE_SIZE).await {
+                Ok(thumb) => artwork::palette(&thumb),
+                Err(e) => return warn!("light sync: artwork unreadable: {e}"),
+            },
+            Err(e) => return debug!(title, artist, "light sync: no artwork: {e}"),
+        };
+        if GENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+
+        let lights = &config.lights;
+        if lights.hue_enabled() {
+            hue(lights, &palette).await;
+        }
+        let body = Colours {
+            title:        &title,
+            artist:       &artist,
+            album:        &album,
+            dominant:     artwork::hex(palette.dominant_rgb),
+            accent:       artwork::hex(palette.accent_rgb),
+            dominant_rgb: palette.dominant_rgb,
+            accent_rgb:   palette.accent_rgb,
+        };
+        for url in &lights.rest_urls {
+            match net::send("lights", || http().post(url).json(&body)).await {
+                Ok(_) => debug!(url, "light endpoint updated"),
+                Err(e) => warn!(url, "light endpoint failed: {e}"),
+            }
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_primaries_map_to_the_gamut_corners() {
+        assert_eq!(xy([255, 0, 0]), [0.7006, 0.2993]);
+        assert_eq!(xy([0, 255, 0]), [0.1724, 0.7468]);
+        assert_eq!(xy([0, 0, 255]), [0.1355, 0.0399]);
+        assert_eq!(xy([0, 0, 0]), xy([255, 255, 255]));
+    }
+
+    #[test]
+    fn reads_the_bridge_errors_of_a_200_reply() {
+        let reply = serde_json::json!([
+            { "success": { "/lights/1/state/on": true } },
+            { "error": { "type": 1, "address": "/lights/1/state", "description": "unauthorized user" } },
+        ]);
+        assert_eq!(hue_errors(&reply), ["unauthorized user"]);
+    }
+}
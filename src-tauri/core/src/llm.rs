@@ -0,0 +1,551 @@
+use async_trait::async_trait;
+use tracing::{debug, error, warn};
+
+use crate::cancel::CancelToken;
+use crate::config::{Config, FeatureConfig, LlmProviderKind};
+use crate::error::{self, Error};
+use crate::net::{self, http, HttpError, HttpResult};
+use crate::usage;
+
+/// A single forced tool call: the model must answer by "calling" it with
+/// arguments matching `schema`, which gives us structured output without
+/// parsing JSON out of free text.
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// JSON Schema of the arguments (an `object` with `properties`/`required`).
+    pub schema: serde_json::Value,
+}
+
+/// Parameters shared by every attempt of one completion.
+pub struct Request<'a> {
+    pub max_tokens: u64,
+    pub temperature: Option<f64>,
+    pub prompt: &'a str,
+    pub tool: Option<&'a Tool>,
+    /// Checked between stream events; a cancelled stream is dropped, which
+    /// closes the connection and stops generation server-side.
+    pub cancel: &'a CancelToken,
+}
+
+/// Token counts reported by the provider for one completion.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Generated text — or, when a tool was forced, the JSON of its arguments.
+pub struct Completion {
+    pub text: String,
+    pub usage: Usage,
+}
+
+/// Model unknown or retired (404), or overloaded (503, 529): the next model
+/// is tried straight away, without waiting through `net::send`'s retries.
+const FALLBACK_STATUSES: [u16; 3] = [404, 503, 529];
+
+pub enum SendError {
+    /// The model is unknown or overloaded — the next model may work.
+    Unavailable(u16),
+    /// Anything else; trying another model won't help.
+    Failed(Error),
+    /// The track changed while the request was in flight.
+    Cancelled,
+}
+
+/// Streaming callback receiving text as it arrives. Spelled as an alias so
+/// `async_trait` doesn't rewrite the elided `&str` lifetime.
+pub type OnDelta<'a> = dyn for<'s> FnMut(&'s str) + Send + 'a;
+
+/// A chat-completion backend. Implementations perform a single attempt
+/// against a single model; fallback across models is handled by [`complete_json`].
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn send(&self, model: &str, req: &Request<'_>) -> Result<Completion, SendError>;
+
+    /// Like `send`, but calls `on_delta` with each text fragment as it
+    /// arrives. Providers without streaming deliver everything in one fragment.
+    async fn send_stream(
+        &self,
+        model: &str,
+        req: &Request<'_>,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Completion, SendError> {
+        let completion = self.send(model, req).await?;
+        on_delta(&completion.text);
+        Ok(completion)
+    }
+}
+
+pub struct Anthropic<'a> {
+    pub api_key: &'a str,
+}
+
+impl Anthropic<'_> {
+    fn post(&self) -> reqwest::RequestBuilder {
+        http()
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", self.api_key)
+            .header("anthropic-version", "2023-06-01")
+    }
+
+    fn body(model: &str, req: &Request, stream: bool) -> serde_json::Value {
+        let mut body = chat_body(model, req, stream);
+        if let Some(tool) = req.tool {
+            body["tools"] = serde_json::json!([{
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.schema,
+            }]);
+            body["tool_choice"] = serde_json::json!({ "type": "tool", "name": tool.name });
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl LlmProvider for Anthropic<'_> {
+    fn name(&self) -> &'static str {
+        "claude"
+    }
+
+    async fn send(&self, model: &str, req: &Request<'_>) -> Result<Completion, SendError> {
+        let body     = Self::body(model, req, false);
+        let response = net::send_except(self.name(), &FALLBACK_STATUSES, || self.post().json(&body)).await;
+        let json     = read_json(response, self.name()).await?;
+        let blocks   = json["content"].as_array().cloned().unwrap_or_default();
+
+        let tool_input = blocks.iter().find(|b| b["type"] == "tool_use").map(|b| b["input"].to_string());
+        let text       = blocks.iter().find_map(|b| b["text"].as_str()).map(|s| s.to_string());
+
+        let text = tool_input.or(text).ok_or_else(|| {
+            error!(provider = "claude", "unexpected response shape: {json}");
+            SendError::Failed(Error::Llm("réponse vide".into()))
+        })?;
+        let usage = Usage {
+            input_tokens:  json["usage"]["input_tokens"].as_u64().unwrap_or(0),
+            output_tokens: json["usage"]["output_tokens"].as_u64().unwrap_or(0),
+        };
+        Ok(Completion { text, usage })
+    }
+
+    async fn send_stream(
+        &self,
+        model: &str,
+        req: &Request<'_>,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Completion, SendError> {
+        let body     = Self::body(model, req, true);
+        let response = net::send_except(self.name(), &FALLBACK_STATUSES, || self.post().json(&body)).await;
+        read_sse(response, self.name(), req.cancel, on_delta, |event: &serde_json::Value, usage: &mut Usage| {
+            match event["type"].as_str() {
+                Some("message_start") => {
+                    usage.input_tokens = event["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                    None
+                }
+                Some("message_delta") => {
+                    usage.output_tokens = event["usage"]["output_tokens"].as_u64().unwrap_or(0);
+                    None
+                }
+                Some("content_block_delta") => {
+                    let delta = &event["delta"];
+                    delta["text"].as_str().or(delta["partial_json"].as_str()).map(|s| s.to_string())
+                }
+                _ => None,
+            }
+        })
+        .await
+    }
+}
+
+/// Any server speaking the OpenAI `/chat/completions` protocol.
+pub struct OpenAiCompatible<'a> {
+    pub base_url: &'a str,
+    pub api_key: &'a str,
+}
+
+impl OpenAiCompatible<'_> {
+    fn post(&self) -> reqwest::RequestBuilder {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        http().post(url).bearer_auth(self.api_key)
+    }
+
+    fn body(model: &str, req: &Request, stream: bool) -> serde_json::Value {
+        let mut body = chat_body(model, req, stream);
+        if let Some(tool) = req.tool {
+            body["tools"] = serde_json::json!([{
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.schema,
+                },
+            }]);
+            body["tool_choice"] = serde_json::json!({
+                "type": "function",
+                "function": { "name": tool.name },
+            });
+        }
+        if stream {
+            // Without this the stream never reports token counts
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatible<'_> {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn send(&self, model: &str, req: &Request<'_>) -> Result<Completion, SendError> {
+        let body     = Self::body(model, req, false);
+        let response = net::send_except(self.name(), &FALLBACK_STATUSES, || self.post().json(&body)).await;
+        let json     = read_json(response, self.name()).await?;
+        let message  = &json["choices"][0]["message"];
+
+        let text = message["tool_calls"][0]["function"]["arguments"]
+            .as_str()
+            .or(message["content"].as_str())
+            .ok_or_else(|| {
+                error!(provider = "openai", "unexpected response shape: {json}");
+                SendError::Failed(Error::Llm("réponse vide".into()))
+            })?;
+        Ok(Completion { text: text.to_string(), usage: openai_usage(&json) })
+    }
+
+    async fn send_stream(
+        &self,
+        model: &str,
+        req: &Request<'_>,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<Completion, SendError> {
+        let body     = Self::body(model, req, true);
+        let response = net::send_except(self.name(), &FALLBACK_STATUSES, || self.post().json(&body)).await;
+        read_sse(response, self.name(), req.cancel, on_delta, |event: &serde_json::Value, usage: &mut Usage| {
+            if event["usage"].is_object() {
+                *usage = openai_usage(event);
+            }
+            let delta = &event["choices"][0]["delta"];
+            delta["tool_calls"][0]["function"]["arguments"]
+                .as_str()
+                .or(delta["content"].as_str())
+                .map(|s| s.to_string())
+        })
+        .await
+    }
+}
+
+fn openai_usage(json: &serde_json::Value) -> Usage {
+    Usage {
+        input_tokens:  json["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+        output_tokens: json["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+    }
+}
+
+/// Request body fields shared by the Anthropic and OpenAI chat endpoints.
+fn chat_body(model: &str, req: &Request, stream: bool) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": model,
+        "max_tokens": req.max_tokens,
+        "messages": [{ "role": "user", "content": req.prompt }]
+    });
+    if let Some(t) = req.temperature {
+        body["temperature"] = t.into();
+    }
+    if stream {
+        body["stream"] = true.into();
+    }
+    body
+}
+
+async fn read_json(response: HttpResult, ctx: &str) -> Result<serde_json::Value, SendError> {
+    match response {
+        Ok(resp) => resp.json().await.map_err(|e| {
+            warn!(provider = ctx, "JSON parse error: {e}");
+            SendError::Failed(Error::http(ctx, HttpError::Transport(e)))
+        }),
+        Err(e) => Err(status_error(e, ctx)),
+    }
+}
+
+fn status_error(err: HttpError, ctx: &str) -> SendError {
+    match err {
+        HttpError::Status(code) if FALLBACK_STATUSES.contains(&code) => SendError::Unavailable(code),
+        e => {
+            warn!(provider = ctx, "request error: {e}");
+            SendError::Failed(Error::http(ctx, e))
+        }
+    }
+}
+
+/// Reads a server-sent-events body line by line, handing each `data:` payload
+/// to `delta_of`, which forwards any text it carries and records token usage.
+async fn read_sse(
+    response: HttpResult,
+    ctx: &str,
+    cancel: &CancelToken,
+    on_delta: &mut OnDelta<'_>,
+    delta_of: impl Fn(&serde_json::Value, &mut Usage) -> Option<String>,
+) -> Result<Completion, SendError> {
+    let mut response = response.map_err(|e| status_error(e, ctx))?;
+    let mut pending  = Vec::new();
+    let mut text     = String::new();
+    let mut usage    = Usage::default();
+
+    loop {
+        if cancel.is_cancelled() {
+            return Err(SendError::Cancelled);
+        }
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                warn!(provider = ctx, "stream interrupted: {e}");
+                return Err(SendError::Failed(Error::http(ctx, HttpError::Transport(e))));
+            }
+        };
+        pending.extend_from_slice(&chunk);
+
+        // Only complete lines are parsed; a UTF-8 sequence split across
+        // chunks stays in `pending` until its line ends.
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+
+            let Some(data) = line.trim_end().strip_prefix("data:").map(str::trim) else { continue };
+            if data == "[DONE]" {
+                return Ok(Completion { text, usage });
+            }
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else { continue };
+            if event["type"] == "error" {
+                error!(provider = ctx, "stream error: {}", event["error"]);
+                let message = event["error"]["message"].as_str().unwrap_or("erreur de flux");
+                return Err(SendError::Failed(Error::Llm(message.to_string())));
+            }
+            if let Some(delta) = delta_of(&event, &mut usage) {
+                on_delta(&delta);
+                text.push_str(&delta);
+            }
+        }
+    }
+
+    Ok(Completion { text, usage })
+}
+
+/// Builds the provider selected in `[llm]`.
+pub fn provider(config: &Config) -> Box<dyn LlmProvider + '_> {
+    match config.llm.provider {
+        LlmProviderKind::Anthropic => Box::new(Anthropic { api_key: &config.api.anthropic_key }),
+        LlmProviderKind::OpenAi => Box::new(OpenAiCompatible {
+            base_url: &config.api.openai_base_url,
+            api_key:  &config.api.openai_key,
+        }),
+    }
+}
+
+/// Forces `tool` and returns its validated arguments, trying the feature's
+/// model chain in order: a 404 (model unknown or retired) or an overload
+/// status moves on to the next one. If the answer doesn't match the schema,
+/// the error is fed back once for a repair attempt.
+///
+/// With `on_partial`, the call is streamed and the callback receives the raw
+/// arguments JSON accumulated so far for the current attempt (see
+/// [`partial_json_string`]).
+pub async fn complete_json(
+    config: &Config,
+    feature: &FeatureConfig,
+    default_max_tokens: u64,
+    prompt: &str,
+    tool: &Tool,
+    cancel: &CancelToken,
+    mut on_partial: Option<&mut OnDelta<'_>>,
+) -> error::Result<serde_json::Value> {
+    let mut prompt = prompt.to_string();
+    let mut error  = String::new();
+
+    for attempt in 0..2 {
+        let request = build_request(config, feature, default_max_tokens, &prompt, Some(tool), cancel);
+        let raw     = with_fallback(config, feature, &request, on_partial.as_deref_mut()).await?;
+
+        match parse_tool_output(&raw, tool) {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt == 0 => {
+                warn!(tool = tool.name, "invalid output ({err}), asking for a repair");
+                prompt = format!(
+                    "{prompt}\n\nTa réponse précédente n'était pas conforme ({err}) :\n{raw}\n\
+                     Corrige-la en appelant l'outil `{}` avec des arguments valides.",
+                    tool.name
+                );
+            }
+            Err(err) => {
+                error!(tool = tool.name, raw, "invalid output after repair: {err}");
+                error = err;
+            }
+        }
+    }
+    Err(Error::Llm(error))
+}
+
+fn build_request<'a>(
+    config: &Config,
+    feature: &FeatureConfig,
+    default_max_tokens: u64,
+    prompt: &'a str,
+    tool: Option<&'a Tool>,
+    cancel: &'a CancelToken,
+) -> Request<'a> {
+    Request {
+        max_tokens:  feature.max_tokens.unwrap_or(default_max_tokens),
+        temperature: config.temperature_for(feature),
+        prompt,
+        tool,
+        cancel,
+    }
+}
+
+/// Runs `request` against the feature's model chain. With `on_partial` the
+/// call is streamed and the callback gets the text accumulated so far.
+async fn with_fallback(
+    config: &Config,
+    feature: &FeatureConfig,
+    request: &Request<'_>,
+    mut on_partial: Option<&mut OnDelta<'_>>,
+) -> error::Result<String> {
+    if usage::over_budget(config) {
+        warn!("monthly token budget exhausted — skipping call");
+        return Err(Error::BudgetExceeded);
+    }
+
+    let provider = provider(config);
+    let models   = config.models_for(feature);
+
+    for model in &models {
+        if request.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let result = match on_partial.as_deref_mut() {
+            Some(cb) => {
+                let mut acc = String::new();
+                provider
+                    .send_stream(model, request, &mut |delta: &str| {
+                        acc.push_str(delta);
+                        cb(&acc);
+                    })
+                    .await
+            }
+            None => provider.send(model, request).await,
+        };
+
+        match result {
+            Ok(completion) => {
+                usage::record(model, completion.usage);
+                return Ok(completion.text);
+            }
+            Err(SendError::Unavailable(code)) => {
+                warn!(provider = provider.name(), model, "model unavailable ({code}), trying next model");
+            }
+            Err(SendError::Failed(err)) => return Err(err),
+            Err(SendError::Cancelled) => {
+                debug!(provider = provider.name(), "cancelled — track changed");
+                return Err(Error::Cancelled);
+            }
+        }
+    }
+    error!(provider = provider.name(), "no model available in {models:?}");
+    Err(Error::Llm(format!("aucun modèle disponible parmi {models:?}")))
+}
+
+/// Parses tool arguments and checks them against the schema's top level:
+/// every `required` property present, of the declared type, and non-empty
+/// for strings. Gateways without tool support answer in plain text, so
+/// fenced JSON is accepted too.
+fn parse_tool_output(raw: &str, tool: &Tool) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value = serde_json::from_str(strip_code_fences(raw))
+        .map_err(|e| format!("JSON invalide : {e}"))?;
+
+    let required   = tool.schema["required"].as_array().cloned().unwrap_or_default();
+    let properties = &tool.schema["properties"];
+
+    for key in required.iter().filter_map(|k| k.as_str()) {
+        let field = &value[key];
+        let ok = match properties[key]["type"].as_str() {
+            Some("string")  => field.as_str().is_some_and(|s| !s.trim().is_empty()),
+            Some("array")   => field.is_array(),
+            Some("object")  => field.is_object(),
+            Some("integer") => field.is_i64() || field.is_u64(),
+            Some("number")  => field.is_number(),
+            Some("boolean") => field.is_boolean(),
+            _               => !field.is_null(),
+        };
+        if !ok {
+            return Err(format!("champ `{key}` manquant ou invalide"));
+        }
+    }
+    Ok(value)
+}
+
+/// Decodes the value of a JSON string field from a possibly truncated JSON
+/// document, so a streamed `{"interpretation": "…` can be shown while it is
+/// still being written. Returns `None` until the value has started.
+pub fn partial_json_string(raw: &str, field: &str) -> Option<String> {
+    let key   = format!("\"{field}\"");
+    let after = &raw[raw.find(&key)? + key.len()..];
+    let after = after.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+
+    let mut out   = String::new();
+    let mut chars = after.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = (hex.len() == 4).then(|| u32::from_str_radix(&hex, 16).ok()).flatten();
+                    match code.and_then(char::from_u32) {
+                        Some(ch) => out.push(ch),
+                        None => break, // truncated or surrogate pair — wait for more
+                    }
+                }
+                Some(other) => out.push(other),
+                None => break,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// Removes ```json / ``` fences from a model response, exactly once each side.
+fn strip_code_fences(s: &str) -> &str {
+    let s = s.trim();
+    let s = s.strip_prefix("```json").or_else(|| s.strip_prefix("```")).unwrap_or(s);
+    let s = s.strip_suffix("```").unwrap_or(s);
+    s.trim()
+}
+
+/// Name of the language as used inside the (French) prompts. Unknown codes
+/// are passed through verbatim — Claude understands most of them as-is.
+pub fn language_name(code: &str) -> &str {
+    match code {
+        "fr" => "français",
+        "en" => "anglais",
+        "es" => "espagnol",
+        "de" => "allemand",
+        "it" => "italien",
+        "pt" => "portugais",
+        "nl" => "néerlandais",
+        "ja" => "japonais",
+        "ko" => "coréen",
+        "zh" => "chinois",
+        "ru" => "russe",
+        "ar" => "arabe",
+        other => other,
+    }
+}
@@ -0,0 +1,542 @@
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::cache;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::genius;
+use crate::insights::{self, Source};
+use crate::itunes;
+use crate::llm::{self, language_name};
+use crate::moods;
+use crate::musixmatch;
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+use crate::vault;
+use crate::webhook;
+
+/// LRCLIB asks clients to identify themselves.
+const USER_AGENT: &str = "enhanced-music/0.1 (https://github.com/Fendry02/enhanced-music)";
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// lrclib.net: free, no key, and often has time-synced lyrics.
+    Lrclib,
+    /// Official API, no scraping; needs `musixmatch_key` and is skipped without it.
+    Musixmatch,
+    /// Scraped from the Genius song page; needs `genius_token` for the search.
+    Genius,
+}
+
+pub struct Lyrics {
+    /// Plain text, one line per lyric line, blank lines between sections.
+    pub text: String,
+    /// LRC (`[mm:ss.xx] line`), when the provider has timings.
+    pub synced: Option<String>,
+    pub provider: Provider,
+}
+
+/// Tries `[lyrics_sources] providers` in order and returns the first hit.
+pub async fn fetch(config: &Config, title: &str, artist: &str, album: &str) -> Result<Lyrics> {
+    // A remaster has the original's lyrics
+    let searched = normalize::title(title);
+    for &provider in &config.lyrics_sources.providers {
+        let found = match provider {
+            Provider::Lrclib     => lrclib(title, &searched, artist, album).await,
+            Provider::Musixmatch => from_musixmatch(&config.api.musixmatch_key, &searched, artist).await,
+            Provider::Genius     => from_genius(&config.api.genius_token, &searched, artist).await,
+        };
+        match found {
+            Ok(Some(lyrics)) => return Ok(lyrics),
+            Ok(None) => debug!(?provider, "no lyrics"),
+            // Offline mode stops the whole chain; other failures fall through
+            Err(Error::Offline) => return Err(Error::Offline),
+            Err(e) => debug!(?provider, "lyrics lookup failed: {e}"),
+        }
+    }
+    Err(Error::NotFound("Paroles introuvables".into()))
+}
+
+async fn from_genius(token: &str, title: &str, artist: &str) -> Result<Option<Lyrics>> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let song = genius::search_song(token, title, artist).await?;
+    let url  = song["url"].as_str().unwrap_or_default();
+    Ok(genius::fetch_lyrics(url).await.map(|text| Lyrics { text, synced: None, provider: Provider::Genius }))
+}
+
+async fn from_musixmatch(key: &str, title: &str, artist: &str) -> Result<Option<Lyrics>> {
+    if key.is_empty() {
+        return Ok(None);
+    }
+    let found = musixmatch::lyrics(key, title, artist).await?;
+    Ok(found.map(|f| Lyrics { text: f.text, synced: f.synced, provider: Provider::Musixmatch }))
+}
+
+async fn lrclib_get(url: &str) -> Result<serde_json::Value> {
+    net::get_json("lrclib", || http().get(url).header("User-Agent", USER_AGENT)).await
+}
+
+/// Exact signature match first, then a looser search on the normalized
+/// title, since Music's album names often differ from LRCLIB's.
+async fn lrclib(title: &str, searched: &str, artist: &str, album: &str) -> Result<Option<Lyrics>> {
+    let exact = format!(
+        "https://lrclib.net/api/get?track_name={}&artist_name={}&album_name={}",
+        url_encode(title),
+        url_encode(artist),
+        url_encode(album)
+    );
+    let hit = match lrclib_get(&exact).await {
+        Ok(hit) => hit,
+        Err(Error::Http { status: 404, .. }) => {
+            let search = format!(
+                "https://lrclib.net/api/search?track_name={}&artist_name={}",
+                url_encode(searched),
+                url_encode(artist)
+            );
+            match lrclib_get(&search).await?.get(0) {
+                Some(hit) => hit.clone(),
+                None => return Ok(None),
+            }
+        }
+        Err(e) => return Err(e),
+    };
+
+    let Some(text) = hit["plainLyrics"].as_str().filter(|t| !t.trim().is_empty()) else {
+        return Ok(None);
+    };
+    Ok(Some(Lyrics {
+        text:     text.trim().to_string(),
+        synced:   hit["syncedLyrics"].as_str().filter(|s| !s.trim().is_empty()).map(str::to_string),
+        provider: Provider::Lrclib,
+    }))
+}
+
+/// A complete LRC file: ID tags, then the synced lines as LRCLIB sends them.
+pub fn to_lrc(title: &str, artist: &str, album: &str, synced: &str) -> String {
+    let mut lrc = format!("[ti:{title}]\n[ar:{artist}]\n");
+    if !album.is_empty() {
+        lrc.push_str(&format!("[al:{album}]\n"));
+    }
+    lrc.push_str("[re:Enhanced Music]\n");
+    lrc.push_str(synced.trim_end());
+    lrc.push('\n');
+    lrc
+}
+
+/// `Artist - Title.lrc`, with characters Finder or the shell dislike replaced.
+pub fn lrc_file_name(title: &str, artist: &str) -> String {
+    let name: String = format!("{artist} - {title}")
+        .chars()
+        .map(|c| if matches!(c, '/' | ':' | '\\' | '\0') { '_' } else { c })
+        .collect();
+    format!("{}.lrc", name.trim_start_matches('.'))
+}
+
+#[derive(Serialize, Clone)]
+pub struct SyncedLine {
+    pub time_ms: u64,
+    pub text: String,
+}
+
+/// `[mm:ss.xx]` → milliseconds; `None` for ID tags like `[ar:…]`.
+fn timestamp_ms(tag: &str) -> Option<u64> {
+    let (min, sec) = tag.split_once(':')?;
+    let min: u64 = min.trim().parse().ok()?;
+    let sec: f64 = sec.trim().parse().ok()?;
+    Some(min * 60_000 + (sec * 1000.0).round() as u64)
+}
+
+/// Timed lines of an LRC text, sorted by time. A line may carry several
+/// timestamps (`[00:12.00][01:40.50]Chorus`) and is repeated for each.
+pub fn parse_lrc(lrc: &str) -> Vec<SyncedLine> {
+    let mut lines = Vec::new();
+    for raw in lrc.lines() {
+        let mut rest  = raw.trim();
+        let mut times = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            let Some(ms) = timestamp_ms(tag.0) else { break };
+            times.push(ms);
+            rest = tag.1;
+        }
+        for time_ms in times {
+            lines.push(SyncedLine { time_ms, text: rest.trim().to_string() });
+        }
+    }
+    lines.sort_by_key(|l| l.time_ms);
+    lines
+}
+
+// ─── Full text ────────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct LyricsText {
+    pub text: String,
+    pub provider: Provider,
+    /// Whether time-synced lyrics exist for the track.
+    pub synced: bool,
+}
+
+/// Full lyrics, from the first `[lyrics_sources]` provider that has them.
+#[tracing::instrument(skip(config))]
+pub async fn text(config: &Config, title: &str, artist: &str, album: &str) -> Result<LyricsText> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+
+    let found = fetch(config, title, artist, album).await?;
+    Ok(LyricsText { text: found.text, provider: found.provider, synced: found.synced.is_some() })
+}
+
+// ─── LRC export ───────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct LrcExport {
+    /// Paths written.
+    pub written: Vec<String>,
+    /// Tracks skipped for lack of synced lyrics.
+    pub missing: Vec<String>,
+}
+
+fn default_lrc_dir() -> std::path::PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    std::path::PathBuf::from(home).join("Music").join("Lyrics")
+}
+
+/// Writes synced lyrics as `.lrc` files. `path` is a directory
+/// (`~/Music/Lyrics` by default), or a file name ending in `.lrc` for a single
+/// track. With `whole_album`, every track of the album found on iTunes is
+/// exported next to each other.
+#[tracing::instrument(skip(config))]
+pub async fn export_lrc(
+    config: &Config,
+    title: &str,
+    artist: &str,
+    album: &str,
+    path: Option<&str>,
+    whole_album: bool,
+) -> Result<LrcExport> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+
+    let target      = path.map(std::path::PathBuf::from).unwrap_or_else(default_lrc_dir);
+    let single_file = !whole_album && target.extension().is_some_and(|e| e == "lrc");
+    let dir = if single_file {
+        target.parent().map(|p| p.to_path_buf()).unwrap_or_default()
+    } else {
+        target.clone()
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| Error::Io(e.to_string()))?;
+
+    let titles = if whole_album {
+        let collection = itunes::album(artist, album, false).await?;
+        match collection["collectionId"].as_i64() {
+            Some(id) => itunes::tracklist(id).await?,
+            None => vec![title.to_string()],
+        }
+    } else {
+        vec![title.to_string()]
+    };
+
+    let mut report = LrcExport { written: Vec::new(), missing: Vec::new() };
+    for t in &titles {
+        let synced = match fetch(config, t, artist, album).await {
+            Ok(found) => found.synced,
+            Err(Error::Offline) => return Err(Error::Offline),
+            Err(_) => None,
+        };
+        let Some(synced) = synced else {
+            report.missing.push(t.clone());
+            continue;
+        };
+        let file = if single_file { target.clone() } else { dir.join(lrc_file_name(t, artist)) };
+        std::fs::write(&file, to_lrc(t, artist, album, &synced)).map_err(|e| Error::Io(e.to_string()))?;
+        info!(path = %file.display(), "LRC written");
+        report.written.push(file.display().to_string());
+    }
+
+    if report.written.is_empty() {
+        let what = if whole_album { "cet album" } else { "ce morceau" };
+        return Err(Error::NotFound(format!("Pas de paroles synchronisées pour {what}")));
+    }
+    Ok(report)
+}
+
+// ─── Lyrics analysis ──────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LyricsAnalysis {
+    pub interpretation: String,
+    /// A few mood and theme tags, lowercase, in the configured language;
+    /// also kept for [`crate::moods::tracks`]. Empty in analyses cached
+    /// before tags existed.
+    #[serde(default)]
+    pub moods: Vec<String>,
+}
+
+fn analysis_key(config: &Config, title: &str, artist: &str) -> String {
+    format!("{artist}|||{title}|||{}", config.language())
+}
+
+/// The analysis already made for the track in the configured language, if
+/// any.
+pub fn cached_analysis(config: &Config, title: &str, artist: &str) -> Option<LyricsAnalysis> {
+    cache::read("lyrics_analysis", &analysis_key(config, title, artist))
+}
+
+/// Cached on disk per track and language, like the translation. `on_text`
+/// receives the interpretation decoded so far (not just the last delta)
+/// while the answer streams in, or all at once from the cache.
+#[tracing::instrument(skip(config, cancel, on_text))]
+pub async fn analysis(
+    config: &Config,
+    cancel: &CancelToken,
+    title: &str,
+    artist: &str,
+    album: &str,
+    mut on_text: Option<&mut llm::OnDelta<'_>>,
+) -> Result<LyricsAnalysis> {
+    if !config.features.lyrics {
+        return Err(Error::Disabled);
+    }
+    if let Some(cached) = cached_analysis(config, title, artist) {
+        if let Some(on_text) = on_text.as_mut() {
+            on_text(&cached.interpretation);
+        }
+        // Analyses made before the search existed get indexed as they're seen
+        insights::record(Source::LyricsAnalysis, title, artist, album, &cached.interpretation);
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if !config.has_keys() {
+        return Err(Error::MissingKeys);
+    }
+
+    // The lyrics come from the configured providers; Genius only adds its
+    // annotations, a bonus whose absence costs the richer prompt
+    let annotations = async {
+        let token = &config.api.genius_token;
+        let song  = genius::search_song(token, title, artist).await?;
+        genius::annotations(token, song["id"].as_i64().unwrap_or_default(), 8).await
+    };
+    let (lyrics, annotations) = tokio::join!(fetch(config, title, artist, album), annotations);
+    let lyrics = lyrics.ok().map(|l| l.text);
+    let annotations = annotations.unwrap_or_else(|e| {
+        match e {
+            Error::MissingKeys | Error::NotFound(_) => debug!("no annotations: {e}"),
+            e => warn!("annotations unavailable: {e}"),
+        }
+        Vec::new()
+    });
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+    let prompt = build_lyrics_prompt(title, artist, lyrics.as_deref(), &annotations, &config.language());
+
+    let mut shown = String::new();
+    let mut on_partial = |raw: &str| {
+        if let Some(text) = llm::partial_json_string(raw, "interpretation") {
+            if text != shown {
+                if let Some(on_text) = on_text.as_mut() {
+                    on_text(&text);
+                }
+                shown = text;
+            }
+        }
+    };
+    let extracted = llm::complete_json(
+        config,
+        &config.lyrics,
+        500,
+        &prompt,
+        &lyrics_tool(),
+        cancel,
+        Some(&mut on_partial),
+    )
+    .await?;
+
+    let analysis = LyricsAnalysis {
+        interpretation: extracted["interpretation"].as_str().unwrap_or("").to_string(),
+        moods:          moods::clean(&serde_json::from_value::<Vec<String>>(extracted["moods"].clone()).unwrap_or_default()),
+    };
+    if !analysis.interpretation.is_empty() {
+        cache::write("lyrics_analysis", &analysis_key(config, title, artist), &analysis);
+        moods::record(title, artist, &analysis.moods);
+        insights::record(Source::LyricsAnalysis, title, artist, album, &analysis.interpretation);
+        vault::lyrics_completed(config, title, artist, album, &analysis);
+    }
+    webhook::analysis_completed(config, "lyrics_analysis", serde_json::json!({ "title": title, "artist": artist }), &analysis);
+    Ok(analysis)
+}
+
+fn build_lyrics_prompt(
+    title: &str,
+    artist: &str,
+    lyrics: Option<&str>,
+    annotations: &[genius::Annotation],
+    language: &str,
+) -> String {
+    let lang  = language_name(language);
+    let intro = format!(
+        "Tu es un expert en musique et en analyse de textes. \
+         Pour le morceau \"{title}\" de {artist}"
+    );
+
+    let notes = if annotations.is_empty() {
+        String::new()
+    } else {
+        let list = annotations
+            .iter()
+            .map(|a| format!("- « {} » : {}", a.fragment, a.body))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "Voici les annotations les plus votées de la communauté Genius ; \
+             synthétise-en les éclairages les plus solides :\n{list}\n\n"
+        )
+    };
+
+    let body = match lyrics {
+        Some(lyr) => format!(
+            "{intro}, voici les paroles :\n\n{lyr}\n\n{notes}\
+             Basé sur ces paroles, explique en 3-4 phrases en {lang}"
+        ),
+        None if !notes.is_empty() => format!(
+            "{intro}.\n\n{notes}\
+             Explique en 3-4 phrases en {lang}"
+        ),
+        None => format!(
+            "{intro}, explique en 3-4 phrases en {lang} \
+             (en te basant sur tes connaissances)"
+        ),
+    };
+
+    format!(
+        "{body} : le thème principal, l'émotion portée, et ce que l'artiste \
+         cherche à exprimer. Sois précis et va au-delà du simple résumé. \
+         Donne aussi 2 à 4 tags d'humeur ou de thème en {lang}, un mot ou deux en minuscules \
+         (par exemple « mélancolique », « défiant », « rupture »).\n\n\
+         Réponds en appelant l'outil `lyrics_analysis`."
+    )
+}
+
+fn lyrics_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "lyrics_analysis",
+        description: "Enregistre l'interprétation du morceau",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "interpretation": {
+                    "type": "string",
+                    "description": "L'interprétation du morceau, 3-4 phrases"
+                },
+                "moods": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "2 à 4 tags d'humeur ou de thème, en minuscules"
+                }
+            },
+            "required": ["interpretation", "moods"]
+        }),
+    }
+}
+
+// ─── Lyrics translation ───────────────────────────────────────────────────────
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TranslatedLine {
+    pub original: String,
+    /// Empty for blank separator lines.
+    pub translation: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LyricsTranslation {
+    pub language: String,
+    pub lines: Vec<TranslatedLine>,
+}
+
+/// Line-by-line translation into `target_lang` (the configured language by
+/// default), cached per track and language.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn translation(
+    config: &Config,
+    cancel: &CancelToken,
+    title: &str,
+    artist: &str,
+    album: &str,
+    target_lang: Option<&str>,
+) -> Result<LyricsTranslation> {
+    if !config.features.lyrics_translation {
+        return Err(Error::Disabled);
+    }
+
+    let language  = target_lang.map(|l| l.trim().to_lowercase()).filter(|l| !l.is_empty()).unwrap_or_else(|| config.language());
+    let cache_key = format!("{artist}|||{title}|||{language}");
+    if let Some(cached) = cache::read::<LyricsTranslation>("lyrics_translation", &cache_key) {
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let lyrics = fetch(config, title, artist, album).await?;
+    if cancel.is_cancelled() {
+        debug!("track changed — dropping result");
+        return Err(Error::Cancelled);
+    }
+    let prompt = build_translation_prompt(title, artist, &lyrics.text, &language);
+    let value  = llm::complete_json(config, &config.lyrics_translation, 3000, &prompt, &translation_tool(), cancel, None).await?;
+
+    let lines: Vec<TranslatedLine> = serde_json::from_value(value["lines"].clone()).map_err(|e| Error::Llm(e.to_string()))?;
+    let translation = LyricsTranslation { language, lines };
+    cache::write("lyrics_translation", &cache_key, &translation);
+    Ok(translation)
+}
+
+fn build_translation_prompt(title: &str, artist: &str, lyrics: &str, language: &str) -> String {
+    let lang = language_name(language);
+    format!(
+        "Voici les paroles de \"{title}\" de {artist} :\n\n{lyrics}\n\n\
+         Traduis-les en {lang}, ligne par ligne, en restant fidèle au sens plutôt qu'à la rime. \
+         Garde chaque ligne d'origine telle quelle, dans l'ordre, y compris les lignes vides entre \
+         les couplets (traduction vide). Si une ligne est déjà en {lang}, recopie-la.\n\n\
+         Réponds en appelant l'outil `lyrics_translation`."
+    )
+}
+
+fn translation_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "lyrics_translation",
+        description: "Enregistre la traduction des paroles, ligne par ligne",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "lines": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "original":    { "type": "string", "description": "La ligne d'origine, inchangée" },
+                            "translation": { "type": "string" }
+                        },
+                        "required": ["original", "translation"]
+                    }
+                }
+            },
+            "required": ["lines"]
+        }),
+    }
+}
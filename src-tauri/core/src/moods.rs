@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::config;
+use crate::history;
+use crate::normalize::{fold, track_key};
+
+/// At most this many tags are kept per track; the model is asked for 2 to 4.
+const MAX_TAGS: usize = 5;
+
+/// One track's mood and theme tags, as the lyrics analysis left them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tagged {
+    title: String,
+    artist: String,
+    moods: Vec<String>,
+}
+
+/// `~/Library/Application Support/enhanced-music/moods.json`, keyed by
+/// folded artist and title.
+fn path() -> PathBuf {
+    config::data_dir().join("moods.json")
+}
+
+fn store() -> &'static Mutex<BTreeMap<String, Tagged>> {
+    static STORE: OnceLock<Mutex<BTreeMap<String, Tagged>>> = OnceLock::new();
+    STORE.get_or_init(|| {
+        let store = std::fs::read_to_string(path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).map_err(|e| warn!("corrupt mood tags, starting fresh: {e}")).ok())
+            .unwrap_or_default();
+        Mutex::new(store)
+    })
+}
+
+fn save(store: &BTreeMap<String, Tagged>) {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(store) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                error!(path = %path.display(), "cannot write mood tags: {e}");
+            }
+        }
+        Err(e) => error!("cannot serialize mood tags: {e}"),
+    }
+}
+
+/// Tags as stored: trimmed, lowercase, no duplicates, [`MAX_TAGS`] at most.
+pub fn clean(moods: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for mood in moods.iter().map(|m| m.trim().to_lowercase()).filter(|m| !m.is_empty()) {
+        if !out.iter().any(|o| fold(o) == fold(&mood)) {
+            out.push(mood);
+        }
+    }
+    out.truncate(MAX_TAGS);
+    out
+}
+
+/// Replaces the track's tags. No tags leaves the previous ones alone.
+pub fn record(title: &str, artist: &str, moods: &[String]) {
+    let moods = clean(moods);
+    if moods.is_empty() {
+        return;
+    }
+    let Ok(mut store) = store().lock() else { return };
+    store.insert(track_key(title, artist), Tagged { title: title.to_string(), artist: artist.to_string(), moods });
+    save(&store);
+}
+
+/// The track's tags, empty when it was never analysed.
+pub fn of(title: &str, artist: &str) -> Vec<String> {
+    store()
+        .lock()
+        .ok()
+        .and_then(|s| s.get(&track_key(title, artist)).map(|t| t.moods.clone()))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodTrack {
+    pub title: String,
+    pub artist: String,
+    pub moods: Vec<String>,
+    /// Times it appears in the listening history.
+    pub plays: usize,
+}
+
+/// Tracks tagged `mood` (case and accents aside), most played first.
+pub fn tracks(mood: &str) -> Vec<MoodTrack> {
+    let wanted = fold(mood);
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+    let tagged: Vec<Tagged> = match store().lock() {
+        Ok(store) => store.values().filter(|t| t.moods.iter().any(|m| fold(m) == wanted)).cloned().collect(),
+        Err(_) => return Vec::new(),
+    };
+
+    let plays = history::plays_by_track();
+    let mut tracks: Vec<MoodTrack> = tagged
+        .into_iter()
+        .map(|t| MoodTrack {
+            plays:  plays.get(&track_key(&t.title, &t.artist)).copied().unwrap_or(0),
+            title:  t.title,
+            artist: t.artist,
+            moods:  t.moods,
+        })
+        .collect();
+    tracks.sort_by(|a, b| b.plays.cmp(&a.plays).then_with(|| a.artist.cmp(&b.artist)).then_with(|| a.title.cmp(&b.title)));
+    tracks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_folds_duplicates_and_caps() {
+        let moods = ["  Mélancolique ", "melancolique", "", "Défiant", "a", "b", "c", "d"].map(String::from);
+        assert_eq!(clean(&moods), ["mélancolique", "défiant", "a", "b", "c"]);
+    }
+}
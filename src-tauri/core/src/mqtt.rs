@@ -0,0 +1,214 @@
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+use crate::config::{Config, MqttConfig};
+use crate::itunes;
+
+/// Whole connect-publish-disconnect exchange.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What goes out on `<prefix>/track`.
+#[derive(Serialize, Clone, PartialEq)]
+pub struct State {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub playing: bool,
+}
+
+/// Last state the broker accepted, so polling only publishes changes.
+static LAST: Mutex<Option<Option<State>>> = Mutex::new(None);
+
+/// Publishes the poller's view when it changed: `<prefix>/state` (`playing`,
+/// `paused`, `stopped`), `<prefix>/track` (JSON, or empty when stopped) and,
+/// on a new track, `<prefix>/artwork` (an iTunes image URL, or empty).
+/// Runs in the background; failures are only logged.
+pub fn update(config: &Config, state: Option<State>) {
+    let mqtt = &config.mqtt;
+    if mqtt.host.is_empty() {
+        return;
+    }
+    // Recorded once the broker has it, so a failed publish is tried again
+    // on the next poll
+    let previous = {
+        let Ok(last) = LAST.lock() else { return };
+        if last.as_ref() == Some(&state) {
+            return;
+        }
+        last.clone().flatten()
+    };
+    let new_track = match (&previous, &state) {
+        (Some(a), Some(b)) => a.title != b.title || a.artist != b.artist,
+        _ => true,
+    };
+
+    let mqtt = mqtt.clone();
+    tokio::spawn(async move {
+        let topic  = |name: &str| format!("{}/{name}", mqtt.topic_prefix.trim_end_matches('/'));
+        let status = match &state {
+            Some(s) if s.playing => "playing",
+            Some(_) => "paused",
+            None => "stopped",
+        };
+        let track  = state.as_ref().and_then(|s| serde_json::to_string(s).ok()).unwrap_or_default();
+        let mut messages = vec![(topic("state"), status.to_string()), (topic("track"), track)];
+        if new_track {
+            let artwork = match &state {
+                Some(s) => artwork_url(&s.title, &s.artist).await,
+                None => String::new(),
+            };
+            messages.push((topic("artwork"), artwork));
+        }
+        match tokio::time::timeout(TIMEOUT, publish(&mqtt, &messages)).await {
+            Ok(Ok(())) => {
+                debug!(status, "MQTT state published");
+                if let Ok(mut last) = LAST.lock() {
+                    *last = Some(state);
+                }
+            }
+            Ok(Err(e)) => warn!(host = mqtt.host, "MQTT publish failed: {e}"),
+            Err(_) => warn!(host = mqtt.host, "MQTT broker timed out"),
+        }
+    });
+}
+
+/// 600 px cover from the iTunes Search API; empty when not found or offline.
+async fn artwork_url(title: &str, artist: &str) -> String {
+    match itunes::song(title, artist).await {
+        Ok(hit) => hit["artworkUrl100"].as_str().unwrap_or("").replace("100x100bb", "600x600bb"),
+        Err(e) => {
+            debug!(title, artist, "no artwork URL for MQTT: {e}");
+            String::new()
+        }
+    }
+}
+
+// ─── MQTT 3.1.1 ───────────────────────────────────────────────────────────────
+
+// Just enough of the protocol to publish at QoS 0, in the clear: a
+// connection per update needs no keep-alive handling or client event loop.
+
+fn remaining_length(mut len: usize, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+/// Length-prefixed UTF-8, cut to the 65535-byte limit at a character
+/// boundary.
+fn string(s: &str, out: &mut Vec<u8>) {
+    let mut end = s.len().min(u16::MAX as usize);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let bytes = &s.as_bytes()[..end];
+    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    remaining_length(body.len(), &mut out);
+    out.extend_from_slice(body);
+    out
+}
+
+fn connect_packet(config: &MqttConfig) -> Vec<u8> {
+    let mut flags = 0x02; // clean session
+    if !config.username.is_empty() {
+        flags |= 0x80;
+        if !config.password.is_empty() {
+            flags |= 0x40;
+        }
+    }
+    let mut body = Vec::new();
+    string("MQTT", &mut body);
+    body.push(4); // protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&30u16.to_be_bytes()); // keep-alive, seconds
+    string(&config.client_id, &mut body);
+    if flags & 0x80 != 0 {
+        string(&config.username, &mut body);
+    }
+    if flags & 0x40 != 0 {
+        string(&config.password, &mut body);
+    }
+    packet(0x10, &body)
+}
+
+fn publish_packet(topic: &str, payload: &str, retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    string(topic, &mut body);
+    body.extend_from_slice(payload.as_bytes());
+    packet(0x30 | retain as u8, &body)
+}
+
+async fn publish(config: &MqttConfig, messages: &[(String, String)]) -> io::Result<()> {
+    let mut stream = TcpStream::connect((config.host.as_str(), config.port)).await?;
+    stream.write_all(&connect_packet(config)).await?;
+
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack).await?;
+    if connack[0] != 0x20 || connack[3] != 0 {
+        let reason = match connack[3] {
+            4 | 5 => "identifiants refusés",
+            _ => "connexion refusée",
+        };
+        return Err(io::Error::other(format!("{reason} (code {})", connack[3])));
+    }
+
+    for (topic, payload) in messages {
+        stream.write_all(&publish_packet(topic, payload, config.retain)).await?;
+    }
+    stream.write_all(&[0xE0, 0x00]).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_length_matches_the_spec_examples() {
+        for (len, bytes) in [
+            (0, &[0x00][..]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (16_383, &[0xFF, 0x7F]),
+            (16_384, &[0x80, 0x80, 0x01]),
+            (2_097_151, &[0xFF, 0xFF, 0x7F]),
+        ] {
+            let mut out = Vec::new();
+            remaining_length(len, &mut out);
+            assert_eq!(out, bytes, "{len}");
+        }
+    }
+
+    #[test]
+    fn encodes_connect_and_publish_packets() {
+        let config = MqttConfig { client_id: "em".into(), username: "u".into(), password: "p".into(), ..MqttConfig::default() };
+        assert_eq!(
+            connect_packet(&config),
+            [&[0x10, 20, 0, 4][..], b"MQTT", &[4, 0xC2, 0, 30, 0, 2], b"em", &[0, 1], b"u", &[0, 1], b"p"].concat()
+        );
+        assert_eq!(publish_packet("a/b", "hi", true), [&[0x31, 7, 0, 3][..], b"a/b", b"hi"].concat());
+
+        let mut out = Vec::new();
+        string(&"é".repeat(40_000), &mut out);
+        assert_eq!((&out[..2], out.len()), (&65_534u16.to_be_bytes()[..], 65_536));
+    }
+}
@@ -0,0 +1,685 @@
+use tracing::warn;
+
+use crate::error::{Error, Result};
+use crate::scripting_bridge as bridge;
+
+/// Runs an AppleScript with `osascript`, passing `args` to its `on run argv`
+/// handler, and returns its trimmed output. Frequent calls (polling, player
+/// controls, the current track's love, stats and file) go through [`bridge`]
+/// instead, which stays in-process. What stays here searches or edits the
+/// library and queue (`whose` filters, `play`, playlists): one event for
+/// Music to evaluate, where key-value coding would fetch every track.
+pub async fn run(script: &str, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| Error::Script(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        warn!("osascript failed: {stderr}");
+        // errAEEventNotPermitted, whatever the language of the message
+        if stderr.contains("(-1743)") {
+            return Err(Error::Permission);
+        }
+        return Err(Error::Script(stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// ─── Love / dislike ───────────────────────────────────────────────────────────
+
+fn nothing_playing() -> Error {
+    Error::NotFound("Aucune lecture en cours".into())
+}
+
+#[derive(serde::Serialize)]
+pub struct LoveState {
+    loved: bool,
+    disliked: bool,
+}
+
+/// Love and dislike state of the current track, as shown in Music.
+pub async fn get_loved() -> Result<LoveState> {
+    let love = bridge::call(bridge::love).await?.ok_or_else(nothing_playing)?;
+    Ok(LoveState { loved: love.loved, disliked: love.disliked })
+}
+
+/// Loves (or un-loves) the current track. Loving clears a dislike, as in Music.
+#[tracing::instrument]
+pub async fn set_loved(loved: bool) -> Result<LoveState> {
+    if !bridge::call(move || bridge::set_loved(loved)).await? {
+        return Err(nothing_playing());
+    }
+    get_loved().await
+}
+
+/// Dislikes (or un-dislikes) the current track; a dislike clears the love.
+#[tracing::instrument]
+pub async fn set_disliked(disliked: bool) -> Result<LoveState> {
+    if !bridge::call(move || bridge::set_disliked(disliked)).await? {
+        return Err(nothing_playing());
+    }
+    get_loved().await
+}
+
+// ─── Rating ───────────────────────────────────────────────────────────────────
+
+/// Music's 0–100 rating of the current track; each star is 20.
+pub async fn get_rating() -> Result<u8> {
+    bridge::call(bridge::rating).await?.ok_or_else(nothing_playing)
+}
+
+/// Sets the rating (clamped to 0–100; Music shows it rounded to half stars)
+/// and returns the one Music kept.
+#[tracing::instrument]
+pub async fn set_rating(rating: u8) -> Result<u8> {
+    if !bridge::call(move || bridge::set_rating(rating)).await? {
+        return Err(nothing_playing());
+    }
+    get_rating().await
+}
+
+// ─── Play stats ───────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct TrackStats {
+    play_count: u32,
+    skip_count: u32,
+    /// Unix seconds; `None` for a track never played through.
+    last_played: Option<u64>,
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Music's play and skip counts and last played date for the current track.
+pub async fn get_track_stats() -> Result<TrackStats> {
+    let stats = bridge::call(bridge::play_stats).await?.ok_or_else(nothing_playing)?;
+    Ok(TrackStats { play_count: stats.played, skip_count: stats.skipped, last_played: stats.played_at })
+}
+
+// ─── File info ────────────────────────────────────────────────────────────────
+
+#[derive(Default, serde::Serialize)]
+pub struct FileInfo {
+    /// POSIX path; `None` for a streamed track.
+    location: Option<String>,
+    /// Music's description, e.g. "Apple Lossless audio file".
+    kind: String,
+    /// Core Audio format ID when the file could be inspected ("alac", "aac",
+    /// "flac", "lpcm", "mp3"…).
+    codec: Option<String>,
+    bitrate_kbps: Option<u32>,
+    sample_rate_hz: Option<u32>,
+    /// Only known for a local, lossless or PCM file.
+    bit_depth: Option<u8>,
+}
+
+impl FileInfo {
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+}
+
+/// What `afinfo` says about a file: its format ID and source bit depth,
+/// from a line like `Data format: 2 ch, 44100 Hz, 'alac' (0x00000001) from
+/// 16-bit source, 4096 frames/packet`.
+async fn inspect_file(path: &str) -> (Option<String>, Option<u8>) {
+    let Ok(output) = tokio::process::Command::new("afinfo").arg(path).output().await else {
+        return (None, None);
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    let Some(line) = text.lines().find(|l| l.trim_start().starts_with("Data format:")) else {
+        return (None, None);
+    };
+    let codec = line
+        .split('\'')
+        .nth(1)
+        .map(|id| if id.trim() == ".mp3" { "mp3".into() } else { id.trim().to_string() });
+    let bit_depth = line
+        .split([',', ' '])
+        .find_map(|word| word.strip_suffix("-bit"))
+        .and_then(|bits| bits.parse().ok());
+    (codec, bit_depth)
+}
+
+/// Format details of the current track, from Music and, for a local file,
+/// `afinfo`.
+pub async fn get_track_file_info() -> Result<FileInfo> {
+    let details  = bridge::call(bridge::file_details).await?.ok_or_else(nothing_playing)?;
+    let positive = |v: u32| Some(v).filter(|&v| v > 0);
+    let mut info = FileInfo {
+        location:       details.location,
+        kind:           details.kind,
+        bitrate_kbps:   positive(details.bit_rate),
+        sample_rate_hz: positive(details.sample_rate),
+        ..FileInfo::default()
+    };
+    if let Some(path) = &info.location {
+        (info.codec, info.bit_depth) = inspect_file(path).await;
+    }
+    Ok(info)
+}
+
+// ─── Audio quality ────────────────────────────────────────────────────────────
+
+/// The badge Music would show. Only local files can be told apart: Music's
+/// scripting interface says nothing about the quality of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioQuality {
+    Lossless,
+    /// Apple's definition: lossless above 48 kHz.
+    HiResLossless,
+    Atmos,
+}
+
+impl AudioQuality {
+    /// From the track's `kind` ("Apple Lossless audio file", "AIFF audio
+    /// file"…) and sample rate in Hz.
+    pub fn detect(kind: &str, sample_rate: u32) -> Option<Self> {
+        let kind = kind.to_lowercase();
+        if kind.contains("atmos") || kind.contains("dolby") {
+            return Some(Self::Atmos);
+        }
+        let lossless = ["lossless", "flac", "aiff", "wav"].iter().any(|k| kind.contains(k));
+        match (lossless, sample_rate > 48_000) {
+            (true, true)  => Some(Self::HiResLossless),
+            (true, false) => Some(Self::Lossless),
+            _ => None,
+        }
+    }
+}
+
+// ─── Playback ─────────────────────────────────────────────────────────────────
+
+/// Scratch playlist albums and queued tracks are played from, so Music plays
+/// them in order — playing a library track would continue with the library's
+/// order instead.
+const QUEUE_PLAYLIST: &str = "Enhanced Music";
+
+/// Plays an album from the library from its first track, in disc/track
+/// order. `artist` matches the track or album artist, loosely.
+#[tracing::instrument]
+pub async fn play_album(artist: &str, album: &str) -> Result<()> {
+    let listing = run(
+        r#"on run argv
+            set {al, ar} to argv
+            set out to ""
+            tell application "Music"
+                repeat with t in (every track of library playlist 1 whose album is al)
+                    if (artist of t contains ar) or (album artist of t contains ar) then
+                        set out to out & (persistent ID of t) & "|||" & (disc number of t) & "|||" & (track number of t) & linefeed
+                    end if
+                end repeat
+            end tell
+            return out
+        end run"#,
+        &[album, artist],
+    )
+    .await?;
+
+    let mut tracks: Vec<(u32, u32, &str)> = listing
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split("|||");
+            let id = parts.next()?;
+            Some((parts.next()?.parse().unwrap_or(1), parts.next()?.parse().unwrap_or(0), id))
+        })
+        .collect();
+    if tracks.is_empty() {
+        return Err(Error::NotFound("Album absent de la bibliothèque".into()));
+    }
+    tracks.sort();
+
+    let mut args = vec![QUEUE_PLAYLIST];
+    args.extend(tracks.iter().map(|t| t.2));
+    run(
+        r#"on run argv
+            set plName to item 1 of argv
+            tell application "Music"
+                if not (exists user playlist plName) then make new user playlist with properties {name:plName}
+                set pl to user playlist plName
+                delete every track of pl
+                repeat with pid in (rest of argv)
+                    duplicate (first track of library playlist 1 whose persistent ID is (pid as text)) to pl
+                end repeat
+                play pl
+            end tell
+        end run"#,
+        &args,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Starts a playlist by name.
+#[tracing::instrument]
+pub async fn play_playlist(name: &str) -> Result<()> {
+    let out = run(
+        r#"on run argv
+            tell application "Music"
+                if not (exists playlist (item 1 of argv)) then return ""
+                play playlist (item 1 of argv)
+                return "ok"
+            end tell
+        end run"#,
+        &[name],
+    )
+    .await?;
+    if out.is_empty() {
+        return Err(Error::NotFound(format!("Playlist « {name} » introuvable")));
+    }
+    Ok(())
+}
+
+/// Sets Music's own volume (0–100), not the system's.
+pub async fn set_volume(volume: u8) -> Result<()> {
+    bridge::call(move || bridge::set_volume(volume)).await
+}
+
+// ─── Up Next ──────────────────────────────────────────────────────────────────
+
+// Music's own Up Next isn't scriptable, so the queue here is what follows the
+// current track in the playlist it plays from; queueing moves playback onto
+// `QUEUE_PLAYLIST`.
+
+/// How many upcoming tracks are listed, and carried over when queueing.
+const UP_NEXT_LIMIT: usize = 25;
+
+/// A library track, addressed by its persistent ID.
+#[derive(serde::Serialize, Clone)]
+pub struct LibraryTrack {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// Part of a compilation: album lookups then credit Various Artists.
+    pub compilation: bool,
+}
+
+/// Parses `id|||title|||artist|||album|||compilation` lines.
+fn parse_tracks(out: &str) -> Vec<LibraryTrack> {
+    out.lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.splitn(5, "|||").collect();
+            (parts.len() == 5).then(|| LibraryTrack {
+                id:          parts[0].to_string(),
+                title:       parts[1].to_string(),
+                artist:      parts[2].to_string(),
+                album:       parts[3].to_string(),
+                compilation: parts[4] == "true",
+            })
+        })
+        .collect()
+}
+
+/// The tracks Music will play after the current one, up to
+/// [`UP_NEXT_LIMIT`]. Empty when nothing is playing or the source has no
+/// order (a radio station, for one).
+pub async fn get_up_next() -> Result<Vec<LibraryTrack>> {
+    let out = run(
+        r#"on run argv
+            set lim to (item 1 of argv) as integer
+            set out to ""
+            tell application "Music"
+                if player state is stopped then return ""
+                try
+                    set src to current playlist
+                    set idx to index of current track
+                    set n to count tracks of src
+                on error
+                    return ""
+                end try
+                repeat with i from (idx + 1) to n
+                    if i > idx + lim then exit repeat
+                    set t to track i of src
+                    set out to out & (persistent ID of t) & "|||" & (name of t) & "|||" & (artist of t) & "|||" & (album of t) & "|||" & (compilation of t) & linefeed
+                end repeat
+            end tell
+            return out
+        end run"#,
+        &[&UP_NEXT_LIMIT.to_string()],
+    )
+    .await?;
+    Ok(parse_tracks(&out))
+}
+
+/// Splices a library track into the queue: right after the current track
+/// (`mode` "next") or at its end ("last"). Outside [`QUEUE_PLAYLIST`], the
+/// playlist is refilled with the current track, the new one and what was
+/// coming next, then resumed where playback was.
+async fn enqueue(id: &str, mode: &str) -> Result<()> {
+    let out = run(
+        r#"on run argv
+            set {plName, mode, pid, lim} to argv
+            tell application "Music"
+                if player state is stopped then return ""
+                set newTrack to first track of library playlist 1 whose persistent ID is pid
+                if not (exists user playlist plName) then make new user playlist with properties {name:plName}
+                set pl to user playlist plName
+                set cur to current track
+                set idx to index of cur
+                set src to current playlist
+
+                if (persistent ID of src) is (persistent ID of pl) then
+                    set n to count tracks of pl
+                    if mode is "last" or idx = n then
+                        duplicate newTrack to pl
+                        return "ok"
+                    end if
+                    set restIds to persistent ID of tracks (idx + 1) thru n of pl
+                    delete tracks (idx + 1) thru n of pl
+                    duplicate newTrack to pl
+                    repeat with rid in restIds
+                        duplicate (first track of library playlist 1 whose persistent ID is (rid as text)) to pl
+                    end repeat
+                    return "ok"
+                end if
+
+                set pos to player position
+                set rest to {}
+                try
+                    set n to count tracks of src
+                    repeat with i from (idx + 1) to n
+                        if i > idx + (lim as integer) then exit repeat
+                        set end of rest to track i of src
+                    end repeat
+                end try
+                delete every track of pl
+                duplicate cur to pl
+                duplicate newTrack to pl
+                repeat with t in rest
+                    try
+                        duplicate t to pl
+                    end try
+                end repeat
+                play pl
+                set player position to pos
+                return "ok"
+            end tell
+        end run"#,
+        &[QUEUE_PLAYLIST, mode, id, &UP_NEXT_LIMIT.to_string()],
+    )
+    .await?;
+    if out.is_empty() {
+        return Err(nothing_playing());
+    }
+    Ok(())
+}
+
+/// Queues a library track to play right after the current one.
+#[tracing::instrument]
+pub async fn play_next(id: &str) -> Result<Vec<LibraryTrack>> {
+    enqueue(id, "next").await?;
+    get_up_next().await
+}
+
+/// Queues a library track at the end of the queue.
+#[tracing::instrument]
+pub async fn add_to_up_next(id: &str) -> Result<Vec<LibraryTrack>> {
+    enqueue(id, "last").await?;
+    get_up_next().await
+}
+
+// ─── Library search ───────────────────────────────────────────────────────────
+
+const SEARCH_LIMIT: usize = 20;
+
+/// Searches the library the way Music's search field does (title, artist,
+/// album…), returning at most [`SEARCH_LIMIT`] tracks.
+#[tracing::instrument]
+pub async fn search_library(query: &str) -> Result<Vec<LibraryTrack>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let out = run(
+        r#"on run argv
+            set {q, lim} to argv
+            set out to ""
+            tell application "Music"
+                set found to search library playlist 1 for q
+                repeat with i from 1 to count found
+                    if i > (lim as integer) then exit repeat
+                    set t to item i of found
+                    set out to out & (persistent ID of t) & "|||" & (name of t) & "|||" & (artist of t) & "|||" & (album of t) & "|||" & (compilation of t) & linefeed
+                end repeat
+            end tell
+            return out
+        end run"#,
+        &[query, &SEARCH_LIMIT.to_string()],
+    )
+    .await?;
+    Ok(parse_tracks(&out))
+}
+
+/// Plays a library track by persistent ID, as returned by [`search_library`].
+#[tracing::instrument]
+pub async fn play_track(id: &str) -> Result<()> {
+    let out = run(
+        r#"on run argv
+            tell application "Music"
+                set found to (every track of library playlist 1 whose persistent ID is (item 1 of argv))
+                if found is {} then return ""
+                play item 1 of found
+                return "ok"
+            end tell
+        end run"#,
+        &[id],
+    )
+    .await?;
+    if out.is_empty() {
+        return Err(Error::NotFound("Morceau absent de la bibliothèque".into()));
+    }
+    Ok(())
+}
+
+// ─── Recently played ──────────────────────────────────────────────────────────
+
+/// How far back Music's `played date` is searched.
+const RECENT_WINDOW_DAYS: u32 = 7;
+
+#[derive(serde::Serialize)]
+pub struct RecentTrack {
+    #[serde(flatten)]
+    pub track: LibraryTrack,
+    /// Unix seconds.
+    pub played_at: u64,
+}
+
+/// The last `limit` (default 10) tracks played, newest first, from Music's
+/// own `played date` — so plays made outside the app count too.
+#[tracing::instrument]
+pub async fn get_recently_played(limit: Option<usize>) -> Result<Vec<RecentTrack>> {
+    let out = run(
+        r#"on run argv
+            set cutoff to (current date) - ((item 1 of argv) as integer) * days
+            set out to ""
+            tell application "Music"
+                repeat with t in (every track of library playlist 1 whose played date > cutoff)
+                    set ago to ((current date) - (played date of t)) as integer
+                    set out to out & ago & "|||" & (persistent ID of t) & "|||" & (name of t) & "|||" & (artist of t) & "|||" & (album of t) & "|||" & (compilation of t) & linefeed
+                end repeat
+            end tell
+            return out
+        end run"#,
+        &[&RECENT_WINDOW_DAYS.to_string()],
+    )
+    .await?;
+
+    let now = unix_now();
+    let mut recent: Vec<(u64, LibraryTrack)> = out
+        .lines()
+        .filter_map(|line| {
+            let (ago, rest) = line.split_once("|||")?;
+            let track = parse_tracks(rest).pop()?;
+            Some((ago.parse().ok()?, track))
+        })
+        .collect();
+    recent.sort_by_key(|(ago, _)| *ago);
+    Ok(recent
+        .into_iter()
+        .take(limit.unwrap_or(10))
+        .map(|(ago, track)| RecentTrack { track, played_at: now.saturating_sub(ago) })
+        .collect())
+}
+
+// ─── Playlists ────────────────────────────────────────────────────────────────
+
+/// A library track Music has played, as listed by [`played_tracks`].
+#[derive(serde::Serialize)]
+pub struct PlayedTrack {
+    #[serde(flatten)]
+    pub track: LibraryTrack,
+    pub genre: String,
+    /// 0 when Music doesn't know it.
+    pub year: u32,
+    /// Music's `played count`.
+    pub plays: u32,
+}
+
+/// Every library track played at least once, most played first. Properties
+/// are read for the whole library in one Apple Event each, which stays
+/// quick where a `repeat` over the tracks would take minutes.
+#[tracing::instrument]
+pub async fn played_tracks() -> Result<Vec<PlayedTrack>> {
+    let out = run(
+        r#"on run argv
+            set out to ""
+            tell application "Music"
+                set lib to library playlist 1
+                set {ids, names, artists, albums, compilations, genres, years, counts} to {persistent ID, name, artist, album, compilation, genre, year, played count} of every track of lib
+            end tell
+            repeat with i from 1 to count ids
+                if (item i of counts) > 0 then
+                    set out to out & (item i of counts) & "|||" & (item i of years) & "|||" & (item i of genres) & "|||" & (item i of ids) & "|||" & (item i of names) & "|||" & (item i of artists) & "|||" & (item i of albums) & "|||" & (item i of compilations) & linefeed
+                end if
+            end repeat
+            return out
+        end run"#,
+        &[],
+    )
+    .await?;
+
+    let mut tracks: Vec<PlayedTrack> = out
+        .lines()
+        .filter_map(|line| {
+            let (plays, rest) = line.split_once("|||")?;
+            let (year, rest)  = rest.split_once("|||")?;
+            let (genre, rest) = rest.split_once("|||")?;
+            let track = parse_tracks(rest).pop()?;
+            Some(PlayedTrack { track, genre: genre.to_string(), year: year.parse().unwrap_or(0), plays: plays.parse().ok()? })
+        })
+        .collect();
+    tracks.sort_by_key(|t| std::cmp::Reverse(t.plays));
+    Ok(tracks)
+}
+
+/// Creates a user playlist of library tracks, by persistent ID and in that
+/// order, and returns how many made it in. An existing playlist of the same
+/// name is left alone: Music allows both.
+#[tracing::instrument(skip(ids))]
+pub async fn create_playlist(name: &str, description: &str, ids: &[String]) -> Result<usize> {
+    let mut args = vec![name, description];
+    args.extend(ids.iter().map(String::as_str));
+    let out = run(
+        r#"on run argv
+            set {plName, plDescription} to {item 1 of argv, item 2 of argv}
+            set added to 0
+            tell application "Music"
+                set pl to make new user playlist with properties {name:plName}
+                try
+                    set description of pl to plDescription
+                end try
+                repeat with pid in (rest of rest of argv)
+                    try
+                        duplicate (first track of library playlist 1 whose persistent ID is (pid as text)) to pl
+                        set added to added + 1
+                    end try
+                end repeat
+            end tell
+            return added
+        end run"#,
+        &args,
+    )
+    .await?;
+    Ok(out.parse().unwrap_or(0))
+}
+
+// ─── Shuffle / repeat ─────────────────────────────────────────────────────────
+
+/// Whether Music shuffles.
+pub async fn get_shuffle() -> Result<bool> {
+    bridge::call(bridge::shuffle).await
+}
+
+/// Turns shuffle on or off and returns the state Music reports back.
+#[tracing::instrument]
+pub async fn set_shuffle(enabled: bool) -> Result<bool> {
+    bridge::call(move || bridge::set_shuffle(enabled)).await?;
+    get_shuffle().await
+}
+
+/// Music's `song repeat`: off, the current track, or the whole playlist.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    Off,
+    One,
+    All,
+}
+
+/// The current repeat mode.
+pub async fn get_repeat() -> Result<Repeat> {
+    bridge::call(bridge::repeat).await
+}
+
+/// Sets the repeat mode and returns the one Music reports back.
+#[tracing::instrument]
+pub async fn set_repeat(mode: Repeat) -> Result<Repeat> {
+    bridge::call(move || bridge::set_repeat(mode)).await?;
+    get_repeat().await
+}
+
+// ─── Automation permission ────────────────────────────────────────────────────
+
+/// Whether macOS lets the app send Apple Events to Music (Privacy &
+/// Security → Automation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Automation {
+    Granted,
+    Denied,
+    /// Music isn't open, and asking would launch it.
+    NotRunning,
+}
+
+/// Asks Music for its name, the cheapest Apple Event there is. The first
+/// time, macOS shows its permission prompt and the call waits for the answer.
+/// Without `launch`, a Music that isn't open is left closed.
+pub async fn automation(launch: bool) -> Result<Automation> {
+    let script = if launch {
+        r#"tell application "Music" to get name"#
+    } else {
+        r#"
+            if application "Music" is running then
+                tell application "Music" to get name
+            end if
+        "#
+    };
+    match run(script, &[]).await {
+        Ok(name) if name.is_empty() => Ok(Automation::NotRunning),
+        Ok(_) => Ok(Automation::Granted),
+        Err(Error::Permission) => Ok(Automation::Denied),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::normalize;
+use crate::url_encode;
+
+/// MusicBrainz rejects anonymous clients.
+const USER_AGENT: &str = "enhanced-music/0.1 (https://github.com/Fendry02/enhanced-music)";
+
+/// Minimum search score (0–100) for a release to count as the album playing.
+const MIN_SCORE: i64 = 90;
+
+/// The original release of an album, as MusicBrainz knows it.
+pub struct Release {
+    pub release_id: String,
+    pub release_group_id: String,
+    /// Empty when MusicBrainz has no date for any edition.
+    pub year: String,
+    pub label: String,
+    /// ISO 3166-1 code, or `XW` for worldwide releases.
+    pub country: String,
+    /// Release-level producer credits, or the track-level ones when the
+    /// release has none.
+    pub producers: Vec<String>,
+    /// Sum of the track lengths; `None` if any track has no length.
+    pub length_ms: Option<u64>,
+}
+
+/// Lucene phrase: quotes and backslashes would end the phrase early.
+fn phrase(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The release with its tracks and producer relationships. Best-effort: a
+/// failure only costs the producer and length fields.
+async fn details(release_id: &str) -> Result<serde_json::Value> {
+    let url = format!(
+        "https://musicbrainz.org/ws/2/release/{release_id}?inc=recordings+artist-rels+recording-level-rels&fmt=json"
+    );
+    net::get_json("musicbrainz", || http().get(&url).header("User-Agent", USER_AGENT)).await
+}
+
+fn tracks(release: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    release["media"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|m| m["tracks"].as_array().into_iter().flatten())
+}
+
+fn producer_names(relations: &serde_json::Value, names: &mut Vec<String>) {
+    for rel in relations.as_array().into_iter().flatten() {
+        if rel["type"] == "producer" {
+            if let Some(name) = rel["artist"]["name"].as_str() {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+}
+
+fn producers(release: &serde_json::Value) -> Vec<String> {
+    let mut names = Vec::new();
+    producer_names(&release["relations"], &mut names);
+    if names.is_empty() {
+        for track in tracks(release) {
+            producer_names(&track["recording"]["relations"], &mut names);
+        }
+    }
+    names
+}
+
+fn length_ms(release: &serde_json::Value) -> Option<u64> {
+    let mut tracks = tracks(release).peekable();
+    tracks.peek()?;
+    tracks.map(|t| t["length"].as_u64()).sum()
+}
+
+async fn search(artist: &str, album: &str) -> Result<Vec<serde_json::Value>> {
+    let query = format!("release:{} AND artist:{}", phrase(&normalize::title(album)), phrase(artist));
+    let url   = format!("https://musicbrainz.org/ws/2/release/?query={}&fmt=json&limit=25", url_encode(&query));
+    let json  = net::get_json("musicbrainz", || http().get(&url).header("User-Agent", USER_AGENT)).await?;
+    Ok(json["releases"].as_array().cloned().unwrap_or_default())
+}
+
+fn best_match(releases: &[serde_json::Value]) -> Result<&serde_json::Value> {
+    releases
+        .iter()
+        .find(|r| r["score"].as_i64().unwrap_or(0) >= MIN_SCORE)
+        .ok_or_else(|| Error::NotFound("Album introuvable sur MusicBrainz".into()))
+}
+
+/// Release group ID of an album — one search, without the details
+/// [`release`] fetches.
+pub async fn release_group(artist: &str, album: &str) -> Result<String> {
+    let releases = search(artist, album).await?;
+    Ok(best_match(&releases)?["release-group"]["id"].as_str().unwrap_or("").to_string())
+}
+
+/// Resolves an album to its release group, then picks the earliest dated
+/// release in that group so year, label and country are the original ones
+/// rather than a reissue's.
+pub async fn release(artist: &str, album: &str) -> Result<Release> {
+    let releases = search(artist, album).await?;
+    let best     = best_match(&releases)?;
+    let group    = best["release-group"]["id"].as_str().unwrap_or("");
+
+    // ISO dates ("1977", "1977-02", "1977-02-04") sort chronologically as strings
+    let original = releases
+        .iter()
+        .filter(|r| r["release-group"]["id"] == group)
+        .filter(|r| r["date"].as_str().is_some_and(|d| !d.is_empty()))
+        .min_by_key(|r| r["date"].as_str().unwrap_or("").to_string())
+        .unwrap_or(best);
+
+    let release_id = original["id"].as_str().unwrap_or("");
+    let details    = details(release_id).await.unwrap_or_default();
+    let producers  = producers(&details);
+    let length_ms  = length_ms(&details);
+
+    Ok(Release {
+        release_id:       release_id.to_string(),
+        release_group_id: group.to_string(),
+        year:             original["date"].as_str().and_then(|d| d.get(..4)).unwrap_or("").to_string(),
+        label:            original["label-info"][0]["label"]["name"].as_str().unwrap_or("").to_string(),
+        country:          original["country"].as_str().unwrap_or("").to_string(),
+        producers,
+        length_ms,
+    })
+}
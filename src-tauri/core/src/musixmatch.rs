@@ -0,0 +1,54 @@
+use serde_json::Value;
+use tracing::warn;
+
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::url_encode;
+
+/// The free plan appends this to every lyrics body.
+const DISCLAIMER: &str = "*******";
+
+pub struct Found {
+    pub text: String,
+    /// LRC, when the plan includes synced lyrics ("subtitles").
+    pub synced: Option<String>,
+}
+
+/// Musixmatch answers 200 even for errors; the real status is in the envelope.
+async fn call(key: &str, method: &str, title: &str, artist: &str) -> Result<Option<Value>> {
+    let url = format!(
+        "https://api.musixmatch.com/ws/1.1/{method}?q_track={}&q_artist={}&apikey={}",
+        url_encode(title),
+        url_encode(artist),
+        url_encode(key)
+    );
+    let json = net::get_json("musixmatch", || http().get(&url)).await?;
+    match json["message"]["header"]["status_code"].as_u64().unwrap_or(0) {
+        200 => Ok(Some(json["message"]["body"].clone())),
+        404 => Ok(None),
+        status => {
+            warn!(method, status, "Musixmatch error");
+            Err(Error::Http { service: "musixmatch".into(), status: status as u16 })
+        }
+    }
+}
+
+/// Lyrics of the best match for `title` by `artist`, with timings when the
+/// plan allows them.
+pub async fn lyrics(key: &str, title: &str, artist: &str) -> Result<Option<Found>> {
+    let Some(body) = call(key, "matcher.lyrics.get", title, artist).await? else {
+        return Ok(None);
+    };
+    let raw  = body["lyrics"]["lyrics_body"].as_str().unwrap_or_default();
+    let text = raw.split(DISCLAIMER).next().unwrap_or_default().trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    // Subtitles are a paid feature: a refusal just means no karaoke
+    let synced = match call(key, "matcher.subtitle.get", title, artist).await {
+        Ok(Some(body)) => body["subtitle"]["subtitle_body"].as_str().filter(|s| !s.trim().is_empty()).map(str::to_string),
+        Ok(None) | Err(_) => None,
+    };
+    Ok(Some(Found { text: text.to_string(), synced }))
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::config::{HttpConfig, RateLimit};
+use crate::error::{Error, Result};
+
+#[derive(Debug)]
+pub enum HttpError {
+    /// The server answered with a non-2xx status.
+    Status(u16),
+    /// Connection, timeout, TLS or body errors.
+    Transport(reqwest::Error),
+    /// Offline mode is on; nothing was sent.
+    Offline,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status(code)  => write!(f, "HTTP status {code}"),
+            Self::Transport(e)  => write!(f, "{e}"),
+            Self::Offline       => write!(f, "offline mode"),
+        }
+    }
+}
+
+pub type HttpResult = std::result::Result<reqwest::Response, HttpError>;
+
+// ─── Shared client ────────────────────────────────────────────────────────────
+
+fn client() -> &'static RwLock<reqwest::Client> {
+    static CLIENT: OnceLock<RwLock<reqwest::Client>> = OnceLock::new();
+    CLIENT.get_or_init(|| RwLock::new(build_client(&HttpConfig::default())))
+}
+
+fn build_client(config: &HttpConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+/// The shared client (a cheap handle onto one connection pool). Requests
+/// sent through [`send`] get their host's timeout; others use `timeout_ms`.
+pub fn http() -> reqwest::Client {
+    client().read().map(|c| c.clone()).unwrap_or_else(|p| p.into_inner().clone())
+}
+
+/// GETs `url` and decodes the JSON body, logging failures under `ctx`.
+pub async fn get_json(ctx: &str, build: impl Fn() -> reqwest::RequestBuilder) -> Result<serde_json::Value> {
+    let resp = send(ctx, build).await.map_err(|e| {
+        warn!(service = ctx, "request error: {e}");
+        Error::http(ctx, e)
+    })?;
+    resp.json().await.map_err(|e| {
+        warn!(service = ctx, "JSON parse error: {e}");
+        Error::http(ctx, HttpError::Transport(e))
+    })
+}
+
+// ─── Offline mode ─────────────────────────────────────────────────────────────
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+// ─── Retry ────────────────────────────────────────────────────────────────────
+
+fn policy() -> &'static RwLock<HttpConfig> {
+    static POLICY: OnceLock<RwLock<HttpConfig>> = OnceLock::new();
+    POLICY.get_or_init(|| RwLock::new(HttpConfig::default()))
+}
+
+/// Applies the `[http]` section; called at startup and on config reload.
+pub fn configure(config: &HttpConfig) {
+    if let Ok(mut p) = policy().write() {
+        let rebuild = p.connect_timeout_ms != config.connect_timeout_ms || p.timeout_ms != config.timeout_ms;
+        *p = config.clone();
+        if rebuild {
+            if let Ok(mut c) = client().write() {
+                *c = build_client(config);
+            }
+        }
+    }
+}
+
+/// Sends the request built by `build` until it succeeds, fails permanently,
+/// or the configured attempts run out. Non-2xx statuses are turned into
+/// errors; 429, 5xx and transport errors (timeouts, refused connections) are
+/// retried with exponential backoff, and a `Retry-After` header is honoured
+/// up to the backoff cap.
+pub async fn send(ctx: &str, build: impl Fn() -> reqwest::RequestBuilder) -> HttpResult {
+    send_except(ctx, &[], build).await
+}
+
+/// [`send`], except that the statuses in `final_statuses` are given up on at
+/// once instead of retried: for callers with a fallback of their own, like
+/// the LLM's next model.
+pub async fn send_except(ctx: &str, final_statuses: &[u16], build: impl Fn() -> reqwest::RequestBuilder) -> HttpResult {
+    if is_offline() {
+        debug!(service = ctx, "offline mode — request skipped");
+        return Err(HttpError::Offline);
+    }
+    let policy = policy().read().map(|p| p.clone()).unwrap_or_default();
+    let mut attempt = 0;
+
+    loop {
+        let (err, hint) = match build().build() {
+            Ok(mut request) => {
+                if request.timeout().is_none() {
+                    *request.timeout_mut() = timeout_for(request.url(), &policy);
+                }
+                throttle(request.url(), &policy).await;
+                match http().execute(request).await {
+                    Ok(resp) if resp.status().is_success() => return Ok(resp),
+                    Ok(resp) => (HttpError::Status(resp.status().as_u16()), retry_after(&resp)),
+                    Err(e) => (HttpError::Transport(e), None),
+                }
+            }
+            Err(e) => return Err(HttpError::Transport(e)),
+        };
+        let fallback = matches!(err, HttpError::Status(code) if final_statuses.contains(&code));
+        if attempt >= policy.retries || fallback || !is_transient(&err) {
+            crate::diagnostics::record_error(ctx, &err);
+            return Err(err);
+        }
+
+        let delay = backoff(&policy, attempt, hint);
+        info!(service = ctx, "{err} — retry {}/{} in {delay:?}", attempt + 1, policy.retries);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// LLM completions routinely run past the generic 20s, streamed ones even more.
+fn builtin_timeout(host: &str) -> Option<Duration> {
+    match host {
+        "api.anthropic.com" | "api.openai.com" => Some(Duration::from_secs(60)),
+        _ => None,
+    }
+}
+
+fn timeout_for(url: &reqwest::Url, policy: &HttpConfig) -> Option<Duration> {
+    let host = url.host_str()?;
+    policy
+        .timeouts
+        .get(host)
+        .map(|ms| Duration::from_millis(*ms))
+        .or_else(|| builtin_timeout(host))
+}
+
+fn is_transient(err: &HttpError) -> bool {
+    match err {
+        HttpError::Status(code) => *code == 429 || (500..600).contains(code),
+        HttpError::Transport(e) => e.is_timeout() || e.is_connect(),
+        HttpError::Offline      => false,
+    }
+}
+
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// `backoff_ms · 2^attempt`, capped, with "equal jitter" (a random point in
+/// the upper half) so simultaneous callers don't retry in lockstep.
+fn backoff(policy: &HttpConfig, attempt: u32, hint: Option<Duration>) -> Duration {
+    let exp    = policy.backoff_ms.saturating_mul(1u64 << attempt.min(16));
+    let capped = exp.min(policy.max_backoff_ms);
+    let ms = if policy.jitter && capped > 1 {
+        capped / 2 + random_u64() % (capped / 2 + 1)
+    } else {
+        capped
+    };
+    let delay = Duration::from_millis(ms);
+    match hint {
+        Some(h) => h.min(Duration::from_millis(policy.max_backoff_ms)).max(delay),
+        None => delay,
+    }
+}
+
+fn random_u64() -> u64 {
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
+
+// ─── Rate limiting ────────────────────────────────────────────────────────────
+
+/// Limits applied when `[http.rate_limits]` doesn't mention the host. iTunes
+/// documents roughly 20 calls per minute; Genius is more lenient but scraping
+/// its pages too quickly gets the IP challenged. Last.fm asks for at most 5
+/// calls per second.
+fn builtin_limit(host: &str) -> Option<RateLimit> {
+    match host {
+        "itunes.apple.com"      => Some(RateLimit { per_minute: 20, burst: 4 }),
+        "music.apple.com"       => Some(RateLimit { per_minute: 20, burst: 2 }),
+        "api.genius.com"        => Some(RateLimit { per_minute: 120, burst: 5 }),
+        "genius.com"            => Some(RateLimit { per_minute: 30, burst: 2 }),
+        "ws.audioscrobbler.com" => Some(RateLimit { per_minute: 300, burst: 5 }),
+        // MusicBrainz allows one request per second per client
+        "musicbrainz.org"       => Some(RateLimit { per_minute: 60, burst: 1 }),
+        "api.discogs.com"       => Some(RateLimit { per_minute: 60, burst: 3 }),
+        // Odesli's documented limit without an API key
+        "api.song.link"         => Some(RateLimit { per_minute: 10, burst: 2 }),
+        "api.setlist.fm"        => Some(RateLimit { per_minute: 60, burst: 2 }),
+        "lrclib.net"            => Some(RateLimit { per_minute: 60, burst: 3 }),
+        "api.musixmatch.com"    => Some(RateLimit { per_minute: 60, burst: 2 }),
+        _ => None,
+    }
+}
+
+struct Bucket {
+    /// May go negative: each queued caller reserves a token ahead of time.
+    tokens: f64,
+    refilled: Instant,
+}
+
+fn buckets() -> &'static Mutex<HashMap<String, Bucket>> {
+    static BUCKETS: OnceLock<Mutex<HashMap<String, Bucket>>> = OnceLock::new();
+    BUCKETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Waits until the request's host has a token available. Callers over the
+/// limit queue up in arrival order instead of hitting the API at once.
+async fn throttle(url: &reqwest::Url, policy: &HttpConfig) {
+    let Some(host) = url.host_str().map(|h| h.to_string()) else { return };
+    let Some(limit) = policy.rate_limits.get(&host).copied().or_else(|| builtin_limit(&host)) else {
+        return;
+    };
+    if limit.per_minute == 0 {
+        return;
+    }
+
+    let wait = {
+        let Ok(mut buckets) = buckets().lock() else { return };
+        let burst  = f64::from(limit.burst.max(1));
+        let rate   = f64::from(limit.per_minute) / 60.0;
+        let now    = Instant::now();
+        let bucket = buckets.entry(host.clone()).or_insert(Bucket { tokens: burst, refilled: now });
+
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens   = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.refilled = now;
+        bucket.tokens  -= 1.0;
+
+        (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate))
+    };
+
+    if let Some(wait) = wait {
+        debug!(host, "rate limited, waiting {wait:?}");
+        tokio::time::sleep(wait).await;
+    }
+}
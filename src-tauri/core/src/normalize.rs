@@ -0,0 +1,226 @@
+/// Words marking a bracketed or dashed suffix as an edition rather than part
+/// of the title.
+const EDITION_WORDS: [&str; 17] = [
+    "deluxe", "remaster", "remastered", "edition", "expanded", "anniversary", "bonus", "reissue", "version",
+    "mono", "stereo", "explicit", "clean", "edit", "single", "ep", "legacy",
+];
+
+fn is_edition(suffix: &str) -> bool {
+    suffix
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| EDITION_WORDS.contains(&word))
+}
+
+/// Strips one trailing edition marker — "(Deluxe Edition)", "[2019 Remaster]",
+/// " - Remastered 2011" — or guest credit, "(feat. Other)".
+fn strip_one(title: &str) -> Option<&str> {
+    let trimmed = title.trim_end();
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if trimmed.ends_with(close) {
+            let start = trimmed.rfind(open)?;
+            let inner = &trimmed[start + 1..trimmed.len() - 1];
+            return (is_edition(inner) || is_featuring(inner)).then(|| &trimmed[..start]);
+        }
+    }
+    let (head, suffix) = trimmed.rsplit_once(" - ")?;
+    is_edition(suffix).then_some(head)
+}
+
+/// Album or track title as metadata services know it: "Album (Deluxe
+/// Edition) [2019 Remaster]" → "Album". Only what searches and matching see —
+/// the display name stays as Music has it. Never returns an empty title.
+pub fn title(title: &str) -> String {
+    let mut rest = title.trim();
+    while let Some(stripped) = strip_one(rest) {
+        let stripped = stripped.trim_end();
+        if stripped.is_empty() {
+            break;
+        }
+        rest = stripped;
+    }
+    rest.to_string()
+}
+
+/// Markers introducing guest artists, matched case-insensitively between
+/// spaces (or after an opening parenthesis).
+const FEATURING: [&str; 6] = ["feat.", "feat", "ft.", "ft", "featuring", "avec"];
+
+/// "feat. Other", as in a title's "(feat. Other)".
+fn is_featuring(credit: &str) -> bool {
+    let first = credit.split_whitespace().next().unwrap_or("").to_lowercase();
+    FEATURING.contains(&first.as_str())
+}
+
+/// An artist field split into the main artist and its guests.
+pub struct Credit<'a> {
+    pub primary: &'a str,
+    pub featured: Vec<&'a str>,
+}
+
+/// "Artist feat. Other & Another" → `Artist` and `[Other, Another]`. Only the
+/// explicit markers split: "Simon & Garfunkel" stays one artist. The earliest
+/// marker splits first, and markers among the guests split them further.
+pub fn split_featuring(artist: &str) -> Credit<'_> {
+    let found = FEATURING
+        .iter()
+        .flat_map(|marker| [format!(" {marker} "), format!(" ({marker} ")])
+        .filter_map(|m| {
+            artist
+                .char_indices()
+                .find(|&(at, _)| artist.get(at..at + m.len()).is_some_and(|s| s.eq_ignore_ascii_case(&m)))
+                .map(|(at, _)| (at, at + m.len()))
+        })
+        .min_by_key(|&(at, end)| (at, std::cmp::Reverse(end)));
+    let Some((at, end)) = found else {
+        return Credit { primary: artist.trim(), featured: Vec::new() };
+    };
+    let guests = artist[end..].trim().trim_end_matches(')');
+    Credit {
+        primary:  artist[..at].trim(),
+        featured: guests
+            .split([',', '&'])
+            .flat_map(|g| {
+                let credit = split_featuring(g);
+                std::iter::once(credit.primary).chain(credit.featured)
+            })
+            .filter(|g| !g.is_empty())
+            .collect(),
+    }
+}
+
+/// The main artist alone, for retrying a search that missed with the full
+/// credit; `None` when there are no guests to drop.
+pub fn primary_artist(artist: &str) -> Option<&str> {
+    let credit = split_featuring(artist);
+    (!credit.featured.is_empty() && !credit.primary.is_empty()).then_some(credit.primary)
+}
+
+/// Lowest [`similarity`] at which a search result counts as the one wanted.
+pub const MATCH_THRESHOLD: f64 = 0.88;
+
+/// Base letter of the accented Latin letters music metadata uses.
+fn fold_char(c: char) -> &'static str {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'þ' => "th",
+        _ => "",
+    }
+}
+
+/// Comparison form of a title or name: lowercase, accents folded, "&" read
+/// as "and", punctuation dropped, one space between words. There is no
+/// Unicode normalization crate to lean on, so decomposed accents (NFD) are
+/// dropped as the combining marks they are.
+pub fn fold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        match fold_char(c) {
+            "" if c.is_alphanumeric() => out.push(c),
+            "" if c == '&' => out.push_str(" and "),
+            "" if c.is_whitespace() || matches!(c, '-' | '–' | '—' | '_' | '/') => out.push(' '),
+            "" => {}
+            base => out.push_str(base),
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Jaro-Winkler similarity of the folded strings, from 0 to 1. Spaces are
+/// ignored too, so "Jay-Z" and "JAY Z" are the same artist.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let squash = |s: &str| fold(s).replace(' ', "");
+    strsim::jaro_winkler(&squash(a), &squash(b))
+}
+
+/// One key per track whatever the spelling, from the folded artist and
+/// title: how the history, the mood tags and the library are matched up.
+pub fn track_key(title: &str, artist: &str) -> String {
+    format!("{}\u{1f}{}", fold(artist), fold(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_guests_at_the_earliest_marker() {
+        let split = |artist| {
+            let credit = split_featuring(artist);
+            (credit.primary, credit.featured)
+        };
+        assert_eq!(split("Daft Punk feat. Pharrell Williams"), ("Daft Punk", vec!["Pharrell Williams"]));
+        assert_eq!(split("Stromae (ft. Orelsan)"), ("Stromae", vec!["Orelsan"]));
+        assert_eq!(split("Jay-Z ft Kanye West, Rihanna & Frank Ocean"), ("Jay-Z", vec!["Kanye West", "Rihanna", "Frank Ocean"]));
+        assert_eq!(split("Zaz avec Thomas Dutronc feat. Charles Aznavour"), ("Zaz", vec!["Thomas Dutronc", "Charles Aznavour"]));
+        assert_eq!(split("Simon & Garfunkel"), ("Simon & Garfunkel", vec![]));
+    }
+
+    #[test]
+    fn folds_accents_and_punctuation() {
+        assert_eq!(fold("Beyoncé"), "beyonce");
+        assert_eq!(fold("Sigur Rós"), "sigur ros");
+        assert_eq!(fold("Guns N' Roses"), "guns n roses");
+        assert_eq!(fold("Mötley Crüe"), "motley crue");
+        assert_eq!(fold("Simon & Garfunkel"), "simon and garfunkel");
+        assert_eq!(fold("Cœur de pirate"), "coeur de pirate");
+        // "é" as "e" + combining acute accent
+        assert_eq!(fold("Beyonce\u{301}"), "beyonce");
+    }
+
+    #[test]
+    fn same_title_despite_accents_and_punctuation() {
+        assert_eq!(similarity("Björk", "Bjork"), 1.0);
+        assert_eq!(similarity("AC/DC", "ACDC"), 1.0);
+        assert_eq!(similarity("Jay-Z", "JAY Z"), 1.0);
+        assert_eq!(similarity("What's Going On", "Whats Going On"), 1.0);
+        assert_eq!(similarity("¿Dónde Están Los Ladrones?", "Donde Estan los Ladrones"), 1.0);
+        assert!(similarity("Rumours", "Rumors") >= MATCH_THRESHOLD);
+    }
+
+    #[test]
+    fn different_titles_stay_apart() {
+        assert!(similarity("Blue", "Blonde") < MATCH_THRESHOLD);
+        assert!(similarity("Abbey Road", "Let It Be") < MATCH_THRESHOLD);
+        assert!(similarity("Kid A", "Amnesiac") < MATCH_THRESHOLD);
+    }
+
+    #[test]
+    fn strips_edition_suffixes() {
+        assert_eq!(title("Album (Deluxe Edition) [2019 Remaster]"), "Album");
+        assert_eq!(title("Purple Rain - Remastered 2015"), "Purple Rain");
+        assert_eq!(title("Hello - Single"), "Hello");
+        assert_eq!(title("Ænima"), "Ænima");
+        assert_eq!(title("(Deluxe Edition)"), "(Deluxe Edition)");
+        assert_eq!(title("Songs About Jane (Tenth Anniversary Edition)"), "Songs About Jane");
+        assert_eq!(title("Get Lucky (feat. Pharrell Williams) [Radio Edit]"), "Get Lucky");
+        assert_eq!(title("Love (Live)"), "Love (Live)");
+    }
+
+    #[test]
+    fn splits_featured_artists() {
+        let credit = split_featuring("Calvin Harris feat. Rihanna & Ne-Yo");
+        assert_eq!(credit.primary, "Calvin Harris");
+        assert_eq!(credit.featured, ["Rihanna", "Ne-Yo"]);
+        assert_eq!(primary_artist("Daft Punk (feat. Pharrell Williams)"), Some("Daft Punk"));
+        assert_eq!(primary_artist("Simon & Garfunkel"), None);
+        assert_eq!(primary_artist("Ft. Lauderdale"), None);
+    }
+}
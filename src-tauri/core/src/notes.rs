@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config;
+use crate::error::{Error, Result};
+use crate::insights;
+use crate::normalize::fold;
+
+/// What a note is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Subject {
+    Track,
+    Album,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub id: u64,
+    pub subject: Subject,
+    /// The track's title, or the album's name.
+    pub title: String,
+    pub artist: String,
+    pub text: String,
+    /// RFC 3339, local time.
+    pub created_at: String,
+}
+
+/// `~/Library/Application Support/enhanced-music/notes.json`, oldest first.
+fn path() -> PathBuf {
+    config::data_dir().join("notes.json")
+}
+
+fn notes() -> &'static Mutex<Vec<Note>> {
+    static NOTES: OnceLock<Mutex<Vec<Note>>> = OnceLock::new();
+    NOTES.get_or_init(|| {
+        let notes = std::fs::read_to_string(path())
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).map_err(|e| warn!("corrupt notes file, starting fresh: {e}")).ok())
+            .unwrap_or_default();
+        Mutex::new(notes)
+    })
+}
+
+/// Unlike the caches, a note that wasn't written is worth an error: it's the
+/// user's own text.
+fn save(notes: &[Note]) -> Result<()> {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    let json = serde_json::to_string_pretty(notes).map_err(|e| Error::Io(e.to_string()))?;
+    std::fs::write(&path, json).map_err(|e| Error::Io(e.to_string()))
+}
+
+fn poisoned() -> Error {
+    Error::Io("notes indisponibles".into())
+}
+
+/// Adds a note to a track or an album.
+pub fn add(subject: Subject, title: &str, artist: &str, text: &str) -> Result<Note> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(Error::Invalid("la note est vide".into()));
+    }
+    let mut notes = notes().lock().map_err(|_| poisoned())?;
+    let note = Note {
+        id:         notes.iter().map(|n| n.id).max().unwrap_or(0) + 1,
+        subject,
+        title:      title.to_string(),
+        artist:     artist.to_string(),
+        text:       text.to_string(),
+        created_at: Local::now().to_rfc3339(),
+    };
+    notes.push(note.clone());
+    if let Err(e) = save(&notes) {
+        notes.pop();
+        return Err(e);
+    }
+    // Unlocked first: the index, opened for the first time, reads the notes
+    drop(notes);
+    insights::record_note(&note);
+    info!(id = note.id, ?subject, "note added");
+    Ok(note)
+}
+
+/// Notes, newest first: all of them, or those of one subject or one
+/// track/album (`title` and `artist` compared case and accents aside).
+pub fn get(subject: Option<Subject>, title: Option<&str>, artist: Option<&str>) -> Vec<Note> {
+    let Ok(notes) = notes().lock() else { return Vec::new() };
+    let title  = title.map(fold);
+    let artist = artist.map(fold);
+    notes
+        .iter()
+        .rev()
+        .filter(|n| subject.is_none_or(|s| n.subject == s))
+        .filter(|n| title.as_ref().is_none_or(|t| fold(&n.title) == *t))
+        .filter(|n| artist.as_ref().is_none_or(|a| fold(&n.artist) == *a))
+        .cloned()
+        .collect()
+}
+
+pub fn delete(id: u64) -> Result<()> {
+    let mut notes = notes().lock().map_err(|_| poisoned())?;
+    let Some(index) = notes.iter().position(|n| n.id == id) else {
+        return Err(Error::NotFound("Note introuvable".into()));
+    };
+    let removed = notes.remove(index);
+    if let Err(e) = save(&notes) {
+        notes.insert(index, removed);
+        return Err(e);
+    }
+    drop(notes);
+    insights::forget_note(id);
+    info!(id, "note deleted");
+    Ok(())
+}
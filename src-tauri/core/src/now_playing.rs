@@ -0,0 +1,110 @@
+use crate::error::Result;
+
+/// What the system's Now Playing widget shows — Safari, Spotify, a podcast
+/// app… Also what [`crate::spotify`] reads.
+pub struct SystemTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub playing: bool,
+    /// A podcast episode: `title` is the episode, `album` the show.
+    pub podcast: bool,
+    /// The album is credited to Various Artists.
+    pub compilation: bool,
+}
+
+/// `Ok(None)` when nothing is playing, or the system won't say: MediaRemote
+/// is private, and recent macOS versions only answer Apple's own apps.
+pub async fn read() -> Result<Option<SystemTrack>> {
+    tokio::task::spawn_blocking(imp::read)
+        .await
+        .map_err(|e| crate::error::Error::Script(e.to_string()))
+}
+
+#[cfg(not(target_os = "macos"))]
+mod imp {
+    pub fn read() -> Option<super::SystemTrack> {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::ffi::c_void;
+    use std::sync::mpsc;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    use block2::{Block, RcBlock};
+    use objc2::msg_send;
+    use objc2::rc::{autoreleasepool, Retained};
+    use objc2::runtime::AnyObject;
+    use objc2_foundation::{NSNumber, NSString};
+    use tracing::debug;
+
+    use super::SystemTrack;
+
+    const FRAMEWORK: &std::ffi::CStr = c"/System/Library/PrivateFrameworks/MediaRemote.framework/MediaRemote";
+
+    /// MediaRemote answers on a queue; an unanswered request means no access.
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    type GetNowPlayingInfo = unsafe extern "C" fn(queue: *mut c_void, handler: &Block<dyn Fn(*mut AnyObject)>);
+
+    extern "C" {
+        fn dispatch_get_global_queue(identifier: isize, flags: usize) -> *mut c_void;
+    }
+
+    /// `MRMediaRemoteGetNowPlayingInfo`, looked up once.
+    fn get_now_playing_info() -> Option<GetNowPlayingInfo> {
+        static FN: OnceLock<Option<usize>> = OnceLock::new();
+        let address = *FN.get_or_init(|| unsafe {
+            let handle = libc::dlopen(FRAMEWORK.as_ptr(), libc::RTLD_LAZY);
+            if handle.is_null() {
+                debug!("MediaRemote unavailable");
+                return None;
+            }
+            let symbol = libc::dlsym(handle, c"MRMediaRemoteGetNowPlayingInfo".as_ptr());
+            (!symbol.is_null()).then_some(symbol as usize)
+        });
+        address.map(|a| unsafe { std::mem::transmute::<usize, GetNowPlayingInfo>(a) })
+    }
+
+    fn string(info: &AnyObject, key: &str) -> String {
+        let key = NSString::from_str(key);
+        let value: Option<Retained<AnyObject>> = unsafe { msg_send![info, objectForKey: &*key] };
+        value
+            .and_then(|v| v.downcast::<NSString>().ok())
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    }
+
+    fn number(info: &AnyObject, key: &str) -> f64 {
+        let key = NSString::from_str(key);
+        let value: Option<Retained<AnyObject>> = unsafe { msg_send![info, objectForKey: &*key] };
+        value.and_then(|v| v.downcast::<NSNumber>().ok()).map_or(0.0, |n| n.doubleValue())
+    }
+
+    pub fn read() -> Option<SystemTrack> {
+        let get = get_now_playing_info()?;
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(move |info: *mut AnyObject| {
+            let track = unsafe { info.as_ref() }.and_then(|info| {
+                autoreleasepool(|_| {
+                    let title = string(info, "kMRMediaRemoteNowPlayingInfoTitle");
+                    (!title.is_empty()).then(|| SystemTrack {
+                        title,
+                        artist:      string(info, "kMRMediaRemoteNowPlayingInfoArtist"),
+                        album:       string(info, "kMRMediaRemoteNowPlayingInfoAlbum"),
+                        playing:     number(info, "kMRMediaRemoteNowPlayingInfoPlaybackRate") > 0.0,
+                        podcast:     string(info, "kMRMediaRemoteNowPlayingInfoMediaType").contains("Podcast"),
+                        compilation: false,
+                    })
+                })
+            });
+            let _ = tx.send(track);
+        });
+        unsafe { get(dispatch_get_global_queue(0, 0), &handler) };
+        rx.recv_timeout(TIMEOUT).ok().flatten()
+    }
+}
@@ -0,0 +1,28 @@
+use crate::error::Result;
+use crate::net::{self, http};
+use crate::url_encode;
+
+/// Platforms worth offering, in display order; Odesli knows many more.
+const PLATFORMS: &[&str] = &["spotify", "youtubeMusic", "youtube", "tidal", "deezer", "bandcamp", "soundcloud"];
+
+pub struct Links {
+    /// The song.link page listing every platform.
+    pub page_url: String,
+    /// `(platform, url)` pairs, in [`PLATFORMS`] order.
+    pub platforms: Vec<(String, String)>,
+}
+
+/// Every platform URL Odesli knows for a song, from any one of its links.
+pub async fn links(song_url: &str) -> Result<Links> {
+    let url  = format!("https://api.song.link/v1-alpha.1/links?url={}", url_encode(song_url));
+    let json = net::get_json("odesli", || http().get(&url)).await?;
+
+    let by_platform = &json["linksByPlatform"];
+    Ok(Links {
+        page_url: json["pageUrl"].as_str().unwrap_or("").to_string(),
+        platforms: PLATFORMS
+            .iter()
+            .filter_map(|p| Some((p.to_string(), by_platform[p]["url"].as_str()?.to_string())))
+            .collect(),
+    })
+}
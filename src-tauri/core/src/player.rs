@@ -0,0 +1,201 @@
+use tracing::debug;
+
+use crate::cancel::TrackScope;
+use crate::classical;
+use crate::config::{Config, Player};
+use crate::error::Result;
+use crate::history;
+use crate::lights;
+use crate::mqtt;
+use crate::music;
+use crate::now_playing;
+use crate::prefetch;
+use crate::radio;
+use crate::scripting_bridge;
+use crate::spotify;
+use crate::webhook;
+
+/// What is playing, whichever player it comes from.
+#[derive(serde::Serialize)]
+pub struct Track {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// Music's genre field; empty for the other players.
+    pub genre: String,
+    pub is_playing: bool,
+    /// Lossless / Hi-Res / Atmos, when Music's data says so.
+    pub quality: Option<music::AudioQuality>,
+    /// Where it was read from; Music-only controls (rating, queue…) don't
+    /// apply to the others.
+    pub player: Player,
+    /// A podcast episode: `title` is the episode and `album` the show, and
+    /// the UI asks for `get_episode_info` instead of album and lyrics.
+    pub is_podcast: bool,
+    /// On a compilation: `artist` is the track's own, and the album commands
+    /// take `compilation` so album-level lookups use Various Artists.
+    pub is_compilation: bool,
+    /// An internet radio stream: artist and title come from the stream's
+    /// title, there is no album, and `station` names the stream.
+    pub is_radio: bool,
+    pub station: Option<String>,
+    /// Composer, work and movement when the track is classical; `artist` is
+    /// then the performer.
+    pub classical: Option<classical::Work>,
+}
+
+impl Track {
+    /// Same identity as the frontend's `trackId`: play/pause doesn't change it.
+    pub fn key(&self) -> String {
+        format!("{}|||{}", self.title, self.artist)
+    }
+}
+
+async fn read_from(player: Player, config: &Config) -> Result<Option<Track>> {
+    let other = |now: now_playing::SystemTrack| {
+        // Radio apps publish the whole stream title as the title
+        let stream = (now.artist.is_empty() && !now.podcast).then(|| radio::parse_stream_title(&now.title)).flatten();
+        let track  = Track {
+            classical:      classical::parse(&now.title, "", ""),
+            title:          now.title,
+            artist:         now.artist,
+            album:          now.album,
+            genre:          String::new(),
+            is_playing:     now.playing,
+            quality:        None,
+            player,
+            is_podcast:     now.podcast,
+            is_compilation: now.compilation,
+            is_radio:       false,
+            station:        None,
+        };
+        match stream {
+            Some(stream) => Track { title: stream.title, artist: stream.artist, is_radio: true, classical: None, ..track },
+            None => track,
+        }
+    };
+    match player {
+        Player::Music => Ok(scripting_bridge::call(scripting_bridge::now_playing).await?.map(|now| {
+            let track = Track {
+                quality:        music::AudioQuality::detect(&now.kind, now.sample_rate),
+                classical:      classical::parse(&now.title, &now.composer, &now.genre),
+                title:          now.title,
+                artist:         now.artist,
+                album:          now.album,
+                genre:          now.genre,
+                is_playing:     now.playing,
+                player,
+                is_podcast:     now.podcast,
+                is_compilation: now.compilation,
+                is_radio:       false,
+                station:        None,
+            };
+            let Some(stream_title) = now.stream_title else { return track };
+            // Unparseable (a jingle, the station's slogan): show it as is, or
+            // the station while the stream says nothing
+            let stream = radio::parse_stream_title(&stream_title).unwrap_or_else(|| radio::StreamTrack {
+                artist: String::new(),
+                title:  if stream_title.is_empty() { track.title.clone() } else { stream_title },
+            });
+            Track {
+                title:     stream.title,
+                artist:    stream.artist,
+                album:     String::new(),
+                is_radio:  true,
+                classical: None,
+                station:   Some(track.title.clone()).filter(|s| !s.is_empty()),
+                ..track
+            }
+        })),
+        Player::Spotify => Ok(spotify::now_playing().await?.map(other)),
+        Player::System if config.features.system_now_playing => Ok(now_playing::read().await?.map(other)),
+        Player::System => Ok(None),
+    }
+}
+
+/// The `pinned` player's track, or that of the first of `[player] sources`
+/// that is playing — failing that, the first one paused.
+pub async fn current_track(config: &Config, pinned: Option<Player>) -> Result<Option<Track>> {
+    let sources = pinned.map_or_else(|| config.player.sources.clone(), |p| vec![p]);
+
+    let mut paused = None;
+    let mut failed = None;
+    for player in sources {
+        match read_from(player, config).await {
+            Ok(Some(track)) if track.is_playing => return Ok(Some(track)),
+            Ok(Some(track)) => {
+                paused.get_or_insert(track);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!(?player, "player unavailable: {e}");
+                failed.get_or_insert(e);
+            }
+        }
+    }
+    match (paused, failed) {
+        (Some(track), _) => Ok(Some(track)),
+        (None, Some(e)) => Err(e),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Seconds into the track [`current_track`] would pick, and whether it is
+/// playing: the same players, in the same order. The system's Now Playing
+/// doesn't give a position, so the other apps are skipped.
+pub async fn position(config: &Config, pinned: Option<Player>) -> Result<Option<(f64, bool)>> {
+    let sources = pinned.map_or_else(|| config.player.sources.clone(), |p| vec![p]);
+
+    let mut paused = None;
+    let mut failed = None;
+    for player in sources {
+        let read = match player {
+            Player::Music => scripting_bridge::call(scripting_bridge::player_position).await,
+            Player::Spotify => spotify::player_position().await,
+            Player::System => Ok(None),
+        };
+        match read {
+            Ok(Some((secs, true))) => return Ok(Some((secs, true))),
+            Ok(Some(position)) => {
+                paused.get_or_insert(position);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!(?player, "player position unavailable: {e}");
+                failed.get_or_insert(e);
+            }
+        }
+    }
+    match (paused, failed) {
+        (Some(position), _) => Ok(Some(position)),
+        (None, Some(e)) => Err(e),
+        (None, None) => Ok(None),
+    }
+}
+
+/// What follows a poll, whoever polls: on a track change the webhook fires,
+/// the previous track's work is cancelled and the lights follow the new
+/// artwork, and the next track's artwork and album card are prefetched;
+/// MQTT and the listening history get every state. `true` when the track
+/// changed.
+pub fn observe(config: &Config, scope: &TrackScope, track: Option<&Track>) -> bool {
+    history::observe(config, track);
+    let changed = scope.observe(&track.map(Track::key).unwrap_or_default());
+    if changed {
+        webhook::track_changed(config, track);
+        if let Some(t) = track.filter(|t| !t.is_radio) {
+            lights::track_changed(config, &t.title, &t.artist, &t.album);
+        }
+        prefetch::track_changed(config, track);
+    }
+    mqtt::update(
+        config,
+        track.map(|t| mqtt::State {
+            title:   t.title.clone(),
+            artist:  t.artist.clone(),
+            album:   t.album.clone(),
+            playing: t.is_playing,
+        }),
+    );
+    changed
+}
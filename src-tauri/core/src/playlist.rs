@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history;
+use crate::llm::{self, language_name};
+use crate::moods;
+use crate::music::{self, LibraryTrack, PlayedTrack};
+use crate::net;
+use crate::normalize::track_key;
+
+/// Tracks offered to the model, the most listened first: enough to choose
+/// from, few enough to keep the prompt affordable.
+const CANDIDATES: usize = 300;
+
+#[derive(Serialize)]
+pub struct GeneratedPlaylist {
+    pub name: String,
+    pub description: String,
+    /// In playlist order.
+    pub tracks: Vec<LibraryTrack>,
+}
+
+/// Library tracks Music has played, ranked by the local history first (it
+/// knows what was actually heard lately) and Music's play count after.
+fn candidates(played: Vec<PlayedTrack>) -> Vec<PlayedTrack> {
+    let heard = history::plays_by_track();
+    let mut seen = HashSet::new();
+    let mut ranked: Vec<(usize, PlayedTrack)> = played
+        .into_iter()
+        .filter(|t| seen.insert(track_key(&t.track.title, &t.track.artist)))
+        .map(|t| (heard.get(&track_key(&t.track.title, &t.track.artist)).copied().unwrap_or(0), t))
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.plays.cmp(&a.1.plays)));
+    ranked.into_iter().take(CANDIDATES).map(|(_, t)| t).collect()
+}
+
+/// A playlist for `prompt` ("un dimanche matin pluvieux"): the model picks
+/// from the library tracks listened to, and the playlist is created in
+/// Music.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn generate(config: &Config, cancel: &CancelToken, prompt: &str) -> Result<GeneratedPlaylist> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Err(Error::Invalid("décrivez la playlist voulue".into()));
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let candidates = candidates(music::played_tracks().await?);
+    if candidates.is_empty() {
+        return Err(Error::NotFound("Aucun morceau écouté dans la bibliothèque".into()));
+    }
+
+    let request = build_playlist_prompt(prompt, &candidates, &config.language());
+    let value   = llm::complete_json(config, &config.playlist, 800, &request, &playlist_tool(), cancel, None).await?;
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let mut picked = HashSet::new();
+    let tracks: Vec<LibraryTrack> = value["tracks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|n| n.as_u64())
+        .filter(|&n| n >= 1 && picked.insert(n))
+        .filter_map(|n| candidates.get(n as usize - 1))
+        .map(|t| t.track.clone())
+        .collect();
+    if tracks.is_empty() {
+        return Err(Error::NotFound("Aucun morceau ne correspond à cette description".into()));
+    }
+
+    let name = match value["name"].as_str().map(str::trim) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => prompt.to_string(),
+    };
+    let description = value["description"].as_str().unwrap_or("").trim().to_string();
+    let ids: Vec<String> = tracks.iter().map(|t| t.id.clone()).collect();
+    let added = music::create_playlist(&name, &description, &ids).await?;
+    info!(name = %name, added, "playlist created");
+
+    Ok(GeneratedPlaylist { name, description, tracks })
+}
+
+fn build_playlist_prompt(prompt: &str, candidates: &[PlayedTrack], language: &str) -> String {
+    let lang = language_name(language);
+    let list = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let mut line = format!("{}. {} — {} ({})", i + 1, t.track.artist, t.track.title, t.track.album);
+            if !t.genre.is_empty() {
+                line.push_str(&format!(" [{}]", t.genre));
+            }
+            let moods = moods::of(&t.track.title, &t.track.artist);
+            if !moods.is_empty() {
+                line.push_str(&format!(" {{{}}}", moods.join(", ")));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Voici des morceaux de la bibliothèque d'une personne, numérotés, avec leur genre entre crochets \
+         et, quand on les connaît, les humeurs de leurs paroles entre accolades :\n\n{list}\n\n\
+         Elle demande une playlist pour : « {prompt} ».\n\
+         Choisis-en 15 à 25, uniquement dans cette liste, dans un ordre qui s'écoute bien d'un bout à l'autre. \
+         Donne-lui un nom court et une description d'une phrase, en {lang}.\n\n\
+         Réponds en appelant l'outil `playlist`."
+    )
+}
+
+fn playlist_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "playlist",
+        description: "Enregistre la playlist composée",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Nom de la playlist, quelques mots"
+                },
+                "description": {
+                    "type": "string",
+                    "description": "Une phrase sur l'esprit de la playlist"
+                },
+                "tracks": {
+                    "type": "array",
+                    "items": { "type": "integer" },
+                    "description": "Numéros des morceaux choisis, dans l'ordre de lecture"
+                }
+            },
+            "required": ["name", "description", "tracks"]
+        }),
+    }
+}
@@ -0,0 +1,47 @@
+use tracing::debug;
+
+use crate::album;
+use crate::artwork;
+use crate::config::{Config, Player};
+use crate::music;
+use crate::player::Track;
+
+/// On a track change, fetches what the popover will ask for when the next
+/// track in Up Next starts — its artwork and, when it is from another album,
+/// the album card — so both are there at once. Music only: the other players
+/// have no queue to read.
+pub fn track_changed(config: &Config, track: Option<&Track>) {
+    if !config.features.prefetch {
+        return;
+    }
+    let Some(track) = track.filter(|t| t.player == Player::Music && !t.is_radio && !t.is_podcast) else { return };
+    let config = config.clone();
+    let album  = track.album.clone();
+
+    tokio::spawn(async move {
+        let next = match music::get_up_next().await {
+            Ok(tracks) => tracks.into_iter().next(),
+            Err(e) => {
+                debug!("Up Next unavailable, nothing prefetched: {e}");
+                return;
+            }
+        };
+        let Some(next) = next else { return };
+        debug!(title = %next.title, artist = %next.artist, "prefetching the next track");
+
+        let artwork = async {
+            if config.features.artwork {
+                if let Err(e) = artwork::prefetch(&config, &next.id, &next.title, &next.artist, &next.album).await {
+                    debug!("next artwork not prefetched: {e}");
+                }
+            }
+        };
+        // The album card of the current album is already on screen
+        let album_info = async {
+            if !next.album.is_empty() && next.album != album {
+                album::prefetch_info(&config, &next.album, &next.artist, next.compilation).await;
+            }
+        };
+        tokio::join!(artwork, album_info);
+    });
+}
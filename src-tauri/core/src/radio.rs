@@ -0,0 +1,29 @@
+/// Separators stations put between artist and title, most common first.
+const SEPARATORS: [&str; 5] = [" - ", " – ", " — ", " | ", " ~ "];
+
+/// Artist and title split out of an internet radio stream's title.
+#[derive(Debug, PartialEq)]
+pub struct StreamTrack {
+    pub artist: String,
+    pub title: String,
+}
+
+/// Reads an ICY `StreamTitle` — "Artist - Title" by convention, or "Title by
+/// Artist" — with or without the `StreamTitle='…';` wrapper. `None` when it
+/// doesn't look like a song (jingle, station slogan, empty).
+pub fn parse_stream_title(raw: &str) -> Option<StreamTrack> {
+    let raw = raw.trim();
+    let raw = raw
+        .strip_prefix("StreamTitle='")
+        .and_then(|s| s.split_once("';").map(|(t, _)| t).or_else(|| s.strip_suffix('\'')))
+        .unwrap_or(raw)
+        .trim();
+
+    let (artist, title) = SEPARATORS
+        .iter()
+        .find_map(|sep| raw.split_once(sep))
+        .or_else(|| raw.rsplit_once(" by ").map(|(title, artist)| (artist, title)))?;
+    let clean = |s: &str| s.trim().trim_matches(['"', '\'', '«', '»', '“', '”']).trim().to_string();
+    let (artist, title) = (clean(artist), clean(title));
+    (!artist.is_empty() && !title.is_empty()).then_some(StreamTrack { artist, title })
+}
@@ -0,0 +1,387 @@
+use crate::error::{Error, Result};
+use crate::music::Repeat;
+
+/// What the poller needs about the current track, read in one go.
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub composer: String,
+    pub genre: String,
+    pub playing: bool,
+    pub kind: String,
+    pub sample_rate: u32,
+    /// A podcast episode kept in the library rather than a song.
+    pub podcast: bool,
+    /// Part of a compilation: `artist` is the track's, the album's is
+    /// Various Artists.
+    pub compilation: bool,
+    /// What an internet radio stream says is on air; `title` is then the
+    /// station.
+    pub stream_title: Option<String>,
+}
+
+/// The current track's love and dislike flags.
+pub struct Love {
+    pub loved: bool,
+    pub disliked: bool,
+}
+
+/// The current track's play counts.
+pub struct PlayStats {
+    pub played: u32,
+    pub skipped: u32,
+    /// Unix seconds; `None` for a track never played through.
+    pub played_at: Option<u64>,
+}
+
+/// How the current track is encoded, as Music reports it.
+pub struct FileDetails {
+    pub kind: String,
+    pub bit_rate: u32,
+    pub sample_rate: u32,
+    /// POSIX path of a file in the library; `None` for a stream.
+    pub location: Option<String>,
+}
+
+/// Runs a blocking Scripting Bridge call off the async runtime: each
+/// property read is a synchronous Apple event.
+pub async fn call<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| Error::Script(e.to_string()))?
+}
+
+#[cfg(not(target_os = "macos"))]
+mod imp {
+    use super::*;
+
+    fn unavailable<T>() -> Result<T> {
+        Err(Error::Script("Scripting Bridge disponible sur macOS uniquement".into()))
+    }
+
+    pub fn now_playing() -> Result<Option<NowPlaying>> {
+        unavailable()
+    }
+    pub fn player_position() -> Result<Option<(f64, bool)>> {
+        unavailable()
+    }
+    pub fn love() -> Result<Option<Love>> {
+        unavailable()
+    }
+    pub fn set_loved(_loved: bool) -> Result<bool> {
+        unavailable()
+    }
+    pub fn set_disliked(_disliked: bool) -> Result<bool> {
+        unavailable()
+    }
+    pub fn play_stats() -> Result<Option<PlayStats>> {
+        unavailable()
+    }
+    pub fn file_details() -> Result<Option<FileDetails>> {
+        unavailable()
+    }
+    pub fn rating() -> Result<Option<u8>> {
+        unavailable()
+    }
+    pub fn set_rating(_rating: u8) -> Result<bool> {
+        unavailable()
+    }
+    pub fn shuffle() -> Result<bool> {
+        unavailable()
+    }
+    pub fn set_shuffle(_enabled: bool) -> Result<()> {
+        unavailable()
+    }
+    pub fn repeat() -> Result<Repeat> {
+        unavailable()
+    }
+    pub fn set_repeat(_mode: Repeat) -> Result<()> {
+        unavailable()
+    }
+    pub fn set_volume(_volume: u8) -> Result<()> {
+        unavailable()
+    }
+}
+
+/// `SBApplication` driven through the Objective-C runtime: Music's
+/// generated classes have no Rust bindings, so properties go through
+/// key-value coding, using the names from Music's scripting definition.
+#[cfg(target_os = "macos")]
+mod imp {
+    use objc2::rc::{autoreleasepool, Retained};
+    use objc2::runtime::{AnyClass, AnyObject, Bool, Sel};
+    use objc2::{msg_send, sel};
+    use objc2_foundation::{NSNumber, NSString};
+
+    use super::*;
+
+    #[link(name = "ScriptingBridge", kind = "framework")]
+    extern "C" {}
+
+    const fn fourcc(code: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*code)
+    }
+
+    const PLAYER_STOPPED: u32 = fourcc(b"kPSS");
+    const PLAYER_PLAYING: u32 = fourcc(b"kPSP");
+    const REPEAT_OFF: u32 = fourcc(b"kRpO");
+    const REPEAT_ONE: u32 = fourcc(b"kRp1");
+    const REPEAT_ALL: u32 = fourcc(b"kAll");
+
+    struct Music(Retained<AnyObject>);
+
+    impl Music {
+        /// Talking to an application that isn't running launches it.
+        fn connect() -> Result<Self> {
+            let class = AnyClass::get(c"SBApplication").ok_or_else(|| Error::Script("ScriptingBridge absent".into()))?;
+            let id = NSString::from_str("com.apple.Music");
+            let app: Option<Retained<AnyObject>> = unsafe { msg_send![class, applicationWithBundleIdentifier: &*id] };
+            app.map(Self).ok_or_else(|| Error::Script("Music introuvable".into()))
+        }
+
+        /// `None` when Music isn't running, so polling doesn't launch it.
+        fn running() -> Result<Option<Self>> {
+            let music = Self::connect()?;
+            let running: Bool = unsafe { msg_send![&*music.0, isRunning] };
+            Ok(running.as_bool().then_some(music))
+        }
+
+        /// No answer means stopped, unless macOS refused the event.
+        fn player_state(&self) -> Result<u32> {
+            match number(&self.0, "playerState") {
+                Some(n) => Ok(n.unsignedIntValue()),
+                None if self.denied() => Err(Error::Permission),
+                None => Ok(PLAYER_STOPPED),
+            }
+        }
+
+        /// Whether the last event failed for want of the Automation
+        /// permission (errAEEventNotPermitted): Scripting Bridge then just
+        /// answers `nil`.
+        fn denied(&self) -> bool {
+            let error: Option<Retained<AnyObject>> = unsafe { msg_send![&*self.0, lastError] };
+            error.is_some_and(|e| {
+                let code: isize = unsafe { msg_send![&*e, code] };
+                code == -1743
+            })
+        }
+
+        /// The current track, unless Music is stopped.
+        fn current_track(&self) -> Result<Option<Retained<AnyObject>>> {
+            if self.player_state()? == PLAYER_STOPPED {
+                return Ok(None);
+            }
+            Ok(value(&self.0, "currentTrack"))
+        }
+    }
+
+    fn value(object: &AnyObject, key: &str) -> Option<Retained<AnyObject>> {
+        let key = NSString::from_str(key);
+        unsafe { msg_send![object, valueForKey: &*key] }
+    }
+
+    fn set_value(object: &AnyObject, key: &str, value: &AnyObject) {
+        let key = NSString::from_str(key);
+        let _: () = unsafe { msg_send![object, setValue: value, forKey: &*key] };
+    }
+
+    fn string(object: &AnyObject, key: &str) -> String {
+        value(object, key)
+            .and_then(|v| v.downcast::<NSString>().ok())
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    }
+
+    fn number(object: &AnyObject, key: &str) -> Option<Retained<NSNumber>> {
+        value(object, key).and_then(|v| v.downcast::<NSNumber>().ok())
+    }
+
+    pub fn now_playing() -> Result<Option<NowPlaying>> {
+        autoreleasepool(|_| {
+            let Some(music) = Music::running()? else { return Ok(None) };
+            let state = music.player_state()?;
+            let Some(track) = music.current_track()? else { return Ok(None) };
+            let title = string(&track, "name");
+            // A track that can't be read (between two tracks, say) looks
+            // like nothing playing, as it did through AppleScript
+            if title.is_empty() {
+                return Ok(None);
+            }
+            let kind    = string(&track, "kind");
+            let genre   = string(&track, "genre");
+            let podcast = [&kind, &genre].iter().any(|s| s.to_lowercase().contains("podcast"));
+            let stream  = !string(&music.0, "currentStreamURL").is_empty();
+            Ok(Some(NowPlaying {
+                title,
+                artist:      string(&track, "artist"),
+                album:       string(&track, "album"),
+                composer:    string(&track, "composer"),
+                compilation: number(&track, "compilation").is_some_and(|n| n.boolValue()),
+                playing:     state == PLAYER_PLAYING,
+                sample_rate: number(&track, "sampleRate").map_or(0, |n| n.unsignedIntValue()),
+                stream_title: stream.then(|| string(&music.0, "currentStreamTitle")),
+                kind,
+                genre,
+                podcast,
+            }))
+        })
+    }
+
+    /// Seconds into the current track and whether it is playing; `None`
+    /// when Music isn't running or is stopped.
+    pub fn player_position() -> Result<Option<(f64, bool)>> {
+        autoreleasepool(|_| {
+            let Some(music) = Music::running()? else { return Ok(None) };
+            let state = music.player_state()?;
+            if state == PLAYER_STOPPED {
+                return Ok(None);
+            }
+            Ok(number(&music.0, "playerPosition").map(|n| (n.doubleValue(), state == PLAYER_PLAYING)))
+        })
+    }
+
+    /// Whether `object` has the property behind `selector`: key-value
+    /// coding on one it lacks raises an Objective-C exception.
+    fn responds(object: &AnyObject, selector: Sel) -> bool {
+        let responds: Bool = unsafe { msg_send![object, respondsToSelector: selector] };
+        responds.as_bool()
+    }
+
+    /// Music's dictionary renamed `loved` to `favorited` in macOS 14; older
+    /// systems only know the former.
+    fn love_key(track: &AnyObject) -> &'static str {
+        if responds(track, sel!(favorited)) { "favorited" } else { "loved" }
+    }
+
+    pub fn love() -> Result<Option<Love>> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(None) };
+            let flag = |key: &str| number(&track, key).is_some_and(|n| n.boolValue());
+            Ok(Some(Love { loved: flag(love_key(&track)), disliked: flag("disliked") }))
+        })
+    }
+
+    /// `false` when nothing is playing. Music clears a dislike on loving.
+    pub fn set_loved(loved: bool) -> Result<bool> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(false) };
+            set_value(&track, love_key(&track), &NSNumber::numberWithBool(loved));
+            Ok(true)
+        })
+    }
+
+    /// `false` when nothing is playing. Music clears a love on disliking.
+    pub fn set_disliked(disliked: bool) -> Result<bool> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(false) };
+            set_value(&track, "disliked", &NSNumber::numberWithBool(disliked));
+            Ok(true)
+        })
+    }
+
+    pub fn play_stats() -> Result<Option<PlayStats>> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(None) };
+            let played_at = value(&track, "playedDate").map(|date| {
+                let secs: f64 = unsafe { msg_send![&*date, timeIntervalSince1970] };
+                secs
+            });
+            Ok(Some(PlayStats {
+                played:    number(&track, "playedCount").map_or(0, |n| n.unsignedIntValue()),
+                skipped:   number(&track, "skippedCount").map_or(0, |n| n.unsignedIntValue()),
+                played_at: played_at.filter(|s| *s > 0.0).map(|s| s as u64),
+            }))
+        })
+    }
+
+    pub fn file_details() -> Result<Option<FileDetails>> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(None) };
+            // `currentTrack` is a plain track reference; resolving it gives
+            // the file track, the only kind with a `location`
+            let resolved: Option<Retained<AnyObject>> = unsafe { msg_send![&*track, get] };
+            let location = resolved
+                .filter(|t| responds(t, sel!(location)))
+                .and_then(|t| value(&t, "location"))
+                .and_then(|url| {
+                    let path: Option<Retained<NSString>> = unsafe { msg_send![&*url, path] };
+                    path.map(|p| p.to_string())
+                })
+                .filter(|p| !p.is_empty());
+            Ok(Some(FileDetails {
+                kind:        string(&track, "kind"),
+                bit_rate:    number(&track, "bitRate").map_or(0, |n| n.unsignedIntValue()),
+                sample_rate: number(&track, "sampleRate").map_or(0, |n| n.unsignedIntValue()),
+                location,
+            }))
+        })
+    }
+
+    pub fn rating() -> Result<Option<u8>> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(None) };
+            Ok(Some(number(&track, "rating").map_or(0, |n| n.integerValue().clamp(0, 100) as u8)))
+        })
+    }
+
+    /// `false` when nothing is playing.
+    pub fn set_rating(rating: u8) -> Result<bool> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let Some(track) = music.current_track()? else { return Ok(false) };
+            set_value(&track, "rating", &NSNumber::numberWithInteger(rating.min(100) as isize));
+            Ok(true)
+        })
+    }
+
+    pub fn shuffle() -> Result<bool> {
+        autoreleasepool(|_| Ok(number(&Music::connect()?.0, "shuffleEnabled").is_some_and(|n| n.boolValue())))
+    }
+
+    pub fn set_shuffle(enabled: bool) -> Result<()> {
+        autoreleasepool(|_| {
+            set_value(&Music::connect()?.0, "shuffleEnabled", &NSNumber::numberWithBool(enabled));
+            Ok(())
+        })
+    }
+
+    pub fn repeat() -> Result<Repeat> {
+        autoreleasepool(|_| {
+            let music = Music::connect()?;
+            let code  = number(&music.0, "songRepeat").map(|n| n.unsignedIntValue());
+            match code {
+                Some(REPEAT_OFF) => Ok(Repeat::Off),
+                Some(REPEAT_ONE) => Ok(Repeat::One),
+                Some(REPEAT_ALL) => Ok(Repeat::All),
+                None if music.denied() => Err(Error::Permission),
+                other => Err(Error::Script(format!("song repeat inattendu : {other:?}"))),
+            }
+        })
+    }
+
+    pub fn set_repeat(mode: Repeat) -> Result<()> {
+        let code = match mode {
+            Repeat::Off => REPEAT_OFF,
+            Repeat::One => REPEAT_ONE,
+            Repeat::All => REPEAT_ALL,
+        };
+        autoreleasepool(|_| {
+            set_value(&Music::connect()?.0, "songRepeat", &NSNumber::numberWithUnsignedInt(code));
+            Ok(())
+        })
+    }
+
+    pub fn set_volume(volume: u8) -> Result<()> {
+        autoreleasepool(|_| {
+            set_value(&Music::connect()?.0, "soundVolume", &NSNumber::numberWithInteger(volume.min(100) as isize));
+            Ok(())
+        })
+    }
+}
+
+pub use imp::*;
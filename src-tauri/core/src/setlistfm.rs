@@ -0,0 +1,63 @@
+use crate::error::{Error, Result};
+use crate::net::{self, http};
+use crate::url_encode;
+
+pub struct Setlist {
+    /// ISO date (`YYYY-MM-DD`); setlist.fm sends `DD-MM-YYYY`.
+    pub date: String,
+    pub venue: String,
+    pub city: String,
+    pub country: String,
+    pub tour: String,
+    /// Main set and encores, in playing order.
+    pub songs: Vec<String>,
+    pub url: String,
+}
+
+fn iso_date(dmy: &str) -> String {
+    match dmy.split('-').collect::<Vec<_>>()[..] {
+        [d, m, y] => format!("{y}-{m}-{d}"),
+        _ => dmy.to_string(),
+    }
+}
+
+/// Most recent setlists of the artist, newest first. Shows announced
+/// without any song (future dates, empty entries) are left out.
+pub async fn recent(key: &str, artist: &str, limit: usize) -> Result<Vec<Setlist>> {
+    let url  = format!("https://api.setlist.fm/rest/1.0/search/setlists?artistName={}&p=1", url_encode(artist));
+    let json = net::get_json("setlistfm", || {
+        http().get(&url).header("x-api-key", key).header("Accept", "application/json")
+    })
+    .await
+    .map_err(|e| match e {
+        // setlist.fm answers 404 rather than an empty list
+        Error::Http { status: 404, .. } => Error::NotFound("Aucune setlist sur setlist.fm".into()),
+        e => e,
+    })?;
+
+    Ok(json["setlist"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|s| {
+            let venue = &s["venue"];
+            Setlist {
+                date:    iso_date(s["eventDate"].as_str().unwrap_or("")),
+                venue:   venue["name"].as_str().unwrap_or("").to_string(),
+                city:    venue["city"]["name"].as_str().unwrap_or("").to_string(),
+                country: venue["city"]["country"]["code"].as_str().unwrap_or("").to_string(),
+                tour:    s["tour"]["name"].as_str().unwrap_or("").to_string(),
+                songs: s["sets"]["set"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|set| set["song"].as_array().into_iter().flatten())
+                    .filter_map(|song| song["name"].as_str().filter(|n| !n.is_empty()).map(str::to_string))
+                    .collect(),
+                url: s["url"].as_str().unwrap_or("").to_string(),
+            }
+        })
+        .filter(|s| !s.songs.is_empty())
+        .take(limit)
+        .collect())
+}
@@ -0,0 +1,263 @@
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::{config_path, Config};
+use crate::error::{Error, Result};
+
+/// Tells a settings bundle from any other JSON file.
+const FORMAT: &str = "enhanced-music/settings";
+
+/// Keys left out of a bundle unless a passphrase encrypts them.
+const SECRETS: [(&str, &str); 15] = [
+    ("api", "genius_token"),
+    ("api", "genius_client_secret"),
+    ("api", "anthropic_key"),
+    ("api", "openai_key"),
+    ("api", "lastfm_key"),
+    ("api", "discogs_token"),
+    ("api", "bandsintown_app_id"),
+    ("api", "setlistfm_key"),
+    ("api", "fanart_key"),
+    ("api", "musixmatch_key"),
+    ("api", "musickit_token"),
+    ("api", "spotify_client_secret"),
+    ("mqtt", "password"),
+    ("lights", "hue_username"),
+    ("server", "token"),
+];
+
+/// OWASP's figure for PBKDF2-HMAC-SHA256; an import or export is rare
+/// enough to afford it.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// The whole of `config.toml` as JSON, secrets apart.
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    exported_at: String,
+    config: toml::Table,
+    /// The secrets as a `"table.key" → value` JSON object, AES-256-GCM
+    /// encrypted with a key derived from the passphrase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secrets: Option<Sealed>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+#[derive(Serialize)]
+pub struct ExportReport {
+    pub path: String,
+    /// Secrets written, encrypted; 0 without a passphrase.
+    pub secrets: usize,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    /// Secrets taken from the bundle.
+    pub secrets: usize,
+    /// Secrets of this machine kept because the bundle had none to give
+    /// (exported or imported without a passphrase).
+    pub kept_secrets: usize,
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content.parse().map_err(|e| Error::Config(format!("TOML invalide : {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(e) => Err(Error::Io(e.to_string())),
+    }
+}
+
+/// Removes the secrets from `table`, returning the non-empty ones.
+fn take_secrets(table: &mut toml::Table) -> serde_json::Map<String, serde_json::Value> {
+    let mut taken = serde_json::Map::new();
+    for (section, key) in SECRETS {
+        let Some(toml::Value::Table(section_table)) = table.get_mut(section) else { continue };
+        if let Some(toml::Value::String(secret)) = section_table.remove(key) {
+            if !secret.is_empty() {
+                taken.insert(format!("{section}.{key}"), secret.into());
+            }
+        }
+    }
+    taken
+}
+
+fn put_secrets(table: &mut toml::Table, secrets: &serde_json::Map<String, serde_json::Value>) {
+    for (path, secret) in secrets {
+        let (Some((section, key)), Some(secret)) = (path.split_once('.'), secret.as_str()) else { continue };
+        // Only the known secret keys, whatever the bundle holds
+        if !SECRETS.contains(&(section, key)) {
+            continue;
+        }
+        let entry = table.entry(section).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(section_table) = entry {
+            section_table.insert(key.to_string(), secret.into());
+        }
+    }
+}
+
+fn key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; 32];
+    let rounds  = NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iteration count");
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, rounds, salt, passphrase.as_bytes(), &mut key);
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).expect("AES-256 takes a 32-byte key"))
+}
+
+fn seal(plain: &[u8], passphrase: &str) -> Result<Sealed> {
+    let rng = SystemRandom::new();
+    let mut salt  = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt).and_then(|()| rng.fill(&mut nonce)).map_err(|_| Error::Io("aléa indisponible".into()))?;
+
+    let mut data = plain.to_vec();
+    key(passphrase, &salt)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| Error::Io("chiffrement impossible".into()))?;
+    Ok(Sealed { salt: STANDARD.encode(salt), nonce: STANDARD.encode(nonce), data: STANDARD.encode(data) })
+}
+
+fn open(sealed: &Sealed, passphrase: &str) -> Result<Vec<u8>> {
+    let corrupt = || Error::Invalid("secrets du fichier illisibles".into());
+    let salt    = STANDARD.decode(&sealed.salt).map_err(|_| corrupt())?;
+    let nonce   = STANDARD.decode(&sealed.nonce).map_err(|_| corrupt())?;
+    let nonce   = Nonce::try_assume_unique_for_key(&nonce).map_err(|_| corrupt())?;
+    let mut data = STANDARD.decode(&sealed.data).map_err(|_| corrupt())?;
+
+    let plain = key(passphrase, &salt)
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| Error::Invalid("mot de passe incorrect".into()))?;
+    Ok(plain.to_vec())
+}
+
+/// Writes `config.toml` to `path` as a JSON bundle. Secrets are left out,
+/// or with a `passphrase` included encrypted.
+pub fn export(path: &Path, passphrase: Option<&str>) -> Result<ExportReport> {
+    let mut config = read_table(&config_path())?;
+    let secrets    = take_secrets(&mut config);
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+
+    let sealed = match passphrase {
+        Some(passphrase) if !secrets.is_empty() => {
+            let plain = serde_json::to_vec(&secrets).map_err(|e| Error::Io(e.to_string()))?;
+            Some(seal(&plain, passphrase)?)
+        }
+        _ => None,
+    };
+    let report = ExportReport {
+        path:    path.display().to_string(),
+        secrets: if sealed.is_some() { secrets.len() } else { 0 },
+    };
+    let bundle = Bundle {
+        format:      FORMAT.to_string(),
+        exported_at: chrono::Local::now().to_rfc3339(),
+        config,
+        secrets:     sealed,
+    };
+
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| Error::Io(e.to_string()))?;
+    std::fs::write(path, json).map_err(|e| Error::Io(e.to_string()))?;
+    #[cfg(unix)]
+    if report.secrets > 0 {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    info!(path = %path.display(), secrets = report.secrets, "settings exported");
+    Ok(report)
+}
+
+/// Replaces `config.toml` with the bundle at `path`. Secrets come from the
+/// bundle when `passphrase` opens them, otherwise this machine's are kept.
+/// The previous file is saved as `config.toml.bak`, and put back if the
+/// result doesn't load.
+pub fn import(path: &Path, passphrase: Option<&str>) -> Result<ImportReport> {
+    let content = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+    let bundle: Bundle = serde_json::from_str(&content)
+        .ok()
+        .filter(|b: &Bundle| b.format == FORMAT)
+        .ok_or_else(|| Error::Invalid("ce fichier n'est pas un export de réglages Enhanced Music".into()))?;
+
+    let target     = config_path();
+    let previous   = std::fs::read_to_string(&target).ok();
+    let local      = take_secrets(&mut read_table(&target)?);
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    let mut config = bundle.config;
+    // Secrets a bundle carries in the clear weren't exported by the app
+    take_secrets(&mut config);
+
+    let secrets = match (&bundle.secrets, passphrase) {
+        (Some(sealed), Some(passphrase)) => {
+            serde_json::from_slice(&open(sealed, passphrase)?).map_err(|_| Error::Invalid("secrets du fichier illisibles".into()))?
+        }
+        _ => serde_json::Map::new(),
+    };
+    let report = if secrets.is_empty() {
+        put_secrets(&mut config, &local);
+        ImportReport { secrets: 0, kept_secrets: local.len() }
+    } else {
+        put_secrets(&mut config, &secrets);
+        ImportReport { secrets: secrets.len(), kept_secrets: 0 }
+    };
+
+    let toml = toml::to_string(&config).map_err(|e| Error::Config(e.to_string()))?;
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    std::fs::write(&target, toml).map_err(|e| Error::Io(e.to_string()))?;
+
+    // Loading migrates a bundle from an older version, so the backup is
+    // written after it, not to be replaced by the bundle's own
+    if let Err(e) = Config::load() {
+        let restored = match &previous {
+            Some(previous) => std::fs::write(&target, previous),
+            None => std::fs::remove_file(&target),
+        };
+        if let Err(io) = restored {
+            warn!(path = %target.display(), "previous config not restored: {io}");
+        }
+        return Err(e);
+    }
+    if let Some(previous) = &previous {
+        std::fs::write(target.with_extension("toml.bak"), previous).map_err(|e| Error::Io(e.to_string()))?;
+    }
+    info!(path = %path.display(), secrets = report.secrets, "settings imported");
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_leave_the_table_and_come_back() {
+        let mut table: toml::Table = "[api]\nanthropic_key = \"sk\"\nlastfm_key = \"\"\nopenai_base_url = \"u\"\n[mqtt]\npassword = \"pw\"\n"
+            .parse()
+            .unwrap();
+        let secrets = take_secrets(&mut table);
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(table["api"].get("anthropic_key"), None);
+        assert_eq!(table["api"]["openai_base_url"].as_str(), Some("u"));
+
+        put_secrets(&mut table, &secrets);
+        assert_eq!(table["mqtt"]["password"].as_str(), Some("pw"));
+    }
+
+    #[test]
+    fn sealed_secrets_need_the_passphrase() {
+        let sealed = seal(b"{}", "correct horse").unwrap();
+        assert_eq!(open(&sealed, "correct horse").unwrap(), b"{}");
+        assert!(matches!(open(&sealed, "wrong"), Err(Error::Invalid(_))));
+    }
+}
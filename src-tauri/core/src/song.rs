@@ -0,0 +1,157 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::genius;
+use crate::itunes;
+use crate::net;
+use crate::odesli;
+
+// ─── Song links ───────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct PlatformLink {
+    pub platform: String,
+    pub url: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct SongLinks {
+    /// Universal song.link page, the one to share.
+    pub page_url: String,
+    pub links: Vec<PlatformLink>,
+}
+
+/// Resolves the track on iTunes, then asks Odesli for the same song elsewhere.
+#[tracing::instrument]
+pub async fn links(title: &str, artist: &str) -> Result<SongLinks> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+
+    let url   = itunes::track_url(title, artist).await?;
+    let links = odesli::links(&url).await?;
+
+    Ok(SongLinks {
+        page_url: links.page_url,
+        links:    links.platforms.into_iter().map(|(platform, url)| PlatformLink { platform, url }).collect(),
+    })
+}
+
+/// Apple Music web link of the track, for "Share" and "Open in browser".
+#[tracing::instrument]
+pub async fn track_url(title: &str, artist: &str) -> Result<String> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    itunes::track_url(title, artist).await
+}
+
+// ─── Song credits ─────────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct CreditGroup {
+    pub role: String,
+    pub names: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SongCredits {
+    pub writers: Vec<String>,
+    pub producers: Vec<String>,
+    pub featured: Vec<String>,
+    /// Genius "custom performances": mixing, mastering, instruments, labels…
+    pub other: Vec<CreditGroup>,
+}
+
+fn artist_names(list: &serde_json::Value) -> Vec<String> {
+    list.as_array()
+        .map(|a| a.iter().filter_map(|x| x["name"].as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+#[tracing::instrument(skip(config))]
+pub async fn credits(config: &Config, title: &str, artist: &str) -> Result<SongCredits> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.genius_token.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let token = &config.api.genius_token;
+    let hit   = genius::search_song(token, title, artist).await?;
+    let id    = hit["id"].as_i64().ok_or_else(|| Error::NotFound("Morceau introuvable sur Genius".into()))?;
+    let song  = genius::song(token, id).await?;
+
+    let other = song["custom_performances"]
+        .as_array()
+        .map(|groups| {
+            groups
+                .iter()
+                .filter_map(|g| {
+                    let names = artist_names(&g["artists"]);
+                    let role  = g["label"].as_str()?.to_string();
+                    (!names.is_empty()).then_some(CreditGroup { role, names })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(SongCredits {
+        writers:   artist_names(&song["writer_artists"]),
+        producers: artist_names(&song["producer_artists"]),
+        featured:  artist_names(&song["featured_artists"]),
+        other,
+    })
+}
+
+// ─── Song connections ─────────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+pub struct ConnectedSong {
+    pub title: String,
+    pub artist: String,
+    pub url: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct SongConnection {
+    /// Genius relationship type: `samples`, `sampled_in`, `interpolates`,
+    /// `cover_of`, `covered_by`, `remix_of`…
+    pub kind: String,
+    pub songs: Vec<ConnectedSong>,
+}
+
+/// What the track samples or interpolates, and who sampled, covered or
+/// remixed it. Only non-empty relationships are returned.
+#[tracing::instrument(skip(config))]
+pub async fn connections(config: &Config, title: &str, artist: &str) -> Result<Vec<SongConnection>> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.genius_token.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let token = &config.api.genius_token;
+    let hit   = genius::search_song(token, title, artist).await?;
+    let id    = hit["id"].as_i64().ok_or_else(|| Error::NotFound("Morceau introuvable sur Genius".into()))?;
+    let song  = genius::song(token, id).await?;
+
+    let relationships = song["song_relationships"].as_array().cloned().unwrap_or_default();
+    Ok(relationships
+        .iter()
+        .filter_map(|r| {
+            let kind  = r["relationship_type"].as_str().or(r["type"].as_str())?.to_string();
+            let songs = r["songs"]
+                .as_array()?
+                .iter()
+                .map(|s| ConnectedSong {
+                    title:  s["title"].as_str().unwrap_or("").to_string(),
+                    artist: s["primary_artist"]["name"].as_str().unwrap_or("").to_string(),
+                    url:    s["url"].as_str().unwrap_or("").to_string(),
+                })
+                .collect::<Vec<_>>();
+            (!songs.is_empty()).then_some(SongConnection { kind, songs })
+        })
+        .collect())
+}
@@ -0,0 +1,229 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::cache;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::music;
+use crate::net::{self, http, HttpError};
+use crate::normalize;
+use crate::now_playing::SystemTrack;
+use crate::url_encode;
+
+// ─── Desktop app ──────────────────────────────────────────────────────────────
+
+/// The Spotify desktop app's current track, through its AppleScript
+/// dictionary; `Ok(None)` when it isn't running or is stopped.
+pub async fn now_playing() -> Result<Option<SystemTrack>> {
+    let out = music::run(
+        r#"if application "Spotify" is running then
+            tell application "Spotify"
+                if player state is not stopped then
+                    return (name of current track) & "|||" & (artist of current track) & "|||" & (album of current track) & "|||" & (player state as text) & "|||" & (id of current track) & "|||" & (album artist of current track)
+                end if
+            end tell
+        end if
+        return """#,
+        &[],
+    )
+    .await?;
+    let parts: Vec<&str> = out.splitn(6, "|||").collect();
+    let [title, artist, album, state, id, album_artist] = parts[..] else { return Ok(None) };
+    Ok(Some(SystemTrack {
+        title:       title.to_string(),
+        artist:      artist.to_string(),
+        album:       album.to_string(),
+        playing:     state == "playing",
+        podcast:     id.starts_with("spotify:episode:"),
+        compilation: album_artist == crate::itunes::VARIOUS_ARTISTS,
+    }))
+}
+
+/// Seconds into the desktop app's current track and whether it is playing;
+/// `Ok(None)` when it isn't running or is stopped.
+pub async fn player_position() -> Result<Option<(f64, bool)>> {
+    let out = music::run(
+        r#"if application "Spotify" is running then
+            tell application "Spotify"
+                if player state is not stopped then
+                    return ((player position) as text) & "|||" & (player state as text)
+                end if
+            end tell
+        end if
+        return """#,
+        &[],
+    )
+    .await?;
+    let Some((position, state)) = out.split_once("|||") else { return Ok(None) };
+    // AppleScript formats reals with the user's decimal separator
+    Ok(position.replace(',', ".").parse().ok().map(|secs| (secs, state == "playing")))
+}
+
+// ─── Web API ──────────────────────────────────────────────────────────────────
+
+const API: &str = "https://api.spotify.com/v1";
+
+/// Pitch classes as Spotify numbers them, from C.
+const PITCHES: [&str; 12] = ["C", "C♯", "D", "E♭", "E", "F", "F♯", "G", "A♭", "A", "B♭", "B"];
+
+/// "C♯ mineur" and its Camelot code ("12A"), how DJs match keys, for pitch
+/// class `pitch` (0 = C).
+pub fn key_name(pitch: u8, minor: bool) -> (String, String) {
+    let pitch = usize::from(pitch % 12);
+    // Camelot numbers go round the circle of fifths: 8B is C major, 5A C minor
+    let camelot = if minor { (pitch * 7 + 4) % 12 + 1 } else { (pitch * 7 + 7) % 12 + 1 };
+    let mode    = if minor { "mineur" } else { "majeur" };
+    (format!("{} {mode}", PITCHES[pitch]), format!("{camelot}{}", if minor { 'A' } else { 'B' }))
+}
+
+/// How the track sounds, as Spotify measured it. The ratings go from 0 to 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioFeatures {
+    pub energy: f64,
+    pub danceability: f64,
+    /// Musical positiveness: high is cheerful, low sad or tense.
+    pub valence: f64,
+    pub acousticness: f64,
+    pub instrumentalness: f64,
+    pub speechiness: f64,
+    pub liveness: f64,
+    /// Beats per minute.
+    pub tempo: f64,
+    /// e.g. "C♯ mineur"; empty when Spotify couldn't tell.
+    pub key: String,
+    /// The key's Camelot code, e.g. "12A".
+    pub camelot: String,
+    /// Beats per bar.
+    pub time_signature: u32,
+}
+
+fn features_from(json: &serde_json::Value) -> Option<AudioFeatures> {
+    let rating = |field: &str| json[field].as_f64().unwrap_or(0.0);
+    let (key, camelot) = match json["key"].as_i64() {
+        Some(pitch @ 0..=11) => key_name(pitch as u8, json["mode"].as_i64() == Some(0)),
+        _ => (String::new(), String::new()),
+    };
+    Some(AudioFeatures {
+        energy:           rating("energy"),
+        danceability:     rating("danceability"),
+        valence:          rating("valence"),
+        acousticness:     rating("acousticness"),
+        instrumentalness: rating("instrumentalness"),
+        speechiness:      rating("speechiness"),
+        liveness:         rating("liveness"),
+        tempo:            json["tempo"].as_f64()?,
+        key,
+        camelot,
+        time_signature:   json["time_signature"].as_u64().unwrap_or(4) as u32,
+    })
+}
+
+/// An app token from the client-credentials flow, kept until a minute
+/// before it expires.
+async fn token(config: &Config) -> Result<String> {
+    static TOKEN: Mutex<Option<(String, Instant, String)>> = Mutex::new(None);
+    let (id, secret) = (&config.api.spotify_client_id, &config.api.spotify_client_secret);
+    if let Ok(token) = TOKEN.lock() {
+        if let Some((token, expires, client)) = token.as_ref() {
+            if client == id && Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+    }
+
+    let resp = net::send("spotify", || {
+        http()
+            .post("https://accounts.spotify.com/api/token")
+            .basic_auth(id, Some(secret))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body("grant_type=client_credentials")
+    })
+    .await
+    .map_err(|e| {
+        warn!("Spotify token request failed: {e}");
+        Error::http("spotify", e)
+    })?;
+    let json: serde_json::Value = resp.json().await.map_err(|e| Error::http("spotify", HttpError::Transport(e)))?;
+    let token = json["access_token"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| Error::Auth("jeton Spotify absent de la réponse".into()))?
+        .to_string();
+    let lifetime = Duration::from_secs(json["expires_in"].as_u64().unwrap_or(3600).saturating_sub(60));
+    if let Ok(mut cached) = TOKEN.lock() {
+        *cached = Some((token.clone(), Instant::now() + lifetime, id.clone()));
+    }
+    Ok(token)
+}
+
+async fn get(config: &Config, path: &str) -> Result<serde_json::Value> {
+    let token = token(config).await?;
+    let url   = format!("{API}/{path}");
+    net::get_json("spotify", || http().get(&url).bearer_auth(&token)).await
+}
+
+/// Spotify ID of the best matching track.
+async fn track_id(config: &Config, title: &str, artist: &str) -> Result<String> {
+    let title = normalize::title(title);
+    let query = url_encode(&format!("track:{title} artist:{}", normalize::split_featuring(artist).primary));
+    let json  = get(config, &format!("search?q={query}&type=track&limit=5")).await?;
+    let score = |t: &serde_json::Value| {
+        let name     = normalize::title(t["name"].as_str().unwrap_or(""));
+        let credited = t["artists"][0]["name"].as_str().unwrap_or("");
+        normalize::similarity(&name, &title).min(normalize::similarity(credited, normalize::split_featuring(artist).primary))
+    };
+    json["tracks"]["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|t| (score(t), t))
+        .filter(|(s, _)| *s >= normalize::MATCH_THRESHOLD)
+        .fold(None, |best: Option<(f64, &serde_json::Value)>, (s, t)| match best {
+            Some((b, _)) if b >= s => best,
+            _ => Some((s, t)),
+        })
+        .and_then(|(_, t)| t["id"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| Error::NotFound("Morceau introuvable sur Spotify".into()))
+}
+
+/// Spotify's audio features for the track, cached on disk: they don't
+/// change. Needs `spotify_client_id` and `spotify_client_secret` in `[api]`.
+#[tracing::instrument(skip(config))]
+pub async fn audio_features(config: &Config, title: &str, artist: &str) -> Result<AudioFeatures> {
+    let key = normalize::track_key(title, artist);
+    if let Some(cached) = cache::read::<AudioFeatures>("spotify_features", &key) {
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.api.spotify_client_id.is_empty() || config.api.spotify_client_secret.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let features = cache::unless_missed("spotify_features", &key, async {
+        let id   = track_id(config, title, artist).await?;
+        let json = get(config, &format!("audio-features/{id}")).await?;
+        features_from(&json).ok_or_else(|| Error::NotFound("Pas de caractéristiques audio pour ce morceau".into()))
+    })
+    .await?;
+    cache::write("spotify_features", &key, &features);
+    Ok(features)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_get_their_camelot_code() {
+        assert_eq!(key_name(0, false), ("C majeur".to_string(), "8B".to_string()));
+        assert_eq!(key_name(9, true), ("A mineur".to_string(), "8A".to_string()));
+        assert_eq!(key_name(1, true), ("C♯ mineur".to_string(), "12A".to_string()));
+        assert_eq!(key_name(7, false).1, "9B");
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history::{self, Play};
+use crate::lastfm;
+use crate::llm::{self, language_name};
+use crate::moods;
+use crate::music::{self, LibraryTrack, PlayedTrack};
+use crate::net;
+use crate::normalize::{fold, track_key};
+
+/// Plays the suggestion follows on from; they aren't suggested again.
+const RECENT: usize = 20;
+
+/// Library tracks offered to the model.
+const CANDIDATES: usize = 150;
+
+/// Tracks of one artist among the candidates, so a favourite doesn't crowd
+/// the rest out.
+const PER_ARTIST: usize = 4;
+
+const SIMILAR: usize = 20;
+
+const SUGGESTIONS: usize = 3;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Play the track.
+    Track,
+    /// Play the track's album from the start.
+    Album,
+}
+
+#[derive(Serialize)]
+pub struct Suggestion {
+    pub kind: Kind,
+    #[serde(flatten)]
+    pub track: LibraryTrack,
+    /// One line on why it fits now.
+    pub reason: String,
+}
+
+/// Library tracks not heard lately: those of artists Last.fm finds close to
+/// `similar` first, in its order, then by play count.
+fn candidates(library: Vec<PlayedTrack>, recent: &HashSet<String>, similar: &[String]) -> Vec<PlayedTrack> {
+    let rank: HashMap<String, usize> = similar.iter().enumerate().map(|(i, name)| (fold(name), i)).collect();
+    let mut ranked: Vec<(usize, PlayedTrack)> = library
+        .into_iter()
+        .filter(|t| !recent.contains(&track_key(&t.track.title, &t.track.artist)))
+        .map(|t| (rank.get(&fold(&t.track.artist)).copied().unwrap_or(usize::MAX), t))
+        .collect();
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.plays.cmp(&a.1.plays)));
+
+    let mut per_artist: HashMap<String, usize> = HashMap::new();
+    ranked
+        .into_iter()
+        .map(|(_, t)| t)
+        .filter(|t| {
+            let count = per_artist.entry(fold(&t.track.artist)).or_default();
+            *count += 1;
+            *count <= PER_ARTIST
+        })
+        .take(CANDIDATES)
+        .collect()
+}
+
+/// The mood tags of `(title, artist)` tracks, most frequent first.
+fn moods_of<'a>(tracks: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (title, artist) in tracks {
+        for mood in moods::of(title, artist) {
+            *counts.entry(mood).or_default() += 1;
+        }
+    }
+    let mut moods: Vec<(String, usize)> = counts.into_iter().collect();
+    moods.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    moods.into_iter().take(6).map(|(m, _)| m).collect()
+}
+
+/// Three things to play next, picked from the library by the model, with
+/// what's playing (`title`, `artist`), the recent history and its moods,
+/// and Last.fm's similar artists to go on.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn next(config: &Config, cancel: &CancelToken, title: Option<&str>, artist: Option<&str>) -> Result<Vec<Suggestion>> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let all    = history::all();
+    let recent = &all[all.len().saturating_sub(RECENT)..];
+    let lead   = artist.filter(|a| !a.is_empty()).or_else(|| recent.last().map(|p| p.artist.as_str()));
+    let similar = match lead {
+        Some(lead) if !config.api.lastfm_key.is_empty() => lastfm::similar_artists(&config.api.lastfm_key, lead, SIMILAR)
+            .await
+            .map(|s| s.into_iter().map(|a| a.name).collect())
+            .unwrap_or_else(|e| {
+                warn!("Last.fm similar artists unavailable: {e}");
+                Vec::new()
+            }),
+        _ => Vec::new(),
+    };
+
+    let mut heard: HashSet<String> = recent.iter().map(|p| track_key(&p.title, &p.artist)).collect();
+    if let (Some(title), Some(artist)) = (title, artist) {
+        heard.insert(track_key(title, artist));
+    }
+    let candidates = candidates(music::played_tracks().await?, &heard, &similar);
+    if candidates.is_empty() {
+        return Err(Error::NotFound("Aucun morceau écouté dans la bibliothèque".into()));
+    }
+    if cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let current = match (title, artist) {
+        (Some(title), Some(artist)) => format!("{artist} — {title}"),
+        _ => "rien".to_string(),
+    };
+    let moods  = moods_of(recent.iter().map(|p| (p.title.as_str(), p.artist.as_str())).chain(title.zip(artist)));
+    let prompt = build_suggestion_prompt(&current, recent, &moods, &similar, &candidates, &config.language());
+    let value  = llm::complete_json(config, &config.suggestion, 500, &prompt, &suggestion_tool(), cancel, None).await?;
+
+    let mut picked = HashSet::new();
+    let suggestions = value["suggestions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| {
+            let n = s["number"].as_u64().filter(|&n| n >= 1)?;
+            let t = candidates.get(n as usize - 1)?;
+            picked.insert(n).then(|| Suggestion {
+                kind:   if s["kind"].as_str() == Some("album") { Kind::Album } else { Kind::Track },
+                track:  t.track.clone(),
+                reason: s["reason"].as_str().unwrap_or("").to_string(),
+            })
+        })
+        .take(SUGGESTIONS)
+        .collect::<Vec<_>>();
+    if suggestions.is_empty() {
+        return Err(Error::NotFound("Aucune suggestion".into()));
+    }
+    Ok(suggestions)
+}
+
+fn build_suggestion_prompt(
+    current: &str,
+    recent: &[Play],
+    moods: &[String],
+    similar: &[String],
+    candidates: &[PlayedTrack],
+    language: &str,
+) -> String {
+    let lang    = language_name(language);
+    let recent  = recent.iter().rev().map(|p| format!("- {} — {}", p.artist, p.title)).collect::<Vec<_>>().join("\n");
+    let moods   = if moods.is_empty() { "inconnues".to_string() } else { moods.join(", ") };
+    let similar = if similar.is_empty() { String::new() } else { format!("Artistes proches selon Last.fm : {}\n", similar.join(", ")) };
+    let list = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let mut line = format!("{}. {} — {} ({})", i + 1, t.track.artist, t.track.title, t.track.album);
+            if !t.genre.is_empty() {
+                line.push_str(&format!(" [{}]", t.genre));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "En cours de lecture : {current}\n\
+         Écoutés récemment, du plus récent au plus ancien :\n{recent}\n\
+         Humeurs de ces morceaux et de celui en cours : {moods}\n{similar}\n\
+         Morceaux de sa bibliothèque, numérotés, genre entre crochets :\n{list}\n\n\
+         Propose {SUGGESTIONS} choix à écouter ensuite, uniquement dans cette liste : qui prolongent l'humeur \
+         du moment ou l'infléchissent en douceur, variés entre eux. Pour chacun, dis s'il faut lancer le morceau \
+         (`track`) ou tout son album (`album`), et donne une raison en une phrase courte, en {lang}.\n\n\
+         Réponds en appelant l'outil `next_suggestions`."
+    )
+}
+
+fn suggestion_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "next_suggestions",
+        description: "Enregistre les suggestions d'écoute",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "suggestions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "number": { "type": "integer", "description": "Numéro du morceau dans la liste" },
+                            "kind":   { "type": "string", "enum": ["track", "album"] },
+                            "reason": { "type": "string", "description": "Une phrase" }
+                        },
+                        "required": ["number", "kind", "reason"]
+                    }
+                }
+            },
+            "required": ["suggestions"]
+        }),
+    }
+}
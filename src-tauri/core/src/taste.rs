@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::cache;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history::{self, Count, Play};
+use crate::llm::{self, language_name};
+use crate::moods;
+use crate::music;
+use crate::net;
+use crate::normalize::track_key;
+
+const TOP: usize = 10;
+
+/// One trait of the profile: a genre, an era or a theme, with the model's
+/// word on it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trait {
+    pub name: String,
+    pub note: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TasteProfile {
+    /// RFC 3339.
+    pub generated_at: String,
+    pub plays: usize,
+    /// The counts the profile was drawn from.
+    pub top_artists: Vec<Count>,
+    pub top_genres: Vec<Count>,
+    /// Decades of release ("1990s"), for the tracks Music knows the year of.
+    pub decades: Vec<Count>,
+    /// Mood and theme tags of the tracks heard, from the lyrics analyses.
+    pub themes: Vec<Count>,
+    pub dominant_genres: Vec<Trait>,
+    pub eras: Vec<Trait>,
+    pub lyrical_themes: Vec<Trait>,
+    /// A paragraph tying it together.
+    pub summary: String,
+}
+
+/// Release decades of `plays`, by way of the years Music has for its
+/// library. Without Music (or without years) there are none.
+async fn decades(plays: &[Play]) -> Vec<Count> {
+    let library = match music::played_tracks().await {
+        Ok(library) => library,
+        Err(e) => {
+            debug!("no release years: {e}");
+            return Vec::new();
+        }
+    };
+    let years: HashMap<String, u32> = library
+        .into_iter()
+        .filter(|t| t.year > 0)
+        .map(|t| (track_key(&t.track.title, &t.track.artist), t.year))
+        .collect();
+    history::top(
+        plays,
+        |p| years.get(&track_key(&p.title, &p.artist)).map(|y| format!("{}s", y / 10 * 10)).unwrap_or_default(),
+        TOP,
+    )
+}
+
+/// Tag counts over `plays`: each play counts once for each of its track's
+/// tags.
+fn themes(plays: &[Play]) -> Vec<Count> {
+    let mut counts: HashMap<String, (usize, u64)> = HashMap::new();
+    for play in plays {
+        for mood in moods::of(&play.title, &play.artist) {
+            let entry = counts.entry(mood).or_default();
+            entry.0 += 1;
+            entry.1 += play.listened_secs;
+        }
+    }
+    let mut themes: Vec<Count> = counts
+        .into_iter()
+        .map(|(name, (plays, secs))| Count { name, plays, minutes: secs / 60 })
+        .collect();
+    themes.sort_by(|a, b| b.plays.cmp(&a.plays).then_with(|| a.name.cmp(&b.name)));
+    themes.truncate(TOP);
+    themes
+}
+
+/// What the whole history says about the listener's taste: genres, eras and
+/// the themes their lyrics keep coming back to. Made once a month, or again
+/// with `refresh`.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn profile(config: &Config, cancel: &CancelToken, refresh: bool) -> Result<TasteProfile> {
+    let plays = history::all();
+    if plays.is_empty() {
+        return Err(Error::NotFound("Aucune écoute enregistrée".into()));
+    }
+
+    let language  = config.language();
+    let cache_key = format!("{}|||{language}", Local::now().format("%Y-%m"));
+    if !refresh {
+        if let Some(cached) = cache::read::<TasteProfile>("taste_profile", &cache_key) {
+            return Ok(cached);
+        }
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let mut profile = TasteProfile {
+        generated_at:    Local::now().to_rfc3339(),
+        plays:           plays.len(),
+        top_artists:     history::top(&plays, |p| p.artist.clone(), TOP),
+        top_genres:      history::top(&plays, |p| p.genre.clone(), TOP),
+        decades:         decades(&plays).await,
+        themes:          themes(&plays),
+        dominant_genres: Vec::new(),
+        eras:            Vec::new(),
+        lyrical_themes:  Vec::new(),
+        summary:         String::new(),
+    };
+
+    let prompt = build_taste_prompt(&profile, &language);
+    let value  = llm::complete_json(config, &config.taste_profile, 900, &prompt, &taste_tool(), cancel, None).await?;
+    let traits = |field: &str| serde_json::from_value::<Vec<Trait>>(value[field].clone()).unwrap_or_default();
+    profile.dominant_genres = traits("genres");
+    profile.eras            = traits("eras");
+    profile.lyrical_themes  = traits("themes");
+    profile.summary         = value["summary"].as_str().unwrap_or("").to_string();
+
+    cache::write("taste_profile", &cache_key, &profile);
+    Ok(profile)
+}
+
+fn counts(counts: &[Count]) -> String {
+    if counts.is_empty() {
+        return "inconnu".to_string();
+    }
+    counts.iter().map(|c| format!("{} ({} écoutes)", c.name, c.plays)).collect::<Vec<_>>().join(", ")
+}
+
+fn build_taste_prompt(profile: &TasteProfile, language: &str) -> String {
+    let lang = language_name(language);
+    format!(
+        "Voici l'historique d'écoute d'une personne, {plays} morceaux en tout.\n\
+         Artistes les plus écoutés : {artists}\n\
+         Genres : {genres}\n\
+         Décennies de sortie : {decades}\n\
+         Humeurs et thèmes des paroles : {themes}\n\n\
+         Dresse son profil de goûts, en {lang} : ses genres de prédilection, les époques vers lesquelles elle revient \
+         et les thèmes de paroles qui l'attirent, chacun avec une courte remarque, puis un paragraphe qui relie le tout. \
+         Appuie-toi uniquement sur ces données ; laisse une liste vide si elles ne disent rien.\n\n\
+         Réponds en appelant l'outil `taste_profile`.",
+        plays   = profile.plays,
+        artists = counts(&profile.top_artists),
+        genres  = counts(&profile.top_genres),
+        decades = counts(&profile.decades),
+        themes  = counts(&profile.themes),
+    )
+}
+
+fn taste_tool() -> llm::Tool {
+    let traits = |what: &str| {
+        serde_json::json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "note": { "type": "string", "description": "Une phrase" }
+                },
+                "required": ["name", "note"]
+            },
+            "description": what
+        })
+    };
+    llm::Tool {
+        name:        "taste_profile",
+        description: "Enregistre le profil de goûts musicaux",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "genres":  traits("2 à 4 genres dominants"),
+                "eras":    traits("1 à 3 époques de prédilection"),
+                "themes":  traits("2 à 4 thèmes de paroles récurrents"),
+                "summary": { "type": "string", "description": "Le profil en un paragraphe" }
+            },
+            "required": ["genres", "eras", "themes", "summary"]
+        }),
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::config::{self, Config};
+use crate::llm::Usage;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MonthUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub requests: u64,
+    #[serde(default)]
+    pub by_model: BTreeMap<String, u64>,
+}
+
+impl MonthUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Persistent token ledger, keyed by `YYYY-MM`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ledger {
+    #[serde(default)]
+    months: BTreeMap<String, MonthUsage>,
+}
+
+fn ledger_path() -> PathBuf {
+    config::data_dir().join("usage.json")
+}
+
+fn ledger() -> &'static Mutex<Ledger> {
+    static LEDGER: OnceLock<Mutex<Ledger>> = OnceLock::new();
+    LEDGER.get_or_init(|| {
+        let ledger = std::fs::read_to_string(ledger_path())
+            .ok()
+            .and_then(|c| {
+                serde_json::from_str(&c)
+                    .map_err(|e| warn!("corrupt usage ledger, starting fresh: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Mutex::new(ledger)
+    })
+}
+
+fn current_month() -> String {
+    chrono::Local::now().format("%Y-%m").to_string()
+}
+
+fn save(ledger: &Ledger) {
+    let path = ledger_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(ledger) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                error!(path = %path.display(), "cannot write usage ledger: {e}");
+            }
+        }
+        Err(e) => error!("cannot serialize usage ledger: {e}"),
+    }
+}
+
+/// Adds one completion's token counts to the current month and persists.
+pub fn record(model: &str, usage: Usage) {
+    let Ok(mut ledger) = ledger().lock() else { return };
+    let month = ledger.months.entry(current_month()).or_default();
+    let total = usage.input_tokens + usage.output_tokens;
+
+    month.input_tokens  += usage.input_tokens;
+    month.output_tokens += usage.output_tokens;
+    month.requests      += 1;
+    *month.by_model.entry(model.to_string()).or_default() += total;
+
+    save(&ledger);
+}
+
+fn this_month() -> MonthUsage {
+    ledger()
+        .lock()
+        .ok()
+        .and_then(|l| l.months.get(&current_month()).cloned())
+        .unwrap_or_default()
+}
+
+pub fn over_budget(config: &Config) -> bool {
+    config
+        .usage
+        .monthly_token_budget
+        .is_some_and(|budget| this_month().total_tokens() >= budget)
+}
+
+#[derive(Serialize)]
+pub struct UsageStats {
+    month: String,
+    current: MonthUsage,
+    budget: Option<u64>,
+    remaining: Option<u64>,
+    history: BTreeMap<String, MonthUsage>,
+}
+
+/// This month's usage against the budget, and the months before.
+pub fn stats(config: &Config) -> UsageStats {
+    let current = this_month();
+    let budget  = config.usage.monthly_token_budget;
+    let history = ledger().lock().map(|l| l.months.clone()).unwrap_or_default();
+
+    UsageStats {
+        month: current_month(),
+        remaining: budget.map(|b| b.saturating_sub(current.total_tokens())),
+        current,
+        budget,
+        history,
+    }
+}
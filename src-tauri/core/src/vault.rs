@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use tracing::{info, warn};
+
+use crate::album::AlbumInfo;
+use crate::bookmarks::Kind;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::lyrics::LyricsAnalysis;
+
+/// Subfolders of the vault, one per kind of note.
+const ALBUMS: &str = "Albums";
+const TRACKS: &str = "Morceaux";
+
+/// `Artist - Name`, with the characters Finder or Obsidian's links dislike
+/// replaced. Also how a track's note links to its album's.
+fn note_name(artist: &str, name: &str) -> String {
+    let name: String = format!("{artist} - {name}")
+        .chars()
+        .map(|c| if matches!(c, '/' | ':' | '\\' | '\0' | '#' | '^' | '[' | ']' | '|') { '_' } else { c })
+        .collect();
+    name.trim_start_matches('.').to_string()
+}
+
+/// A YAML string, quoted so a colon or a leading dash in a title can't
+/// break the frontmatter.
+fn yaml(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn yaml_list(key: &str, items: &[String]) -> String {
+    if items.is_empty() {
+        return format!("{key}: []\n");
+    }
+    let items: String = items.iter().map(|i| format!("  - {}\n", yaml(i))).collect();
+    format!("{key}:\n{items}")
+}
+
+pub fn album_markdown(artist: &str, album: &str, info: &AlbumInfo) -> String {
+    let mut md = format!(
+        "---\ntype: album\nartist: {}\nalbum: {}\nyear: {}\ngenre: {}\nlabel: {}\ncountry: {}\n",
+        yaml(artist),
+        yaml(album),
+        yaml(&info.release_year),
+        yaml(&info.genre),
+        yaml(&info.label),
+        yaml(&info.country),
+    );
+    md.push_str(&yaml_list("producers", &info.producers));
+    md.push_str(&yaml_list("lastfm_tags", &info.tags));
+    if !info.release_group_mbid.is_empty() {
+        md.push_str(&format!("musicbrainz: {}\n", yaml(&info.release_group_mbid)));
+    }
+    md.push_str(&format!("exported: {}\n---\n\n# {album}\n\n", Local::now().format("%Y-%m-%d")));
+
+    let byline = [artist, info.release_year.as_str()].into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(", ");
+    md.push_str(&format!("*{byline}*\n\n{}\n", info.context.trim()));
+    if !info.notable_fact.trim().is_empty() {
+        md.push_str(&format!("\n## Le saviez-vous ?\n\n{}\n", info.notable_fact.trim()));
+    }
+    if !info.charts.is_empty() {
+        md.push_str("\n## Classements\n\n");
+        md.extend(info.charts.iter().map(|c| format!("- {} : n° {}\n", c.chart, c.position)));
+    }
+    if !info.certifications.is_empty() {
+        md.push_str("\n## Certifications\n\n");
+        md.extend(info.certifications.iter().map(|c| format!("- {} : {}\n", c.body, c.level)));
+    }
+    md
+}
+
+pub fn lyrics_markdown(title: &str, artist: &str, album: &str, analysis: &LyricsAnalysis) -> String {
+    let mut md = format!("---\ntype: lyrics\ntitle: {}\nartist: {}\n", yaml(title), yaml(artist));
+    if !album.is_empty() {
+        md.push_str(&format!("album: {}\n", yaml(album)));
+    }
+    md.push_str(&yaml_list("moods", &analysis.moods));
+    md.push_str(&format!("exported: {}\n---\n\n# {title}\n\n*{artist}*", Local::now().format("%Y-%m-%d")));
+    if !album.is_empty() {
+        md.push_str(&format!(" — [[{}|{album}]]", note_name(artist, album)));
+    }
+    md.push_str(&format!("\n\n{}\n", analysis.interpretation.trim()));
+    md
+}
+
+/// `dir`, else `[vault] path`, with a leading `~/` taken as the home folder.
+fn vault_dir(config: &Config, dir: Option<&str>) -> Result<PathBuf> {
+    let dir = dir.filter(|d| !d.trim().is_empty()).unwrap_or(config.vault.path.trim());
+    if dir.is_empty() {
+        return Err(Error::Invalid("aucun dossier d'export configuré (`[vault] path`)".into()));
+    }
+    Ok(match dir.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(dir),
+    })
+}
+
+/// Writes `markdown` to `<vault>/<folder>/<name>.md`, replacing the note an
+/// earlier export left there.
+fn write(vault: &Path, folder: &str, name: &str, markdown: &str) -> Result<PathBuf> {
+    let dir = vault.join(folder);
+    std::fs::create_dir_all(&dir).map_err(|e| Error::Io(e.to_string()))?;
+    let path = dir.join(format!("{name}.md"));
+    std::fs::write(&path, markdown).map_err(|e| Error::Io(e.to_string()))?;
+    info!(path = %path.display(), "analysis exported");
+    Ok(path)
+}
+
+pub fn export_album(config: &Config, artist: &str, album: &str, info: &AlbumInfo, dir: Option<&str>) -> Result<PathBuf> {
+    write(&vault_dir(config, dir)?, ALBUMS, &note_name(artist, album), &album_markdown(artist, album, info))
+}
+
+pub fn export_lyrics(
+    config: &Config,
+    title: &str,
+    artist: &str,
+    album: &str,
+    analysis: &LyricsAnalysis,
+    dir: Option<&str>,
+) -> Result<PathBuf> {
+    write(&vault_dir(config, dir)?, TRACKS, &note_name(artist, title), &lyrics_markdown(title, artist, album, analysis))
+}
+
+/// Exports an analysis as its command returned it (`content`), for the
+/// window, which holds album info nothing else keeps. `title` is ignored for
+/// album info.
+pub fn export(
+    config: &Config,
+    kind: Kind,
+    title: &str,
+    artist: &str,
+    album: &str,
+    content: serde_json::Value,
+    dir: Option<&str>,
+) -> Result<PathBuf> {
+    let invalid = |e: serde_json::Error| Error::Invalid(format!("analyse illisible : {e}"));
+    match kind {
+        Kind::AlbumInfo => export_album(config, artist, album, &serde_json::from_value(content).map_err(invalid)?, dir),
+        Kind::LyricsAnalysis => export_lyrics(config, title, artist, album, &serde_json::from_value(content).map_err(invalid)?, dir),
+    }
+}
+
+/// With `[vault] auto_export`, writes album info as soon as it's made; a
+/// failure is only logged.
+pub fn album_completed(config: &Config, artist: &str, album: &str, info: &AlbumInfo) {
+    if config.vault.auto_export {
+        if let Err(e) = export_album(config, artist, album, info, None) {
+            warn!("album info not exported: {e}");
+        }
+    }
+}
+
+/// The same for a lyrics analysis.
+pub fn lyrics_completed(config: &Config, title: &str, artist: &str, album: &str, analysis: &LyricsAnalysis) {
+    if config.vault.auto_export {
+        if let Err(e) = export_lyrics(config, title, artist, album, analysis, None) {
+            warn!("lyrics analysis not exported: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lyrics_note_has_frontmatter_and_links_its_album() {
+        let analysis = LyricsAnalysis { interpretation: "Un adieu.\n".into(), moods: vec!["nostalgie".into()] };
+        let md = lyrics_markdown("Partir: là-bas", "Zaz", "Effet \"miroir\"", &analysis);
+        assert!(md.starts_with("---\ntype: lyrics\ntitle: \"Partir: là-bas\"\nartist: \"Zaz\"\nalbum: \"Effet \\\"miroir\\\"\"\n"));
+        assert!(md.contains("moods:\n  - \"nostalgie\"\n"));
+        assert!(md.ends_with("*Zaz* — [[Zaz - Effet \"miroir\"|Effet \"miroir\"]]\n\nUn adieu.\n"));
+        assert_eq!(note_name("AC/DC", "Back in Black"), "AC_DC - Back in Black");
+    }
+}
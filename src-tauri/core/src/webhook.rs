@@ -0,0 +1,69 @@
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::net::{self, http};
+
+/// Sent to every `[webhook] urls` entry as JSON: the event name and time,
+/// plus the event's own fields.
+#[derive(Serialize)]
+struct Payload<'a, T: Serialize> {
+    event: &'a str,
+    /// RFC 3339, local time.
+    sent_at: String,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// POSTs the event to each configured URL in the background. Retries and
+/// backoff are [`net::send`]'s; a hook that still fails is only logged.
+fn post<T: Serialize>(config: &Config, event: &str, data: T) {
+    if config.webhook.urls.is_empty() {
+        return;
+    }
+    let payload = Payload { event, sent_at: chrono::Local::now().to_rfc3339(), data };
+    let body    = match serde_json::to_value(&payload) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!(event, "webhook payload failed: {e}");
+            return;
+        }
+    };
+    for url in config.webhook.urls.clone() {
+        let body = body.clone();
+        tokio::spawn(async move {
+            match net::send("webhook", || http().post(&url).json(&body)).await {
+                Ok(_) => debug!(url, "webhook delivered"),
+                Err(e) => warn!(url, "webhook failed: {e}"),
+            }
+        });
+    }
+}
+
+#[derive(Serialize)]
+struct TrackChanged<'a, T: Serialize> {
+    /// `null` when playback stopped.
+    track: Option<&'a T>,
+}
+
+/// `track_changed`, on every change of what the poller sees.
+pub fn track_changed<T: Serialize>(config: &Config, track: Option<&T>) {
+    post(config, "track_changed", TrackChanged { track });
+}
+
+#[derive(Serialize)]
+struct AnalysisCompleted<'a, S: Serialize, R: Serialize> {
+    /// The command that produced it: `album_info`, `artist_info`,
+    /// `lyrics_analysis`.
+    kind: &'a str,
+    /// What it is about, e.g. `{ "artist": …, "album": … }`.
+    subject: S,
+    result: &'a R,
+}
+
+/// `analysis_completed`, when `[webhook] on_analysis` is set.
+pub fn analysis_completed<S: Serialize, R: Serialize>(config: &Config, kind: &str, subject: S, result: &R) {
+    if config.webhook.on_analysis {
+        post(config, "analysis_completed", AnalysisCompleted { kind, subject, result });
+    }
+}
@@ -0,0 +1,65 @@
+use crate::net::{self, http};
+use crate::normalize;
+use crate::{path_encode, url_encode};
+
+/// Wikimedia asks API clients to identify themselves.
+const USER_AGENT: &str = "enhanced-music/0.1 (https://github.com/Fendry02/enhanced-music)";
+
+async fn get(url: &str) -> Option<serde_json::Value> {
+    net::get_json("wikipedia", || http().get(url).header("User-Agent", USER_AGENT)).await.ok()
+}
+
+/// Intro of the first search result whose title contains `must_contain`,
+/// from the `lang` edition. Disambiguation pages are skipped.
+async fn summary(lang: &str, query: &str, must_contain: &str) -> Option<String> {
+    let search = get(&format!(
+        "https://{lang}.wikipedia.org/w/rest.php/v1/search/page?q={}&limit=5",
+        url_encode(query)
+    ))
+    .await?;
+
+    let wanted = must_contain.to_lowercase();
+    let key = search["pages"]
+        .as_array()?
+        .iter()
+        .find(|p| p["title"].as_str().is_some_and(|t| t.to_lowercase().contains(&wanted)))?["key"]
+        .as_str()?
+        .to_string();
+
+    let page = get(&format!("https://{lang}.wikipedia.org/api/rest_v1/page/summary/{}", path_encode(&key))).await?;
+    if page["type"] == "disambiguation" {
+        return None;
+    }
+    page["extract"]
+        .as_str()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.chars().take(1500).collect())
+}
+
+/// Tries the output language's edition first, then English, which covers
+/// far more albums.
+async fn summary_any(language: &str, query: &str, must_contain: &str) -> String {
+    if language != "en" {
+        if let Some(s) = summary(language, query, must_contain).await {
+            return s;
+        }
+    }
+    summary("en", query, must_contain).await.unwrap_or_default()
+}
+
+pub async fn album_summary(language: &str, artist: &str, album: &str) -> String {
+    let album = normalize::title(album);
+    summary_any(language, &format!("{album} {artist} album"), &album).await
+}
+
+pub async fn artist_summary(language: &str, artist: &str) -> String {
+    summary_any(language, artist, artist).await
+}
+
+/// Page of a classical work, e.g. "Symphony No. 5 (Beethoven)": matched on
+/// the work's name without its key or catalogue number.
+pub async fn work_summary(language: &str, composer: &str, work: &str) -> String {
+    let name = work.split([',', '(']).next().unwrap_or(work);
+    let name = name.split(" in ").next().unwrap_or(name).trim();
+    summary_any(language, &format!("{work} {composer}"), name).await
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::cache;
+use crate::cancel::CancelToken;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::history::{self, Count, Play};
+use crate::llm::{self, language_name};
+use crate::net;
+
+const TOP: usize = 10;
+
+const MONTHS: [&str; 12] = [
+    "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MonthGenre {
+    /// 1 to 12.
+    pub month: u32,
+    pub genre: String,
+    pub plays: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Day {
+    pub date: String,
+    pub minutes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Wrapped {
+    pub year: i32,
+    pub plays: usize,
+    pub listening_hours: f64,
+    pub artists: usize,
+    pub top_artists: Vec<Count>,
+    pub top_tracks: Vec<Count>,
+    pub top_albums: Vec<Count>,
+    pub top_genres: Vec<Count>,
+    /// Each month's most played genre, months without plays left out: how
+    /// the year's taste moved.
+    pub genre_by_month: Vec<MonthGenre>,
+    /// The day with the most listening.
+    pub busiest_day: Option<Day>,
+    /// The model's account of the year, a few paragraphs.
+    pub narrative: String,
+}
+
+fn year_plays(year: i32) -> Vec<Play> {
+    let Some(start) = NaiveDate::from_ymd_opt(year, 1, 1).and_then(|d| Local.from_local_datetime(&d.into()).earliest()) else {
+        return Vec::new();
+    };
+    history::since(start).into_iter().filter(|p| p.started().is_some_and(|s| s.year() == year)).collect()
+}
+
+fn genre_by_month(plays: &[Play]) -> Vec<MonthGenre> {
+    (1..=12)
+        .filter_map(|month| {
+            let in_month: Vec<Play> = plays.iter().filter(|p| p.started().is_some_and(|s| s.month() == month)).cloned().collect();
+            let top = history::top(&in_month, |p| p.genre.clone(), 1).into_iter().next()?;
+            Some(MonthGenre { month, genre: top.name, plays: top.plays })
+        })
+        .collect()
+}
+
+fn busiest_day(plays: &[Play]) -> Option<Day> {
+    let mut days: HashMap<String, u64> = HashMap::new();
+    for play in plays {
+        if let Some(started) = play.started() {
+            *days.entry(started.date_naive().to_string()).or_default() += play.listened_secs;
+        }
+    }
+    days.into_iter().max_by_key(|(date, secs)| (*secs, std::cmp::Reverse(date.clone()))).map(|(date, secs)| Day { date, minutes: secs / 60 })
+}
+
+/// The year in review from the local history: tops, hours, how the genres
+/// moved month to month, and the model's narrative. Cached until a new play
+/// of that year comes in.
+#[tracing::instrument(skip(config, cancel))]
+pub async fn generate(config: &Config, cancel: &CancelToken, year: i32) -> Result<Wrapped> {
+    let plays = year_plays(year);
+    if plays.is_empty() {
+        return Err(Error::NotFound(format!("Aucune écoute enregistrée en {year}")));
+    }
+
+    let language  = config.language();
+    let cache_key = format!("{year}|||{}|||{language}", plays.len());
+    if let Some(cached) = cache::read::<Wrapped>("wrapped", &cache_key) {
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    if config.llm_key().is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let secs = plays.iter().map(|p| p.listened_secs).sum::<u64>();
+    let mut wrapped = Wrapped {
+        year,
+        plays:           plays.len(),
+        listening_hours: (secs as f64 / 360.0).round() / 10.0,
+        artists:         history::top(&plays, |p| p.artist.to_lowercase(), usize::MAX).len(),
+        top_artists:     history::top(&plays, |p| p.artist.clone(), TOP),
+        top_tracks:      history::top(&plays, |p| format!("{} — {}", p.artist, p.title), TOP),
+        top_albums:      history::top(&plays, |p| if p.album.is_empty() { String::new() } else { format!("{} — {}", p.artist, p.album) }, TOP),
+        top_genres:      history::top(&plays, |p| p.genre.clone(), TOP),
+        genre_by_month:  genre_by_month(&plays),
+        busiest_day:     busiest_day(&plays),
+        narrative:       String::new(),
+    };
+
+    let prompt = build_wrapped_prompt(&wrapped, &language);
+    let value  = llm::complete_json(config, &config.wrapped, 1200, &prompt, &wrapped_tool(), cancel, None).await?;
+    wrapped.narrative = value["narrative"].as_str().unwrap_or("").to_string();
+
+    cache::write("wrapped", &cache_key, &wrapped);
+    Ok(wrapped)
+}
+
+fn counts(counts: &[Count]) -> String {
+    counts.iter().map(|c| format!("{} ({} écoutes, {} min)", c.name, c.plays, c.minutes)).collect::<Vec<_>>().join(", ")
+}
+
+fn build_wrapped_prompt(w: &Wrapped, language: &str) -> String {
+    let lang   = language_name(language);
+    let months = w
+        .genre_by_month
+        .iter()
+        .map(|m| format!("{} : {}", MONTHS[m.month as usize - 1], m.genre))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "Voici l'année musicale {year} d'une personne : {plays} morceaux, {hours} heures d'écoute, {artists} artistes.\n\
+         Artistes les plus écoutés : {top_artists}\n\
+         Morceaux : {top_tracks}\n\
+         Albums : {top_albums}\n\
+         Genres : {top_genres}\n\
+         Genre dominant mois par mois : {months}\n\n\
+         Écris le récit de son année en musique, en {lang}, en t'adressant à elle : 3 à 4 courts paragraphes sur ses fidélités, \
+         l'évolution de ses goûts au fil des mois et ce que ces choix disent d'elle. Appuie-toi uniquement sur ces données.\n\n\
+         Réponds en appelant l'outil `year_in_review`.",
+        year        = w.year,
+        plays       = w.plays,
+        hours       = w.listening_hours,
+        artists     = w.artists,
+        top_artists = counts(&w.top_artists),
+        top_tracks  = counts(&w.top_tracks),
+        top_albums  = counts(&w.top_albums),
+        top_genres  = counts(&w.top_genres),
+    )
+}
+
+fn wrapped_tool() -> llm::Tool {
+    llm::Tool {
+        name:        "year_in_review",
+        description: "Enregistre le récit de l'année musicale",
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "narrative": {
+                    "type": "string",
+                    "description": "Le récit, 3-4 paragraphes séparés par une ligne vide"
+                }
+            },
+            "required": ["narrative"]
+        }),
+    }
+}
+
+// ─── Export ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+fn list(counts: &[Count], item: impl Fn(&Count) -> String) -> Vec<String> {
+    counts.iter().enumerate().map(|(i, c)| format!("{}. {}", i + 1, item(c))).collect()
+}
+
+pub fn to_markdown(w: &Wrapped) -> String {
+    let mut out = vec![
+        format!("# Mon année {} en musique", w.year),
+        String::new(),
+        format!("**{}** morceaux · **{}** heures d'écoute · **{}** artistes", w.plays, w.listening_hours, w.artists),
+        String::new(),
+        w.narrative.clone(),
+    ];
+    let sections = [("Artistes", &w.top_artists), ("Morceaux", &w.top_tracks), ("Albums", &w.top_albums), ("Genres", &w.top_genres)];
+    for (title, counts) in sections.into_iter().filter(|(_, c)| !c.is_empty()) {
+        out.extend([String::new(), format!("## {title}"), String::new()]);
+        out.extend(list(counts, |c| format!("{} — {} écoutes", c.name, c.plays)));
+    }
+    if !w.genre_by_month.is_empty() {
+        out.extend([String::new(), "## Mois par mois".to_string(), String::new()]);
+        out.extend(w.genre_by_month.iter().map(|m| format!("- {} : {}", MONTHS[m.month as usize - 1], m.genre)));
+    }
+    if let Some(day) = &w.busiest_day {
+        out.extend([String::new(), format!("Journée la plus musicale : {} ({} min)", day.date, day.minutes)]);
+    }
+    out.push(String::new());
+    out.join("\n")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn to_html(w: &Wrapped) -> String {
+    let mut body = vec![
+        format!("<h1>Mon année {} en musique</h1>", w.year),
+        format!(
+            "<p class=\"stats\"><b>{}</b> morceaux · <b>{}</b> heures d'écoute · <b>{}</b> artistes</p>",
+            w.plays, w.listening_hours, w.artists
+        ),
+    ];
+    body.extend(w.narrative.split("\n\n").filter(|p| !p.trim().is_empty()).map(|p| format!("<p>{}</p>", escape(p.trim()))));
+    let sections = [("Artistes", &w.top_artists), ("Morceaux", &w.top_tracks), ("Albums", &w.top_albums), ("Genres", &w.top_genres)];
+    for (title, counts) in sections.into_iter().filter(|(_, c)| !c.is_empty()) {
+        let items: String = counts.iter().map(|c| format!("<li>{} <span>{} écoutes</span></li>", escape(&c.name), c.plays)).collect();
+        body.push(format!("<h2>{title}</h2><ol>{items}</ol>"));
+    }
+    if !w.genre_by_month.is_empty() {
+        let items: String = w
+            .genre_by_month
+            .iter()
+            .map(|m| format!("<li>{} : {}</li>", MONTHS[m.month as usize - 1], escape(&m.genre)))
+            .collect();
+        body.push(format!("<h2>Mois par mois</h2><ul>{items}</ul>"));
+    }
+    if let Some(day) = &w.busiest_day {
+        body.push(format!("<p>Journée la plus musicale : {} ({} min)</p>", day.date, day.minutes));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"fr\">\n<head>\n<meta charset=\"utf-8\">\n<title>Mon année {year} en musique</title>\n\
+         <style>body{{font:16px/1.5 -apple-system,sans-serif;max-width:40em;margin:3em auto;padding:0 1em}}\
+         .stats{{font-size:1.2em}}li span{{color:#888}}</style>\n</head>\n<body>\n{body}\n</body>\n</html>\n",
+        year = w.year,
+        body = body.join("\n"),
+    )
+}
+
+/// Writes the year in review to `path` as Markdown or HTML.
+pub async fn export(config: &Config, cancel: &CancelToken, year: i32, format: Format, path: &Path) -> Result<String> {
+    let wrapped = generate(config, cancel, year).await?;
+    let content = match format {
+        Format::Markdown => to_markdown(&wrapped),
+        Format::Html     => to_html(&wrapped),
+    };
+    std::fs::write(path, content).map_err(|e| Error::Io(e.to_string()))?;
+    info!(path = %path.display(), year, "year in review exported");
+    Ok(path.display().to_string())
+}
@@ -0,0 +1,169 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveTime, Timelike, Weekday};
+use enhanced_music_core::music;
+use serde::Serialize;
+use tauri::Manager;
+use tracing::{info, warn};
+
+use crate::config::{self, AlarmConfig, SharedConfig};
+use crate::error::{Error, Result};
+
+/// How often the scheduler looks at the clock; well under a minute so no
+/// `HH:MM` slot is missed.
+const TICK: Duration = Duration::from_secs(15);
+
+/// Volume steps of the fade-in.
+const RAMP_STEP: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+pub struct Alarm {
+    /// Position in `[[alarms]]`, used to address it in the other commands.
+    index: usize,
+    #[serde(flatten)]
+    alarm: AlarmConfig,
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| Error::Invalid(format!("heure « {time} » (HH:MM attendu)")))
+}
+
+fn parse_days(days: &[String]) -> Result<Vec<Weekday>> {
+    days.iter()
+        .map(|d| Weekday::from_str(d.trim()).map_err(|_| Error::Invalid(format!("jour « {d} »"))))
+        .collect()
+}
+
+/// Whether `alarm` should go off at this minute.
+fn is_due(alarm: &AlarmConfig, now: chrono::DateTime<Local>) -> bool {
+    let (Ok(time), Ok(days)) = (parse_time(&alarm.time), parse_days(&alarm.days)) else {
+        return false;
+    };
+    alarm.enabled
+        && time.hour() == now.hour()
+        && time.minute() == now.minute()
+        && (days.is_empty() || days.contains(&now.weekday()))
+}
+
+/// Starts the playlist silently, then raises the volume step by step.
+async fn ring(alarm: &AlarmConfig) -> Result<()> {
+    let target = alarm.volume.min(100);
+    let steps  = (alarm.ramp_secs / RAMP_STEP.as_secs()).max(1);
+    music::set_volume(if alarm.ramp_secs == 0 { target } else { 0 }).await?;
+    music::play_playlist(&alarm.playlist).await?;
+    if alarm.ramp_secs == 0 {
+        return Ok(());
+    }
+    for step in 1..=steps {
+        tokio::time::sleep(RAMP_STEP).await;
+        music::set_volume((target as u64 * step / steps) as u8).await?;
+    }
+    Ok(())
+}
+
+/// Runs the alarm scheduler for the life of the app. Each alarm fires at
+/// most once per minute it matches, whichever tick sees it first.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut fired: Option<String> = None;
+        loop {
+            let now    = Local::now();
+            let minute = now.format("%Y-%m-%d %H:%M").to_string();
+            let config = app.state::<SharedConfig>().get();
+            for (index, alarm) in config.alarms.iter().enumerate() {
+                let key = format!("{minute}#{index}");
+                if !is_due(alarm, now) || fired.as_deref() == Some(key.as_str()) {
+                    continue;
+                }
+                fired = Some(key);
+                info!(index, playlist = %alarm.playlist, "alarm");
+                let alarm = alarm.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = ring(&alarm).await {
+                        warn!("alarm failed: {e}");
+                    }
+                });
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+// ─── Commands ─────────────────────────────────────────────────────────────────
+
+fn alarms(app: &tauri::AppHandle) -> Vec<Alarm> {
+    let config = app.state::<SharedConfig>().get();
+    config.alarms.iter().cloned().enumerate().map(|(index, alarm)| Alarm { index, alarm }).collect()
+}
+
+/// Rewrites `[[alarms]]` in `config.toml` from `list`.
+fn save(app: &tauri::AppHandle, list: &[AlarmConfig]) -> Result<Vec<Alarm>> {
+    use toml_edit::{value, Array, ArrayOfTables, Item, Table};
+
+    let mut tables = ArrayOfTables::new();
+    for alarm in list {
+        let mut table = Table::new();
+        table["time"]     = value(alarm.time.trim());
+        table["playlist"] = value(alarm.playlist.as_str());
+        if !alarm.days.is_empty() {
+            table["days"] = value(alarm.days.iter().map(|d| d.trim().to_lowercase()).collect::<Array>());
+        }
+        table["volume"]    = value(alarm.volume.min(100) as i64);
+        table["ramp_secs"] = value(alarm.ramp_secs as i64);
+        table["enabled"]   = value(alarm.enabled);
+        tables.push(table);
+    }
+    config::edit(app, |doc| {
+        if tables.is_empty() {
+            doc.remove("alarms");
+        } else {
+            doc["alarms"] = Item::ArrayOfTables(tables);
+        }
+    })?;
+    Ok(alarms(app))
+}
+
+fn at(list: &mut [AlarmConfig], index: usize) -> Result<&mut AlarmConfig> {
+    list.get_mut(index).ok_or_else(|| Error::NotFound("Alarme introuvable".into()))
+}
+
+/// The alarms in `config.toml`.
+#[tauri::command]
+pub fn list_alarms(app: tauri::AppHandle) -> Vec<Alarm> {
+    alarms(&app)
+}
+
+/// Appends an alarm to `config.toml`.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn add_alarm(alarm: AlarmConfig, app: tauri::AppHandle) -> Result<Vec<Alarm>> {
+    parse_time(&alarm.time)?;
+    parse_days(&alarm.days)?;
+    if alarm.playlist.trim().is_empty() {
+        return Err(Error::Invalid("playlist vide".into()));
+    }
+    let mut list = app.state::<SharedConfig>().get().alarms.clone();
+    list.push(alarm);
+    save(&app, &list)
+}
+
+/// Removes the alarm at `index`.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn remove_alarm(index: usize, app: tauri::AppHandle) -> Result<Vec<Alarm>> {
+    let mut list = app.state::<SharedConfig>().get().alarms.clone();
+    at(&mut list, index)?;
+    list.remove(index);
+    save(&app, &list)
+}
+
+/// Switches an alarm on or off without removing it.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn set_alarm_enabled(index: usize, enabled: bool, app: tauri::AppHandle) -> Result<Vec<Alarm>> {
+    let mut list = app.state::<SharedConfig>().get().alarms.clone();
+    at(&mut list, index)?.enabled = enabled;
+    save(&app, &list)
+}
@@ -0,0 +1,235 @@
+use serde::Serialize;
+use tauri::menu::{CheckMenuItem, Submenu};
+use tauri::Manager;
+use tracing::{info, warn};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDevice {
+    /// Core Audio object ID; stable until the device is unplugged.
+    id: u32,
+    name: String,
+    /// Whether it is the system's default output.
+    current: bool,
+}
+
+/// Output devices, the current one flagged.
+#[tauri::command]
+pub fn list_output_devices() -> Result<Vec<OutputDevice>> {
+    hal::output_devices()
+}
+
+/// Makes `id` the default output and returns the updated list.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn set_output_device(id: u32, app: tauri::AppHandle) -> Result<Vec<OutputDevice>> {
+    hal::set_default_output(id)?;
+    info!(id, "output device changed");
+    let devices = hal::output_devices()?;
+    refresh_menu(&app, &devices);
+    Ok(devices)
+}
+
+// ─── Tray submenu ─────────────────────────────────────────────────────────────
+
+/// Tray "Audio Output" submenu, rebuilt whenever the pointer enters the tray
+/// icon since devices come and go.
+pub struct OutputMenu(pub Submenu<tauri::Wry>);
+
+/// Menu IDs of the submenu's items are `output:<device id>`.
+pub const MENU_PREFIX: &str = "output:";
+
+pub fn refresh_menu(app: &tauri::AppHandle, devices: &[OutputDevice]) {
+    let Some(menu) = app.try_state::<OutputMenu>() else { return };
+    let menu = &menu.0;
+    if let Ok(items) = menu.items() {
+        for item in items {
+            let _ = menu.remove(&item);
+        }
+    }
+    for device in devices {
+        let id = format!("{MENU_PREFIX}{}", device.id);
+        match CheckMenuItem::with_id(app, id, &device.name, true, device.current, None::<&str>) {
+            Ok(item) => {
+                let _ = menu.append(&item);
+            }
+            Err(e) => warn!("output menu item failed: {e}"),
+        }
+    }
+}
+
+/// Re-reads the devices into the tray submenu; failures only get logged.
+pub fn reload_menu(app: &tauri::AppHandle) {
+    match hal::output_devices() {
+        Ok(devices) => refresh_menu(app, &devices),
+        Err(e) => warn!("listing output devices failed: {e}"),
+    }
+}
+
+/// Handles a click on one of the submenu's items.
+pub fn on_menu_event(app: &tauri::AppHandle, id: &str) {
+    let Some(Ok(id)) = id.strip_prefix(MENU_PREFIX).map(str::parse) else { return };
+    if let Err(e) = set_output_device(id, app.clone()) {
+        warn!("switching output failed: {e}");
+    }
+}
+
+// ─── Core Audio ───────────────────────────────────────────────────────────────
+
+#[cfg(not(target_os = "macos"))]
+mod hal {
+    use super::*;
+
+    pub fn output_devices() -> Result<Vec<OutputDevice>> {
+        Err(Error::Io("sorties audio disponibles sur macOS uniquement".into()))
+    }
+
+    pub fn set_default_output(_id: u32) -> Result<()> {
+        output_devices().map(|_| ())
+    }
+}
+
+/// The handful of HAL calls needed, declared by hand.
+#[cfg(target_os = "macos")]
+mod hal {
+    use std::ffi::c_void;
+    use std::ptr::{self, NonNull};
+
+    use objc2_core_foundation::{CFRetained, CFString};
+
+    use super::*;
+
+    #[repr(C)]
+    struct PropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    extern "C" {
+        fn AudioObjectGetPropertyDataSize(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: *mut u32,
+        ) -> i32;
+        fn AudioObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+        fn AudioObjectSetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: u32,
+            data: *const c_void,
+        ) -> i32;
+    }
+
+    const fn fourcc(code: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*code)
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    const DEVICES: u32 = fourcc(b"dev#");
+    const DEFAULT_OUTPUT: u32 = fourcc(b"dOut");
+    const NAME: u32 = fourcc(b"lnam");
+    const STREAMS: u32 = fourcc(b"stm#");
+    const SCOPE_GLOBAL: u32 = fourcc(b"glob");
+    const SCOPE_OUTPUT: u32 = fourcc(b"outp");
+
+    fn address(selector: u32, scope: u32) -> PropertyAddress {
+        PropertyAddress { selector, scope, element: 0 }
+    }
+
+    fn check(status: i32, what: &str) -> Result<()> {
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(Error::Io(format!("Core Audio ({what}) : erreur {status}")))
+        }
+    }
+
+    fn data_size(object: u32, address: &PropertyAddress) -> Result<u32> {
+        let mut size = 0;
+        check(
+            unsafe { AudioObjectGetPropertyDataSize(object, address, 0, ptr::null(), &mut size) },
+            "taille",
+        )?;
+        Ok(size)
+    }
+
+    fn get<T: Copy + Default>(object: u32, address: &PropertyAddress) -> Result<T> {
+        let mut value = T::default();
+        let mut size  = std::mem::size_of::<T>() as u32;
+        check(
+            unsafe {
+                AudioObjectGetPropertyData(object, address, 0, ptr::null(), &mut size, (&mut value as *mut T).cast())
+            },
+            "lecture",
+        )?;
+        Ok(value)
+    }
+
+    fn device_ids() -> Result<Vec<u32>> {
+        let address  = address(DEVICES, SCOPE_GLOBAL);
+        let mut size = data_size(SYSTEM_OBJECT, &address)?;
+        let mut ids  = vec![0u32; size as usize / std::mem::size_of::<u32>()];
+        check(
+            unsafe {
+                AudioObjectGetPropertyData(SYSTEM_OBJECT, &address, 0, ptr::null(), &mut size, ids.as_mut_ptr().cast())
+            },
+            "périphériques",
+        )?;
+        ids.truncate(size as usize / std::mem::size_of::<u32>());
+        Ok(ids)
+    }
+
+    fn name(device: u32) -> Result<String> {
+        let raw: Option<NonNull<CFString>> = get(device, &address(NAME, SCOPE_GLOBAL))?;
+        // The HAL hands over a +1 reference
+        let name = raw.map(|p| unsafe { CFRetained::from_raw(p) });
+        Ok(name.map(|n| n.to_string()).unwrap_or_default())
+    }
+
+    fn has_output(device: u32) -> bool {
+        data_size(device, &address(STREAMS, SCOPE_OUTPUT)).is_ok_and(|size| size > 0)
+    }
+
+    pub fn output_devices() -> Result<Vec<OutputDevice>> {
+        let current: u32 = get(SYSTEM_OBJECT, &address(DEFAULT_OUTPUT, SCOPE_GLOBAL))?;
+        Ok(device_ids()?
+            .into_iter()
+            .filter(|&id| has_output(id))
+            .map(|id| OutputDevice { id, name: name(id).unwrap_or_default(), current: id == current })
+            .collect())
+    }
+
+    pub fn set_default_output(id: u32) -> Result<()> {
+        if !output_devices()?.iter().any(|d| d.id == id) {
+            return Err(Error::NotFound("Sortie audio introuvable".into()));
+        }
+        let address = address(DEFAULT_OUTPUT, SCOPE_GLOBAL);
+        check(
+            unsafe {
+                AudioObjectSetPropertyData(
+                    SYSTEM_OBJECT,
+                    &address,
+                    0,
+                    ptr::null(),
+                    std::mem::size_of::<u32>() as u32,
+                    (&id as *const u32).cast(),
+                )
+            },
+            "changement de sortie",
+        )
+    }
+}
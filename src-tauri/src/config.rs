@@ -1,48 +1,138 @@
-use serde::Deserialize;
-use std::path::PathBuf;
-
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct ApiConfig {
-    #[serde(default)]
-    pub genius_token: String,
-    #[serde(default)]
-    pub anthropic_key: String,
-}
-
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct Config {
-    #[serde(default)]
-    pub api: ApiConfig,
-}
-
-impl Config {
-    pub fn load() -> Self {
-        let path = config_path();
-        let content = match std::fs::read_to_string(&path) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Config not found at {}: {e}", path.display());
-                return Self::default();
-            }
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info, warn};
+
+pub use enhanced_music_core::config::*;
+use enhanced_music_core::settings;
+
+/// Why `config.toml` was last refused, until a load succeeds. The UI asks on
+/// start, then follows `config-error` and `config-changed`.
+#[derive(Default)]
+pub struct ConfigError(Mutex<Option<String>>);
+
+impl ConfigError {
+    fn set(&self, error: Option<String>) {
+        if let Ok(mut e) = self.0.lock() {
+            *e = error;
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_config_error(state: tauri::State<'_, ConfigError>) -> Option<String> {
+    state.0.lock().ok().and_then(|e| e.clone())
+}
+
+/// First load: an unusable file still starts the app, on the fallback, with
+/// the reason kept for the UI.
+pub fn initial() -> (Config, ConfigError) {
+    match Config::load() {
+        Ok(config) => (config, ConfigError::default()),
+        Err(e) => {
+            error!(path = %config_path().display(), "{e}");
+            (Config::fallback(), ConfigError(Mutex::new(Some(e.to_string()))))
+        }
+    }
+}
+
+/// Watches the config directory and reloads `SharedConfig` on change,
+/// emitting `config-changed`. The directory is watched rather than the file
+/// because editors often save by rename, which drops a file-level watch.
+pub fn watch(app: &AppHandle) {
+    let path = config_path();
+    let Some(dir) = path.parent().map(|d| d.to_path_buf()) else { return };
+    let app = app.clone();
+
+    std::thread::spawn(move || {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => { warn!("config watcher unavailable: {e}"); return; }
         };
-        match toml::from_str(&content) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Invalid config: {e}");
-                Self::default()
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            warn!(dir = %dir.display(), "cannot watch config directory: {e}");
+            return;
+        }
+
+        while let Ok(event) = rx.recv() {
+            let touches_config = matches!(&event, Ok(ev) if ev.paths.iter().any(|p| p == &path));
+            if !touches_config {
+                continue;
             }
+            // Editors emit bursts of events per save — settle, then drain.
+            std::thread::sleep(Duration::from_millis(200));
+            while rx.try_recv().is_ok() {}
+
+            reload(&app);
+        }
+    });
+}
+
+/// Re-reads the configuration, applies it and tells the UI. A file that
+/// can't be used leaves the last good configuration in place and emits
+/// `config-error` with the reason.
+pub fn reload(app: &AppHandle) {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("config not reloaded: {e}");
+            app.state::<ConfigError>().set(Some(e.to_string()));
+            let _ = app.emit("config-error", e.to_string());
+            return;
         }
+    };
+    info!(path = %config_path().display(), "config reloaded");
+    app.state::<ConfigError>().set(None);
+    crate::net::configure(&config.http);
+    crate::set_offline(app, config.offline);
+    app.state::<SharedConfig>().set(config);
+    let _ = app.emit("config-changed", ());
+}
+
+/// Applies `change` to `config.toml` — comments and layout are kept — and
+/// reloads. For the few settings the app changes itself.
+pub fn edit(app: &AppHandle, change: impl FnOnce(&mut toml_edit::Document)) -> crate::error::Result<()> {
+    use crate::error::Error;
+
+    let path = config_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::Io(e.to_string())),
+    };
+    let mut doc: toml_edit::Document = content
+        .parse()
+        .map_err(|e| Error::Config(format!("TOML invalide : {e}")))?;
+    // A file the app creates starts at the current format
+    if content.is_empty() {
+        doc["version"] = toml_edit::value(CONFIG_VERSION);
     }
+    change(&mut doc);
 
-    pub fn has_keys(&self) -> bool {
-        !self.api.genius_token.is_empty() && !self.api.anthropic_key.is_empty()
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::Io(e.to_string()))?;
     }
+    std::fs::write(&path, doc.to_string()).map_err(|e| Error::Io(e.to_string()))?;
+    info!(path = %path.display(), "config updated");
+    reload(app);
+    Ok(())
+}
+
+// ─── Export / import ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn export_settings(path: String, passphrase: Option<String>) -> crate::error::Result<settings::ExportReport> {
+    settings::export(std::path::Path::new(&path), passphrase.as_deref())
 }
 
-fn config_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_default();
-    PathBuf::from(home)
-        .join(".config")
-        .join("enhanced-music")
-        .join("config.toml")
+/// Reloads right away rather than waiting for the watcher, so the reply
+/// finds the imported settings applied.
+#[tauri::command]
+pub async fn import_settings(app: AppHandle, path: String, passphrase: Option<String>) -> crate::error::Result<settings::ImportReport> {
+    let report = settings::import(std::path::Path::new(&path), passphrase.as_deref())?;
+    reload(&app);
+    Ok(report)
 }
@@ -0,0 +1,125 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use enhanced_music_core::diagnostics::{recent_errors, ApiError};
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::config::{self, LlmProviderKind, SharedConfig};
+use crate::error::Result;
+use crate::logging;
+use crate::net::{self, http};
+
+#[derive(Serialize)]
+struct KeyPresence {
+    genius: bool,
+    anthropic: bool,
+    openai: bool,
+}
+
+#[derive(Serialize)]
+struct Reachability {
+    service: &'static str,
+    reachable: bool,
+    /// HTTP status of the probe, even an error one: any answer proves the host is up.
+    status: Option<u16>,
+    latency_ms: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DirSize {
+    path: String,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct Diagnostics {
+    version: &'static str,
+    config_path: String,
+    config_exists: bool,
+    llm_provider: String,
+    keys: KeyPresence,
+    recent_errors: Vec<ApiError>,
+    data_dir: DirSize,
+    log_dir: DirSize,
+    offline: bool,
+    /// Empty in offline mode: probing would defeat its purpose.
+    reachability: Vec<Reachability>,
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else { return 0 };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&e.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn dir_info(path: &Path) -> DirSize {
+    DirSize { path: path.display().to_string(), bytes: dir_size(path) }
+}
+
+/// One unauthenticated GET with a short timeout and no retry.
+async fn probe(service: &'static str, url: &str) -> Reachability {
+    let started = Instant::now();
+    match http().get(url).timeout(Duration::from_secs(5)).send().await {
+        Ok(resp) => Reachability {
+            service,
+            reachable:  true,
+            status:     Some(resp.status().as_u16()),
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            error:      None,
+        },
+        Err(e) => Reachability {
+            service,
+            reachable:  false,
+            status:     None,
+            latency_ms: None,
+            error:      Some(e.to_string()),
+        },
+    }
+}
+
+/// Everything a "debug info" panel needs, without revealing the keys themselves.
+#[tauri::command]
+pub async fn get_diagnostics(app: tauri::AppHandle) -> Result<Diagnostics> {
+    let config      = app.state::<SharedConfig>().get();
+    let config_path = config::config_path();
+
+    let (llm_name, llm_url) = match config.llm.provider {
+        LlmProviderKind::Anthropic => ("anthropic", "https://api.anthropic.com".to_string()),
+        LlmProviderKind::OpenAi    => ("openai", config.api.openai_base_url.clone()),
+    };
+    let reachability = if net::is_offline() {
+        Vec::new()
+    } else {
+        let (genius, itunes, llm) = tokio::join!(
+            probe("genius", "https://api.genius.com"),
+            probe("itunes", "https://itunes.apple.com/search?term=test&limit=1"),
+            probe(llm_name, &llm_url),
+        );
+        vec![genius, itunes, llm]
+    };
+
+    Ok(Diagnostics {
+        version:       env!("CARGO_PKG_VERSION"),
+        config_exists: config_path.exists(),
+        config_path:   config_path.display().to_string(),
+        llm_provider:  format!("{:?}", config.llm.provider),
+        keys: KeyPresence {
+            genius:    !config.api.genius_token.is_empty(),
+            anthropic: !config.api.anthropic_key.is_empty(),
+            openai:    !config.api.openai_key.is_empty(),
+        },
+        recent_errors: recent_errors().lock().map(|e| e.iter().cloned().collect()).unwrap_or_default(),
+        data_dir:      dir_info(&config::data_dir()),
+        log_dir:       dir_info(&logging::log_dir()),
+        offline:       net::is_offline(),
+        reachability,
+    })
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveTime, Timelike, Weekday};
+use enhanced_music_core::cancel::CancelToken;
+use enhanced_music_core::digest::{self, Digest, Period};
+use enhanced_music_core::history::{self, ExportFormat, Import, Range};
+use enhanced_music_core::music;
+use enhanced_music_core::wrapped::{self, Format, Wrapped};
+use tauri::Manager;
+use tracing::{info, warn};
+
+use crate::config::{DigestConfig, SharedConfig};
+use crate::error::Result;
+
+/// Same pace as the alarms: well under a minute, so `time` isn't missed.
+const TICK: Duration = Duration::from_secs(15);
+
+/// The digest of the day or of the last seven days.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn get_digest(period: Period, app: tauri::AppHandle) -> Result<Digest> {
+    let config = app.state::<SharedConfig>().get();
+    digest::get(&config, &CancelToken::default(), period).await
+}
+
+/// The year in review, for `year`.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn generate_wrapped(year: i32, app: tauri::AppHandle) -> Result<Wrapped> {
+    let config = app.state::<SharedConfig>().get();
+    wrapped::generate(&config, &CancelToken::default(), year).await
+}
+
+/// Writes the year in review to `path`, as `markdown` or `html`.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn export_wrapped(year: i32, format: Format, path: String, app: tauri::AppHandle) -> Result<String> {
+    let config = app.state::<SharedConfig>().get();
+    wrapped::export(&config, &CancelToken::default(), year, format, std::path::Path::new(&path)).await
+}
+
+/// Writes the plays of `range` (all of them without one) to `path`, as
+/// `csv` or `json`. Returns how many were written.
+#[tauri::command]
+#[tracing::instrument]
+pub fn export_history(format: ExportFormat, range: Option<Range>, path: String) -> Result<usize> {
+    history::export(format, &range.unwrap_or_default(), std::path::Path::new(&path))
+}
+
+/// Backfills the history with the Last.fm scrobbles of `user`.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn import_lastfm_history(user: String, app: tauri::AppHandle) -> Result<Import> {
+    let config = app.state::<SharedConfig>().get();
+    history::import_lastfm(&config, &CancelToken::default(), &user).await
+}
+
+/// Whether the scheduled digest goes out at this minute.
+fn is_due(digest: &DigestConfig, now: chrono::DateTime<Local>) -> bool {
+    let Some(period) = digest.schedule else { return false };
+    let Ok(time) = NaiveTime::parse_from_str(digest.time.trim(), "%H:%M") else { return false };
+    let day_ok = period == Period::Day || Weekday::from_str(digest.weekday.trim()).is_ok_and(|d| d == now.weekday());
+    day_ok && time.hour() == now.hour() && time.minute() == now.minute()
+}
+
+async fn notify(digest: &Digest) -> Result<()> {
+    let subtitle = match digest.period {
+        Period::Day  => "Votre journée en musique",
+        Period::Week => "Votre semaine en musique",
+    };
+    music::run(
+        r#"on run argv
+            display notification (item 1 of argv) with title "Enhanced Music" subtitle (item 2 of argv)
+        end run"#,
+        &[&digest.summary, subtitle],
+    )
+    .await
+    .map(drop)
+}
+
+/// Sends `[digest] schedule` as a notification at `time`; the digest itself
+/// stays available through `get_digest`.
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut sent: Option<String> = None;
+        loop {
+            let now    = Local::now();
+            let minute = now.format("%Y-%m-%d %H:%M").to_string();
+            let config = app.state::<SharedConfig>().get();
+            if is_due(&config.digest, now) && sent.as_deref() != Some(minute.as_str()) {
+                sent = Some(minute);
+                let period = config.digest.schedule.unwrap_or(Period::Day);
+                match digest::get(&config, &CancelToken::default(), period).await {
+                    Ok(digest) => {
+                        info!(?period, "digest sent");
+                        if let Err(e) = notify(&digest).await {
+                            warn!("digest notification failed: {e}");
+                        }
+                    }
+                    Err(e) => warn!(?period, "no digest: {e}"),
+                }
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
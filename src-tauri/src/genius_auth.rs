@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::json;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, SharedConfig};
+use crate::error::{Error, Result};
+use crate::net::{self, http, HttpError};
+use crate::{url_decode, url_encode};
+
+/// Must match the redirect URI registered for the Genius API client.
+const REDIRECT_PORT: u16 = 53682;
+const REDIRECT_PATH: &str = "/callback";
+
+/// How long the browser round-trip may take before giving up.
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(300);
+
+fn redirect_uri() -> String {
+    format!("http://127.0.0.1:{REDIRECT_PORT}{REDIRECT_PATH}")
+}
+
+/// Created readable by its owner only, so the token is never exposed, not
+/// even between writing it and restricting the file.
+fn store_token(token: &str) -> Result<()> {
+    use std::io::Write;
+
+    std::fs::create_dir_all(config::data_dir()).map_err(|e| Error::Io(e.to_string()))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(config::genius_token_path()).map_err(|e| Error::Io(e.to_string()))?;
+    file.write_all(token.as_bytes()).map_err(|e| Error::Io(e.to_string()))
+}
+
+/// 16 bytes from the system's CSPRNG, in hex, for the CSRF `state`.
+fn random_state() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| Error::Auth("générateur aléatoire indisponible".into()))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Query parameters of the redirect, or `None` for any other request
+/// (browsers also ask for `/favicon.ico`).
+fn callback_params(request_line: &str) -> Option<HashMap<String, String>> {
+    let target        = request_line.strip_prefix("GET ")?.split(' ').next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != REDIRECT_PATH {
+        return None;
+    }
+    Some(
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (url_decode(k), url_decode(v)))
+            .collect(),
+    )
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "<!doctype html><meta charset=\"utf-8\"><title>Enhanced Music</title>\
+         <p style=\"font:15px -apple-system,sans-serif;text-align:center;margin-top:80px\">{message}</p>"
+    );
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Serves the redirect URI until Genius sends the browser back, and returns
+/// the authorization code.
+async fn wait_for_code(listener: TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| Error::Io(e.to_string()))?;
+        let mut buf = vec![0u8; 8192];
+        let n = match stream.read(&mut buf).await {
+            Ok(n) => n,
+            Err(e) => {
+                debug!("callback read failed: {e}");
+                continue;
+            }
+        };
+        let request      = String::from_utf8_lossy(&buf[..n]);
+        let request_line = request.lines().next().unwrap_or_default();
+        let Some(params) = callback_params(request_line) else {
+            respond(&mut stream, "404 Not Found", "").await;
+            continue;
+        };
+
+        if params.get("state").map(String::as_str) != Some(state) {
+            respond(&mut stream, "400 Bad Request", "Réponse inattendue de Genius.").await;
+            return Err(Error::Auth("réponse de Genius invalide".into()));
+        }
+        if let Some(error) = params.get("error") {
+            respond(&mut stream, "200 OK", "Connexion à Genius annulée.").await;
+            return Err(Error::Auth(format!("accès refusé ({error})")));
+        }
+        let Some(code) = params.get("code").filter(|c| !c.is_empty()) else {
+            respond(&mut stream, "400 Bad Request", "Réponse inattendue de Genius.").await;
+            return Err(Error::Auth("code d'autorisation absent".into()));
+        };
+        respond(&mut stream, "200 OK", "Connexion à Genius réussie — vous pouvez fermer cet onglet.").await;
+        return Ok(code.clone());
+    }
+}
+
+async fn exchange_code(client_id: &str, client_secret: &str, code: &str) -> Result<String> {
+    let body = json!({
+        "code":          code,
+        "client_id":     client_id,
+        "client_secret": client_secret,
+        "redirect_uri":  redirect_uri(),
+        "response_type": "code",
+        "grant_type":    "authorization_code",
+    });
+    // Sent once: the code is single-use, so a retry after a timeout the
+    // server did see would only fail with `invalid_grant` and hide why
+    let sent = http().post("https://api.genius.com/oauth/token").json(&body).send().await;
+    let resp = match sent {
+        Ok(resp) if resp.status().is_success() => Ok(resp),
+        Ok(resp) => Err(HttpError::Status(resp.status().as_u16())),
+        Err(e) => Err(HttpError::Transport(e)),
+    }
+    .map_err(|e| {
+        warn!("Genius token exchange failed: {e}");
+        Error::http("genius", e)
+    })?;
+    let json: serde_json::Value = resp.json().await.map_err(|e| Error::http("genius", HttpError::Transport(e)))?;
+    json["access_token"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| Error::Auth("jeton absent de la réponse".into()))
+}
+
+/// Signs in to Genius in the browser (OAuth with a local redirect listener),
+/// stores the resulting token and reloads the configuration with it.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn genius_sign_in(app: tauri::AppHandle) -> Result<()> {
+    let config = app.state::<SharedConfig>().get();
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    let (client_id, client_secret) = (&config.api.genius_client_id, &config.api.genius_client_secret);
+    if client_id.is_empty() || client_secret.is_empty() {
+        return Err(Error::MissingKeys);
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", REDIRECT_PORT))
+        .await
+        .map_err(|e| Error::Io(format!("port {REDIRECT_PORT} indisponible : {e}")))?;
+    let state = random_state()?;
+    let authorize_url = format!(
+        "https://api.genius.com/oauth/authorize?client_id={}&redirect_uri={}&scope=me&state={state}&response_type=code",
+        url_encode(client_id),
+        url_encode(&redirect_uri()),
+    );
+    crate::open_in_browser(&authorize_url).await?;
+
+    let code = tokio::time::timeout(SIGN_IN_TIMEOUT, wait_for_code(listener, &state))
+        .await
+        .map_err(|_| Error::Auth("délai dépassé".into()))??;
+    let token = exchange_code(client_id, client_secret, &code).await?;
+
+    store_token(&token)?;
+    info!("signed in to Genius");
+    config::reload(&app);
+    Ok(())
+}
+
+/// Forgets the token stored by [`genius_sign_in`]; one set in `config.toml`
+/// stays in use.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub fn genius_sign_out(app: tauri::AppHandle) -> Result<()> {
+    match std::fs::remove_file(config::genius_token_path()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Error::Io(e.to_string())),
+    }
+    info!("signed out of Genius");
+    config::reload(&app);
+    Ok(())
+}
+
+/// Whether a signed-in token is stored, for the settings UI.
+#[tauri::command]
+pub fn genius_signed_in() -> bool {
+    config::stored_genius_token().is_some()
+}
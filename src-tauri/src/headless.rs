@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::album;
+use crate::artist;
+use crate::cancel::TrackScope;
+use crate::config::{self, SharedConfig};
+use crate::error::{Error, Result};
+use crate::lyrics;
+use crate::player;
+use crate::url_decode;
+
+/// Same pace as the popover's own polling, which isn't there to do it.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Without a window nothing asks for the current track, so the webhook, MQTT
+/// and lights would never hear of a change: poll on the popover's behalf,
+/// and serve the API.
+pub fn start(app: &AppHandle) {
+    let poller = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match crate::read_current_track(&poller).await {
+                Ok(track) => {
+                    let config = poller.state::<SharedConfig>().get();
+                    player::observe(&config, &poller.state::<TrackScope>(), track.as_ref());
+                }
+                Err(e) => debug!("poll failed: {e}"),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if app.state::<SharedConfig>().get().server.token.is_empty() {
+            if let Err(e) = generate_token(&app) {
+                warn!("API token not generated, every request will be refused: {e}");
+            }
+        }
+        let port     = app.state::<SharedConfig>().get().server.port;
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!(port, "API unavailable: {e}");
+                return;
+            }
+        };
+        info!(port, "API listening");
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move { handle(&app, port, stream).await });
+                }
+                Err(e) => debug!("accept failed: {e}"),
+            }
+        }
+    });
+}
+
+/// Writes a random `[server] token` to `config.toml`, which API clients
+/// then copy from there.
+fn generate_token(app: &AppHandle) -> Result<()> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| Error::Auth("générateur aléatoire indisponible".into()))?;
+    let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    config::edit(app, |doc| doc["server"]["token"] = toml_edit::value(token))?;
+    info!("API token generated in config.toml");
+    Ok(())
+}
+
+/// Why a request is refused before routing, if it is. A web page the user
+/// opens can reach 127.0.0.1 too: its requests carry an `Origin`, or, by DNS
+/// rebinding, a `Host` that isn't ours; and without the token nothing gets
+/// through, which keeps the LLM keys from being spent by anyone else.
+fn refusal(headers: &HashMap<String, String>, port: u16, token: &str) -> Option<(&'static str, Error)> {
+    let host = headers.get("host").map(String::as_str).unwrap_or_default();
+    if host != format!("127.0.0.1:{port}") && host != format!("localhost:{port}") {
+        return Some(("403 Forbidden", Error::Auth("hôte non autorisé".into())));
+    }
+    if headers.contains_key("origin") {
+        return Some(("403 Forbidden", Error::Auth("requête d'un navigateur refusée".into())));
+    }
+    let given = headers.get("authorization").and_then(|a| a.strip_prefix("Bearer ")).unwrap_or_default();
+    // Compared in constant time, so response times don't spell the token
+    let matches = given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0;
+    if token.is_empty() || !matches {
+        return Some(("401 Unauthorized", Error::Auth("jeton manquant ou invalide".into())));
+    }
+    None
+}
+
+/// Header names lowercased, up to the blank line.
+fn parse_headers(request: &str) -> HashMap<String, String> {
+    request
+        .lines()
+        .skip(1)
+        .take_while(|l| !l.is_empty())
+        .filter_map(|l| l.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+/// Path and query parameters of a `GET`, or `None` for any other method.
+fn parse_request(request_line: &str) -> Option<(String, HashMap<String, String>)> {
+    let target        = request_line.strip_prefix("GET ")?.split(' ').next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params        = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (url_decode(k), url_decode(v)))
+        .collect();
+    Some((path.to_string(), params))
+}
+
+async fn handle(app: &AppHandle, port: u16, mut stream: TcpStream) {
+    let mut buf = vec![0u8; 8192];
+    let n = match stream.read(&mut buf).await {
+        Ok(n) => n,
+        Err(e) => {
+            debug!("request read failed: {e}");
+            return;
+        }
+    };
+    let request      = String::from_utf8_lossy(&buf[..n]);
+    let request_line = request.lines().next().unwrap_or_default();
+    let token        = app.state::<SharedConfig>().get().server.token.clone();
+    let (status, body) = match (refusal(&parse_headers(&request), port, &token), parse_request(request_line)) {
+        (Some((status, error)), _) => (status, serde_json::to_string(&error).unwrap_or_default()),
+        (None, Some((path, params))) => route(app, &path, &params).await,
+        (None, None) => ("405 Method Not Allowed", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// JSON endpoints mirroring the popover's commands; errors come back as the
+/// same `{ kind, message }` the UI gets.
+async fn route(app: &AppHandle, path: &str, params: &HashMap<String, String>) -> (&'static str, String) {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    let param  = |name: &str| -> Result<&str> {
+        params
+            .get(name)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| Error::Invalid(format!("paramètre `{name}` manquant")))
+    };
+    let optional    = |name: &str| params.get(name).map(String::as_str).unwrap_or_default();
+    let compilation = params.get("compilation").is_some_and(|v| v == "true");
+
+    match path {
+        "/now-playing" => reply(crate::read_current_track(app).await),
+        "/album-info" => reply(match (param("album"), param("artist")) {
+            (Ok(album), Ok(artist)) => album::info(&config, &cancel, album, artist, compilation).await,
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        }),
+        "/artist-info" => reply(match param("artist") {
+            Ok(artist) => artist::info(&config, &cancel, artist).await,
+            Err(e) => Err(e),
+        }),
+        "/lyrics" => reply(match (param("title"), param("artist")) {
+            (Ok(title), Ok(artist)) => lyrics::text(&config, title, artist, optional("album")).await,
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        }),
+        "/lyrics-analysis" => reply(match (param("title"), param("artist")) {
+            (Ok(title), Ok(artist)) => lyrics::analysis(&config, &cancel, title, artist, optional("album"), None).await,
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        }),
+        _ => ("404 Not Found", String::new()),
+    }
+}
+
+fn reply<T: Serialize>(result: Result<T>) -> (&'static str, String) {
+    let status = match &result {
+        Ok(_) => "200 OK",
+        Err(Error::Invalid(_)) => "400 Bad Request",
+        Err(Error::NotFound(_)) => "404 Not Found",
+        Err(Error::Disabled | Error::MissingKeys | Error::Offline | Error::Permission) => "503 Service Unavailable",
+        Err(_) => "502 Bad Gateway",
+    };
+    let body = match result {
+        Ok(value) => serde_json::to_string(&value),
+        Err(e) => serde_json::to_string(&e),
+    };
+    (status, body.unwrap_or_default())
+}
@@ -0,0 +1,124 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use tracing::{debug, warn};
+
+use crate::cancel::{CancelToken, TrackScope};
+use crate::config::SharedConfig;
+use crate::error::{Error, Result};
+use crate::lyrics::{self, SyncedLine};
+use crate::net;
+
+/// How often the player is asked for the playback position; in between, the
+/// position is extrapolated from the clock.
+const POSITION_POLL: Duration = Duration::from_secs(1);
+const TICK: Duration = Duration::from_millis(100);
+
+/// The running session, so a new one (or `stop_karaoke`) can end it.
+#[derive(Default)]
+pub struct KaraokeSession(Mutex<Option<CancelToken>>);
+
+impl KaraokeSession {
+    fn replace(&self, token: Option<CancelToken>) {
+        let Ok(mut current) = self.0.lock() else { return };
+        if let Some(old) = current.take() {
+            old.cancel();
+        }
+        *current = token;
+    }
+}
+
+/// Payload of `lyric-line-changed`.
+#[derive(Serialize, Clone)]
+struct LineChanged {
+    title: String,
+    artist: String,
+    /// `None` before the first line and during the intro.
+    index: Option<usize>,
+    text: String,
+    next: Option<String>,
+}
+
+/// Index of the line being sung at `ms`.
+fn line_at(lines: &[SyncedLine], ms: u64) -> Option<usize> {
+    lines.partition_point(|l| l.time_ms <= ms).checked_sub(1)
+}
+
+/// Starts following playback for the given track and emits
+/// `lyric-line-changed` whenever the current line changes. Returns the synced
+/// lines so the UI can lay them all out. The session ends on `stop_karaoke`,
+/// on the next `start_karaoke`, or when the track changes.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+pub async fn start_karaoke(title: String, artist: String, album: String, app: tauri::AppHandle) -> Result<Vec<SyncedLine>> {
+    let config = app.state::<SharedConfig>().get();
+    let track  = app.state::<TrackScope>().token();
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+
+    let found = lyrics::fetch(&config, &title, &artist, &album).await?;
+    let lines = found
+        .synced
+        .as_deref()
+        .map(lyrics::parse_lrc)
+        .filter(|l| !l.is_empty())
+        .ok_or_else(|| Error::NotFound("Pas de paroles synchronisées pour ce morceau".into()))?;
+
+    let stop = CancelToken::default();
+    app.state::<KaraokeSession>().replace(Some(stop.clone()));
+
+    let session = lines.clone();
+    let handle  = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut position: Option<(f64, bool, Instant)> = None;
+        let mut polled: Option<Instant> = None;
+        let mut current = None;
+
+        while !stop.is_cancelled() && !track.is_cancelled() {
+            if polled.is_none_or(|p| p.elapsed() >= POSITION_POLL) {
+                polled   = Some(Instant::now());
+                position = match crate::read_position(&handle).await {
+                    Ok(read) => read.map(|(secs, playing)| (secs, playing, Instant::now())),
+                    Err(Error::Permission) => {
+                        warn!("karaoke stopped: Automation permission denied");
+                        break;
+                    }
+                    // Keep extrapolating from the last position until one comes
+                    Err(e) => {
+                        debug!("playback position unavailable: {e}");
+                        position
+                    }
+                };
+            }
+            if let Some((secs, playing, at)) = position {
+                let elapsed = if playing { at.elapsed().as_secs_f64() } else { 0.0 };
+                let index   = line_at(&session, ((secs + elapsed) * 1000.0) as u64);
+                if index != current {
+                    current = index;
+                    let payload = LineChanged {
+                        title:  title.clone(),
+                        artist: artist.clone(),
+                        index,
+                        text:   index.map(|i| session[i].text.clone()).unwrap_or_default(),
+                        next:   session.get(index.map_or(0, |i| i + 1)).map(|l| l.text.clone()),
+                    };
+                    if let Err(e) = handle.emit("lyric-line-changed", payload) {
+                        warn!("cannot emit lyric line: {e}");
+                    }
+                }
+            }
+            tokio::time::sleep(TICK).await;
+        }
+        debug!("karaoke session ended");
+    });
+
+    Ok(lines)
+}
+
+#[tauri::command]
+pub fn stop_karaoke(app: tauri::AppHandle) {
+    app.state::<KaraokeSession>().replace(None);
+}
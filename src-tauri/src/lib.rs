@@ -1,460 +1,660 @@
+mod alarm;
+mod audio;
 mod config;
-
-use std::io::Read;
-use std::sync::OnceLock;
-use std::time::Duration;
-
-use config::Config;
+mod diagnostics;
+mod digest;
+mod genius_auth;
+mod headless;
+mod karaoke;
+mod logging;
+mod music;
+mod onboarding;
+mod share_card;
+
+use enhanced_music_core::{
+    album, artist, artwork, audio_analysis, bookmarks, cancel, classical, episode, error, insights, lyrics, moods, net,
+    notes, player, playlist, song, spotify, suggestion, taste, url_decode, url_encode, usage, vault,
+};
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager,
+    Emitter, Manager,
 };
+use tracing::{debug, info, warn};
 
-// ─── HTTP agent (shared, with timeouts) ───────────────────────────────────────
+use cancel::TrackScope;
+use config::{Player, SharedConfig};
+use error::{Error, Result};
+use net::http;
+use player::Track;
+
+// The commands below are the GUI's side of `enhanced_music_core`: they read
+// the shared config and the current track's cancel token, then hand over.
+
+// ─── Track ────────────────────────────────────────────────────────────────────
 
-fn http() -> &'static ureq::Agent {
-    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
-    AGENT.get_or_init(|| {
-        ureq::AgentBuilder::new()
-            .timeout_connect(Duration::from_secs(5))
-            .timeout(Duration::from_secs(20))
-            .build()
-    })
+/// `Ok(None)` when nothing is playing; `Err` only when Music can't be queried.
+#[tauri::command]
+async fn get_current_track(app: tauri::AppHandle) -> Result<Option<Track>> {
+    let track  = read_current_track(&app).await?;
+    let config = app.state::<SharedConfig>().get();
+    player::observe(&config, &app.state::<TrackScope>(), track.as_ref());
+    Ok(track)
 }
 
-// ─── URL encoding ─────────────────────────────────────────────────────────────
+/// Player pinned with `set_active_source`; `None` follows `[player] sources`.
+#[derive(Default)]
+struct ActiveSource(std::sync::Mutex<Option<Player>>);
 
-fn url_encode(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c.to_string(),
-            ' ' => "+".to_string(),
-            c => format!("%{:02X}", c as u32),
-        })
-        .collect()
-}
-
-// ─── Anthropic helpers ────────────────────────────────────────────────────────
-
-fn call_claude(api_key: &str, max_tokens: u64, prompt: &str) -> Option<serde_json::Value> {
-    http()
-        .post("https://api.anthropic.com/v1/messages")
-        .set("x-api-key", api_key)
-        .set("anthropic-version", "2023-06-01")
-        .send_json(serde_json::json!({
-            "model": "claude-haiku-4-5-20251001",
-            "max_tokens": max_tokens,
-            "messages": [{ "role": "user", "content": prompt }]
-        }))
-        .map_err(|e| eprintln!("[claude] request error: {e}"))
-        .ok()?
-        .into_json()
-        .map_err(|e| eprintln!("[claude] JSON parse error: {e}"))
-        .ok()
-}
-
-fn extract_claude_json(response: &serde_json::Value, ctx: &str) -> Option<serde_json::Value> {
-    let text = response["content"][0]["text"].as_str().or_else(|| {
-        eprintln!("[claude:{ctx}] unexpected response shape: {response}");
-        None
-    })?;
-    let clean = strip_code_fences(text);
-    serde_json::from_str(clean)
-        .map_err(|e| eprintln!("[claude:{ctx}] JSON parse error: {e}\nRaw: {clean}"))
-        .ok()
-}
-
-/// Removes ```json / ``` fences from a Claude response, exactly once each side.
-fn strip_code_fences(s: &str) -> &str {
-    let s = s.trim();
-    let s = s.strip_prefix("```json").or_else(|| s.strip_prefix("```")).unwrap_or(s);
-    let s = s.strip_suffix("```").unwrap_or(s);
-    s.trim()
-}
-
-// ─── Genius helpers ───────────────────────────────────────────────────────────
-
-fn genius_get(url: &str, token: &str) -> Option<serde_json::Value> {
-    http()
-        .get(url)
-        .set("Authorization", &format!("Bearer {}", token))
-        .call()
-        .map_err(|e| eprintln!("[genius] GET {url} error: {e}"))
-        .ok()?
-        .into_json()
-        .map_err(|e| eprintln!("[genius] JSON parse error: {e}"))
-        .ok()
-}
-
-/// Fetches an album description from Genius via song search → song → album.
-/// Uses an inner `Option`-returning function so `?` can be used freely.
-fn genius_album_description(token: &str, artist: &str, album: &str) -> String {
-    genius_album_description_inner(token, artist, album).unwrap_or_default()
-}
-
-fn genius_album_description_inner(token: &str, artist: &str, album: &str) -> Option<String> {
-    let query  = url_encode(&format!("{} {}", artist, album));
-    let search = genius_get(&format!("https://api.genius.com/search?q={}", query), token)?;
-
-    let song_id = search["response"]["hits"][0]["result"]["id"]
-        .as_i64()
-        .or_else(|| { eprintln!("[genius] no hits for «{album}» by {artist}"); None })?;
-
-    let song     = genius_get(&format!("https://api.genius.com/songs/{}", song_id), token)?;
-    let album_id = song["response"]["song"]["album"]["id"].as_i64()?;
-    let album_v  = genius_get(&format!("https://api.genius.com/albums/{}", album_id), token)?;
-
-    album_v["response"]["album"]["description_preview"]
-        .as_str()
-        .filter(|s| !s.is_empty() && *s != "?")
-        .map(|s| s.to_string())
-}
-
-// ─── iTunes helper ────────────────────────────────────────────────────────────
-
-/// Returns (release_year, genre) from the iTunes Search API.
-fn itunes_album_metadata(artist: &str, album: &str) -> (String, String) {
-    let query = url_encode(&format!("{} {}", artist, album));
-    let url = format!(
-        "https://itunes.apple.com/search?term={}&media=music&entity=album&limit=10",
-        query
-    );
-
-    let json: serde_json::Value = match http().get(&url).call().ok().and_then(|r| r.into_json().ok()) {
-        Some(v) => v,
-        None => {
-            eprintln!("[itunes] request failed for «{album}» by {artist}");
-            return (String::new(), String::new());
-        }
-    };
+/// Pins the popover to one player, or with `None` goes back to the
+/// `[player] sources` order.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+fn set_active_source(source: Option<Player>, app: tauri::AppHandle) {
+    if let Ok(mut active) = app.state::<ActiveSource>().0.lock() {
+        *active = source;
+    }
+    info!(?source, "active source changed");
+}
 
-    let album_lc  = album.to_lowercase();
-    let artist_lc = artist.to_lowercase();
+fn pinned_player(app: &tauri::AppHandle) -> Option<Player> {
+    app.state::<ActiveSource>().0.lock().ok().and_then(|a| *a)
+}
 
-    let hit = json["results"].as_array().and_then(|arr| {
-        arr.iter().find(|r| {
-            r["collectionName"].as_str().unwrap_or("").to_lowercase().contains(&album_lc)
-                && r["artistName"].as_str().unwrap_or("").to_lowercase().contains(&artist_lc)
-        })
-    });
+async fn read_current_track(app: &tauri::AppHandle) -> Result<Option<Track>> {
+    let config = app.state::<SharedConfig>().get();
+    player::current_track(&config, pinned_player(app)).await
+}
 
-    let year = hit
-        .and_then(|r| r["releaseDate"].as_str())
-        .and_then(|d| d.get(..4))
-        .unwrap_or("")
-        .to_string();
+/// Playback position in the track [`read_current_track`] gives.
+async fn read_position(app: &tauri::AppHandle) -> Result<Option<(f64, bool)>> {
+    let config = app.state::<SharedConfig>().get();
+    player::position(&config, pinned_player(app)).await
+}
 
-    let genre = hit
-        .and_then(|r| r["primaryGenreName"].as_str())
-        .unwrap_or("")
-        .to_string();
+// ─── Copy now playing ─────────────────────────────────────────────────────────
+
+/// Fills `{title}`, `{artist}`, `{album}` and `{year}`, then drops the
+/// punctuation an empty field leaves behind ("(Album, )" → "(Album)").
+fn format_now_playing(template: &str, track: &Track, year: &str) -> String {
+    let text = template
+        .replace("{title}", &track.title)
+        .replace("{artist}", &track.artist)
+        .replace("{album}", &track.album)
+        .replace("{year}", year);
+    let text = text.replace(", )", ")").replace("(, ", "(").replace(" ()", "").replace("()", "");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
 
-    (year, genre)
+async fn write_clipboard(text: &str) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut child = tokio::process::Command::new("pbcopy")
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Io(e.to_string()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await.map_err(|e| Error::Io(e.to_string()))?;
+    }
+    child.wait().await.map_err(|e| Error::Io(e.to_string()))?;
+    Ok(())
 }
 
-// ─── Track ────────────────────────────────────────────────────────────────────
+/// Puts the playing track on the clipboard, formatted with `format` or
+/// `[clipboard] format`, and returns the copied text.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn copy_now_playing(format: Option<String>, app: tauri::AppHandle) -> Result<String> {
+    let config = app.state::<SharedConfig>().get();
+    let track  = read_current_track(&app)
+        .await?
+        .ok_or_else(|| Error::NotFound("Aucune lecture en cours".into()))?;
+    let year = match track.player {
+        Player::Music  => enhanced_music_core::music::run(r#"tell application "Music" to return year of current track"#, &[]).await.unwrap_or_default(),
+        _ => String::new(),
+    };
+    let year = if year == "0" { "" } else { year.as_str() };
+
+    let text = format_now_playing(format.as_deref().unwrap_or(&config.clipboard.format), &track, year);
+    write_clipboard(&text).await?;
+    Ok(text)
+}
+
+// ─── Artwork ──────────────────────────────────────────────────────────────────
 
 #[derive(serde::Serialize)]
-struct Track {
+struct Artwork {
+    /// `data:` URL of the image itself.
+    data_url: String,
+    /// Blurred placeholder to draw while the image decodes; `None` if the
+    /// thumbnail couldn't be made.
+    blurhash: Option<String>,
+}
+
+/// The current track's own artwork when it has one, else the iTunes Search
+/// cover (not available offline).
+#[tauri::command]
+async fn get_artwork(title: String, artist: String, album: Option<String>, app: tauri::AppHandle) -> Result<Artwork> {
+    let config = app.state::<SharedConfig>().get();
+    if !config.features.artwork {
+        return Err(Error::Disabled);
+    }
+    let bytes    = artwork::fetch(&config, &title, &artist, album.as_deref().unwrap_or("")).await?;
+    let blurhash = match artwork::thumbnail(&bytes, artwork::BLURHASH_SIZE).await {
+        Ok(thumb) => Some(artwork::blurhash(&thumb)),
+        Err(e) => {
+            debug!("no blurhash: {e}");
+            None
+        }
+    };
+    Ok(Artwork { data_url: artwork::data_url(&bytes), blurhash })
+}
+
+/// Dominant and accent colours of the artwork, to theme the popover. Reuses
+/// the image `get_artwork` just downloaded.
+#[tauri::command]
+async fn get_artwork_palette(
     title: String,
     artist: String,
-    album: String,
-    is_playing: bool,
-}
-
-#[tauri::command]
-fn get_current_track() -> Option<Track> {
-    let script = r#"
-        if application "Music" is running then
-            tell application "Music"
-                if player state is not stopped then
-                    try
-                        set t  to name of current track
-                        set ar to artist of current track
-                        set al to album of current track
-                        if player state is playing then
-                            set s to "playing"
-                        else
-                            set s to "paused"
-                        end if
-                        return t & "|||" & ar & "|||" & al & "|||" & s
-                    end try
-                end if
-            end tell
-        end if
-        return ""
-    "#;
-
-    let output = std::process::Command::new("osascript")
-        .arg("-e")
-        .arg(script)
-        .output()
-        .ok()?;
-
-    let raw = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if raw.is_empty() {
-        return None;
+    album: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<artwork::Palette> {
+    let config = app.state::<SharedConfig>().get();
+    if !config.features.artwork {
+        return Err(Error::Disabled);
     }
-
-    let parts: Vec<&str> = raw.splitn(4, "|||").collect();
-    (parts.len() == 4).then(|| Track {
-        title:      parts[0].to_string(),
-        artist:     parts[1].to_string(),
-        album:      parts[2].to_string(),
-        is_playing: parts[3].trim() == "playing",
-    })
+    let bytes = artwork::fetch(&config, &title, &artist, album.as_deref().unwrap_or("")).await?;
+    let thumb = artwork::thumbnail(&bytes, artwork::PALETTE_SIZE).await?;
+    Ok(artwork::palette(&thumb))
 }
 
-// ─── Artwork ──────────────────────────────────────────────────────────────────
+// ─── Album ────────────────────────────────────────────────────────────────────
 
+/// With `compilation`, `artist` is the track's: text searches (iTunes,
+/// Genius) still use it, while sources keyed on the album's credit
+/// (MusicBrainz, Last.fm, Wikipedia) look up Various Artists.
 #[tauri::command]
-fn get_artwork(title: String, artist: String) -> Option<String> {
-    let query = url_encode(&format!("{} {}", artist, title));
-    let json: serde_json::Value = http()
-        .get(&format!(
-            "https://itunes.apple.com/search?term={}&media=music&entity=song&limit=1",
-            query
-        ))
-        .call()
-        .ok()?
-        .into_json()
-        .ok()?;
-
-    let artwork_url = json["results"][0]["artworkUrl100"].as_str()?.to_string();
-    let hd_url = artwork_url.replace("100x100bb", "600x600bb");
-
-    let mut bytes = Vec::new();
-    http()
-        .get(&hd_url)
-        .call()
-        .ok()?
-        .into_reader()
-        .read_to_end(&mut bytes)
-        .ok()?;
-
-    if bytes.is_empty() {
-        return None;
-    }
+#[tracing::instrument(skip(app))]
+async fn get_album_info(
+    album: String,
+    artist: String,
+    compilation: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<album::AlbumInfo> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    album::info(&config, &cancel, &album, &artist, compilation.unwrap_or(false)).await
+}
 
-    use base64::{engine::general_purpose::STANDARD, Engine};
-    Some(format!("data:image/jpeg;base64,{}", STANDARD.encode(&bytes)))
+/// Long-form essay on the whole album, cached per album and language;
+/// `refresh` regenerates.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_album_deep_dive(
+    album: String,
+    artist: String,
+    compilation: Option<bool>,
+    refresh: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<album::AlbumDeepDive> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    album::deep_dive(&config, &cancel, &album, &artist, compilation.unwrap_or(false), refresh.unwrap_or(false)).await
 }
 
-// ─── Album info ───────────────────────────────────────────────────────────────
+#[derive(Clone, serde::Serialize)]
+struct AlbumAnalysisProgress {
+    album: String,
+    done: usize,
+    total: usize,
+}
 
-#[derive(serde::Serialize)]
-struct AlbumInfo {
-    release_year: String,
-    genre: String,
-    context: String,
-    notable_fact: String,
+/// Lyrics analyses of the whole album, for a listening session ahead;
+/// `album-analysis-progress` follows it track by track. Not tied to the
+/// track playing: going through the album is the point.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn analyze_album(
+    album: String,
+    artist: String,
+    compilation: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<album::AlbumAnalysis> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = cancel::CancelToken::default();
+    let mut on_progress = |done, total| {
+        let _ = app.emit("album-analysis-progress", AlbumAnalysisProgress { album: album.clone(), done, total });
+    };
+    album::analyze(&config, &cancel, &album, &artist, compilation.unwrap_or(false), Some(&mut on_progress)).await
 }
 
+/// Physical-release context from Discogs; needs `discogs_token`.
 #[tauri::command]
-fn get_album_info(
+#[tracing::instrument(skip(app))]
+async fn get_release_details(
     album: String,
     artist: String,
-    state: tauri::State<'_, Config>,
-) -> Option<AlbumInfo> {
-    if !state.has_keys() {
-        eprintln!("[album_info] API keys missing — skipping");
-        return None;
-    }
+    compilation: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<album::ReleaseDetails> {
+    let config = app.state::<SharedConfig>().get();
+    album::release_details(&config, &album, &artist, compilation.unwrap_or(false)).await
+}
 
-    let (release_year, genre) = itunes_album_metadata(&artist, &album);
-    let description           = genius_album_description(&state.api.genius_token, &artist, &album);
-    let prompt                = build_album_prompt(&album, &artist, &release_year, &genre, &description);
+// ─── Artist ───────────────────────────────────────────────────────────────────
 
-    let response  = call_claude(&state.api.anthropic_key, 400, &prompt)?;
-    let extracted = extract_claude_json(&response, "album")?;
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_artist_info(artist: String, app: tauri::AppHandle) -> Result<artist::ArtistInfo> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    artist::info(&config, &cancel, &artist).await
+}
 
-    Some(AlbumInfo {
-        release_year,
-        genre,
-        context:      extracted["context"].as_str().unwrap_or("").to_string(),
-        notable_fact: extracted["notable_fact"].as_str().unwrap_or("").to_string(),
-    })
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_similar_artists(artist: String, app: tauri::AppHandle) -> Result<Vec<artist::SimilarArtist>> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    artist::similar(&config, &cancel, &artist).await
 }
 
-fn build_album_prompt(album: &str, artist: &str, year: &str, genre: &str, description: &str) -> String {
-    let meta = if year.is_empty() {
-        String::new()
-    } else {
-        format!(" (sorti en {year}, genre : {genre})")
-    };
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_upcoming_shows(artist: String, app: tauri::AppHandle) -> Result<Vec<artist::Show>> {
+    let config = app.state::<SharedConfig>().get();
+    artist::upcoming_shows(&config, &artist).await
+}
 
-    let base = if description.is_empty() {
-        format!("En te basant sur tes connaissances, pour l'album \"{album}\" de {artist}{meta}, réponds en français.")
-    } else {
-        format!("Pour l'album \"{album}\" de {artist}{meta}, basé sur cette description :\n{description}\nRéponds en français.")
-    };
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_recent_setlists(
+    artist: String,
+    title: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<artist::RecentSetlists> {
+    let config = app.state::<SharedConfig>().get();
+    artist::recent_setlists(&config, &artist, title.as_deref()).await
+}
+
+// ─── Episode and classical ────────────────────────────────────────────────────
 
-    format!(
-        "{base}\n\nRéponds UNIQUEMENT avec ce JSON valide (sans markdown) :\
-         {{\"context\":\"2-3 phrases sur le contexte et la genèse de l'album\",\
-         \"notable_fact\":\"Un fait marquant ou anecdote sur cet album\"}}"
-    )
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_episode_info(show: String, title: String, app: tauri::AppHandle) -> Result<episode::EpisodeInfo> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    episode::info(&config, &cancel, &show, &title).await
 }
 
-// ─── Lyrics analysis ──────────────────────────────────────────────────────────
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_classical_info(
+    composer: String,
+    work: String,
+    movement: Option<String>,
+    performer: String,
+    album: String,
+    app: tauri::AppHandle,
+) -> Result<classical::ClassicalInfo> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    classical::info(&config, &cancel, &composer, &work, movement.as_deref(), &performer, &album).await
+}
 
-#[derive(serde::Serialize)]
-struct LyricsAnalysis {
-    interpretation: String,
+// ─── Song ─────────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+#[tracing::instrument]
+async fn get_song_links(title: String, artist: String) -> Result<song::SongLinks> {
+    song::links(&title, &artist).await
 }
 
 #[tauri::command]
-fn get_lyrics_analysis(
+#[tracing::instrument]
+async fn get_track_url(title: String, artist: String) -> Result<String> {
+    song::track_url(&title, &artist).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_song_credits(title: String, artist: String, app: tauri::AppHandle) -> Result<song::SongCredits> {
+    let config = app.state::<SharedConfig>().get();
+    song::credits(&config, &title, &artist).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_song_connections(
     title: String,
     artist: String,
-    state: tauri::State<'_, Config>,
-) -> Option<LyricsAnalysis> {
-    if !state.has_keys() {
-        return None;
-    }
+    app: tauri::AppHandle,
+) -> Result<Vec<song::SongConnection>> {
+    let config = app.state::<SharedConfig>().get();
+    song::connections(&config, &title, &artist).await
+}
 
-    let genius_token = &state.api.genius_token;
-    let query        = url_encode(&format!("{} {}", artist, title));
-    let search_url   = format!("https://api.genius.com/search?q={}", query);
+// ─── Browser ──────────────────────────────────────────────────────────────────
 
-    let search = genius_get(&search_url, genius_token)?;
+/// Opens a web link in the default browser.
+#[tauri::command]
+async fn open_url(url: String) -> Result<()> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(Error::Script(format!("lien refusé : {url}")));
+    }
+    open_in_browser(&url).await
+}
 
-    let song_url = search["response"]["hits"][0]["result"]["url"]
-        .as_str()
-        .or_else(|| { eprintln!("[lyrics] no hits for «{title}» by {artist}"); None })?;
+/// Hands `url` to the default browser, or whichever app handles its scheme.
+pub(crate) async fn open_in_browser(url: &str) -> Result<()> {
+    let status = tokio::process::Command::new("open")
+        .arg(url)
+        .status()
+        .await
+        .map_err(|e| Error::Script(e.to_string()))?;
+    if !status.success() {
+        return Err(Error::Script(format!("open a échoué ({status})")));
+    }
+    Ok(())
+}
 
-    let lyrics = fetch_genius_lyrics(song_url);
-    let prompt  = build_lyrics_prompt(&title, &artist, lyrics.as_deref());
+// ─── Lyrics ───────────────────────────────────────────────────────────────────
 
-    let response  = call_claude(&state.api.anthropic_key, 450, &prompt)?;
-    let extracted = extract_claude_json(&response, "lyrics")?;
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_lyrics(title: String, artist: String, album: String, app: tauri::AppHandle) -> Result<lyrics::LyricsText> {
+    let config = app.state::<SharedConfig>().get();
+    lyrics::text(&config, &title, &artist, &album).await
+}
 
-    Some(LyricsAnalysis {
-        interpretation: extracted["interpretation"].as_str().unwrap_or("").to_string(),
-    })
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn export_lrc(
+    title: String,
+    artist: String,
+    album: String,
+    path: Option<String>,
+    whole_album: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<lyrics::LrcExport> {
+    let config = app.state::<SharedConfig>().get();
+    lyrics::export_lrc(&config, &title, &artist, &album, path.as_deref(), whole_album.unwrap_or(false)).await
 }
 
-fn build_lyrics_prompt(title: &str, artist: &str, lyrics: Option<&str>) -> String {
-    let intro = format!(
-        "Tu es un expert en musique et en analyse de textes. \
-         Pour le morceau \"{title}\" de {artist}"
-    );
+/// Payload of `analysis-chunk`: the text decoded so far (not just the last
+/// delta), tagged with the track it belongs to so stale streams can be dropped.
+#[derive(serde::Serialize, Clone)]
+struct AnalysisChunk<'a> {
+    feature: &'static str,
+    title: &'a str,
+    artist: &'a str,
+    text: &'a str,
+}
 
-    let body = match lyrics {
-        Some(lyr) => format!(
-            "{intro}, voici les paroles :\n\n{lyr}\n\n\
-             Basé sur ces paroles, explique en 3-4 phrases en français"
-        ),
-        None => format!(
-            "{intro}, explique en 3-4 phrases en français \
-             (en te basant sur tes connaissances)"
-        ),
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_lyrics_analysis(
+    title: String,
+    artist: String,
+    album: String,
+    app: tauri::AppHandle,
+) -> Result<lyrics::LyricsAnalysis> {
+    let config      = app.state::<SharedConfig>().get();
+    let cancel      = app.state::<TrackScope>().token();
+    let mut on_text = |text: &str| {
+        let _ = app.emit("analysis-chunk", AnalysisChunk {
+            feature: "lyrics",
+            title:   &title,
+            artist:  &artist,
+            text,
+        });
     };
+    lyrics::analysis(&config, &cancel, &title, &artist, &album, Some(&mut on_text)).await
+}
+
+/// How the track sounds, from Spotify's audio features: energy, mood,
+/// tempo, key…
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_audio_features(title: String, artist: String, app: tauri::AppHandle) -> Result<spotify::AudioFeatures> {
+    let config = app.state::<SharedConfig>().get();
+    spotify::audio_features(&config, &title, &artist).await
+}
 
-    format!(
-        "{body} : le thème principal, l'émotion portée, et ce que l'artiste \
-         cherche à exprimer. Sois précis et va au-delà du simple résumé.\n\n\
-         Réponds UNIQUEMENT avec ce JSON (sans markdown) : \
-         {{\"interpretation\": \"...\"}}"
-    )
-}
-
-// ─── Lyrics scraping ──────────────────────────────────────────────────────────
-
-fn fetch_genius_lyrics(url: &str) -> Option<String> {
-    let html = http()
-        .get(url)
-        .set(
-            "User-Agent",
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) \
-             AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-        )
-        .call()
-        .ok()?
-        .into_string()
-        .ok()?;
-
-    let lyrics = extract_lyrics_from_html(&html);
-    if lyrics.trim().is_empty() {
-        None
-    } else {
-        Some(lyrics.chars().take(3000).collect())
+/// Tempo and key worked out from the audio file itself: `path`, else the
+/// current track's when it's a local file. For what no online service knows.
+#[tauri::command]
+#[tracing::instrument]
+async fn analyze_audio_features(path: Option<String>) -> Result<audio_analysis::AudioAnalysis> {
+    match path {
+        Some(path) => audio_analysis::analyze_file(&path).await,
+        None => audio_analysis::analyze_current().await,
     }
 }
 
-/// Extracts plain-text lyrics from `data-lyrics-container="true"` divs.
-fn extract_lyrics_from_html(html: &str) -> String {
-    let mut result = String::new();
-    let mut pos    = 0;
+/// Tracks whose lyrics analysis tagged them `mood`, most played first.
+#[tauri::command]
+fn get_tracks_by_mood(mood: String) -> Vec<moods::MoodTrack> {
+    moods::tracks(&mood)
+}
 
-    while let Some(rel) = html[pos..].find("data-lyrics-container=\"true\"") {
-        let tag_start = pos + rel;
+/// A playlist for `prompt`, picked from the library tracks listened to and
+/// created in Music. A fresh token: the track changing doesn't make the
+/// request moot.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn generate_playlist(prompt: String, app: tauri::AppHandle) -> Result<playlist::GeneratedPlaylist> {
+    let config = app.state::<SharedConfig>().get();
+    playlist::generate(&config, &cancel::CancelToken::default(), &prompt).await
+}
 
-        let Some(open_end) = html[tag_start..].find('>') else { break };
-        let content_start = tag_start + open_end + 1;
+/// Three library picks to play after `title` by `artist` (or after the
+/// last play when nothing is playing).
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_next_suggestion(
+    title: Option<String>,
+    artist: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Vec<suggestion::Suggestion>> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    suggestion::next(&config, &cancel, title.as_deref(), artist.as_deref()).await
+}
 
-        let mut depth = 1usize;
-        let mut scan  = content_start;
+#[tauri::command]
+fn add_note(subject: notes::Subject, title: String, artist: String, text: String) -> Result<notes::Note> {
+    notes::add(subject, &title, &artist, &text)
+}
 
-        while depth > 0 && scan < html.len() {
-            if html[scan..].starts_with("<div") {
-                depth += 1;
-                scan  += 4;
-            } else if html[scan..].starts_with("</div>") {
-                depth -= 1;
-                if depth == 0 { break; }
-                scan += 6;
-            } else {
-                scan += html[scan..].chars().next().map_or(1, |c| c.len_utf8());
-            }
-        }
+/// All notes, or those of a subject kind or of one track or album.
+#[tauri::command]
+fn get_notes(subject: Option<notes::Subject>, title: Option<String>, artist: Option<String>) -> Vec<notes::Note> {
+    notes::get(subject, title.as_deref(), artist.as_deref())
+}
 
-        let section = html_to_text(&html[content_start..scan]);
-        if !result.is_empty() { result.push('\n'); }
-        result.push_str(section.trim());
-        pos = scan;
-    }
+#[tauri::command]
+fn delete_note(id: u64) -> Result<()> {
+    notes::delete(id)
+}
+
+/// Keeps an analysis as shown (`content`), to find it again once the track
+/// has changed.
+#[tauri::command]
+fn star_analysis(
+    kind: bookmarks::Kind,
+    title: String,
+    artist: String,
+    album: String,
+    content: serde_json::Value,
+) -> Result<bookmarks::Bookmark> {
+    bookmarks::star(kind, &title, &artist, &album, content)
+}
+
+#[tauri::command]
+fn unstar_analysis(id: u64) -> Result<()> {
+    bookmarks::unstar(id)
+}
 
-    result
+/// Starred analyses, newest first.
+#[tauri::command]
+fn get_starred(kind: Option<bookmarks::Kind>) -> Vec<bookmarks::Bookmark> {
+    bookmarks::list(kind)
 }
 
-/// Strips HTML tags, converts `<br>` to newlines, and decodes common entities.
-fn html_to_text(fragment: &str) -> String {
-    const ENTITIES: &[(&str, char)] = &[
-        ("&amp;",  '&'), ("&lt;",   '<'), ("&gt;",   '>'),
-        ("&quot;", '"'), ("&apos;", '\''),("&#x27;", '\''),
-        ("&#39;",  '\''),
-    ];
+/// Writes an analysis as shown (`content`) as a Markdown note with
+/// frontmatter, into `path` or the `[vault]` folder. Returns the file's path.
+#[tauri::command]
+fn export_analysis(
+    kind: bookmarks::Kind,
+    title: String,
+    artist: String,
+    album: String,
+    content: serde_json::Value,
+    path: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String> {
+    let config = app.state::<SharedConfig>().get();
+    vault::export(&config, kind, &title, &artist, &album, content, path.as_deref()).map(|p| p.display().to_string())
+}
 
-    let mut out = String::with_capacity(fragment.len());
-    let mut pos = 0;
+/// Lyrics analyses, album info and notes that talk about `query`.
+#[tauri::command]
+fn search_insights(query: String) -> Vec<insights::Hit> {
+    insights::search(&query)
+}
 
-    while pos < fragment.len() {
-        let rest = &fragment[pos..];
+/// The taste profile of the month; `refresh` makes a new one right away.
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_taste_profile(refresh: Option<bool>, app: tauri::AppHandle) -> Result<taste::TasteProfile> {
+    let config = app.state::<SharedConfig>().get();
+    taste::profile(&config, &cancel::CancelToken::default(), refresh.unwrap_or(false)).await
+}
 
-        if rest.starts_with('<') {
-            if let Some(end) = rest.find('>') {
-                if rest[1..end].trim_start().to_lowercase().starts_with("br") {
-                    out.push('\n');
+#[tauri::command]
+#[tracing::instrument(skip(app))]
+async fn get_lyrics_translation(
+    title: String,
+    artist: String,
+    album: String,
+    target_lang: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<lyrics::LyricsTranslation> {
+    let config = app.state::<SharedConfig>().get();
+    let cancel = app.state::<TrackScope>().token();
+    lyrics::translation(&config, &cancel, &title, &artist, &album, target_lang.as_deref()).await
+}
+
+// ─── Usage ────────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+fn get_usage_stats(app: tauri::AppHandle) -> Result<usage::UsageStats> {
+    Ok(usage::stats(&app.state::<SharedConfig>().get()))
+}
+
+// ─── API key validation ───────────────────────────────────────────────────────
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum KeyStatus {
+    Missing,
+    Valid,
+    Invalid,
+    NetworkError,
+}
+
+#[derive(serde::Serialize)]
+struct KeyValidation {
+    genius: KeyStatus,
+    anthropic: KeyStatus,
+}
+
+/// Maps a probe request outcome to a key status: 401/403 mean the key was
+/// rejected, any other HTTP status proves the key got through.
+fn probe_status(result: reqwest::Result<reqwest::Response>, ctx: &str) -> KeyStatus {
+    match result {
+        Ok(resp) => match resp.status().as_u16() {
+            401 | 403 => KeyStatus::Invalid,
+            code => {
+                if !resp.status().is_success() {
+                    warn!(service = ctx, "unexpected status {code} while validating key");
                 }
-                pos += end + 1;
-            } else {
-                pos += 1;
+                KeyStatus::Valid
             }
-        } else if rest.starts_with('&') {
-            match ENTITIES.iter().find(|&&(e, _)| rest.starts_with(e)) {
-                Some(&(entity, ch)) => { out.push(ch); pos += entity.len(); }
-                None                => { out.push('&'); pos += 1; }
-            }
-        } else {
-            let c = rest.chars().next().unwrap();
-            out.push(c);
-            pos += c.len_utf8();
+        },
+        Err(e) => {
+            warn!(service = ctx, "network error while validating key: {e}");
+            KeyStatus::NetworkError
+        }
+    }
+}
+
+/// Checks keys with cheap, token-free calls. Keys passed explicitly (e.g. from
+/// the onboarding form) take precedence over the ones in the config file.
+#[tauri::command]
+async fn validate_api_keys(
+    genius_token: Option<String>,
+    anthropic_key: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<KeyValidation> {
+    if net::is_offline() {
+        return Err(Error::Offline);
+    }
+    let config        = app.state::<SharedConfig>().get();
+    let genius_token  = genius_token.unwrap_or_else(|| config.api.genius_token.clone());
+    let anthropic_key = anthropic_key.unwrap_or_else(|| config.api.anthropic_key.clone());
+
+    let genius = async {
+        if genius_token.is_empty() {
+            return KeyStatus::Missing;
+        }
+        let result = http()
+            .get("https://api.genius.com/search?q=test")
+            .bearer_auth(&genius_token)
+            .send()
+            .await;
+        probe_status(result, "genius")
+    };
+
+    let anthropic = async {
+        if anthropic_key.is_empty() {
+            return KeyStatus::Missing;
         }
+        let result = http()
+            .get("https://api.anthropic.com/v1/models?limit=1")
+            .header("x-api-key", &anthropic_key)
+            .header("anthropic-version", "2023-06-01")
+            .send()
+            .await;
+        probe_status(result, "anthropic")
+    };
+
+    let (genius, anthropic) = tokio::join!(genius, anthropic);
+    Ok(KeyValidation { genius, anthropic })
+}
+
+// ─── Offline mode ─────────────────────────────────────────────────────────────
+
+/// Tray checkbox, kept so a config reload can update its tick.
+struct OfflineItem(CheckMenuItem<tauri::Wry>);
+
+/// Switches offline mode on or off and tells the UI, which refetches when
+/// going back online.
+pub(crate) fn set_offline(app: &tauri::AppHandle, offline: bool) {
+    if net::is_offline() == offline {
+        return;
+    }
+    net::set_offline(offline);
+    info!(offline, "offline mode changed");
+    if let Some(item) = app.try_state::<OfflineItem>() {
+        let _ = item.0.set_checked(offline);
     }
+    let _ = app.emit("offline-changed", offline);
+}
 
-    out
+#[tauri::command]
+fn is_offline() -> Result<bool> {
+    Ok(net::is_offline())
 }
 
 // ─── Window positioning ───────────────────────────────────────────────────────
@@ -477,26 +677,128 @@ fn as_physical_size(size: tauri::Size) -> (f64, f64) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let config = Config::load();
+    logging::init();
+    let (config, config_error) = config::initial();
+    net::configure(&config.http);
+    net::set_offline(config.offline);
+    let headless = config.headless || std::env::args().any(|a| a == "--headless");
+    let config   = SharedConfig::new(config);
 
     tauri::Builder::default()
         .manage(config)
+        .manage(config_error)
+        .manage(TrackScope::default())
+        .manage(ActiveSource::default())
+        .manage(karaoke::KaraokeSession::default())
         .invoke_handler(tauri::generate_handler![
             get_current_track,
+            set_active_source,
+            copy_now_playing,
+            music::get_loved,
+            music::set_loved,
+            music::set_disliked,
+            music::get_rating,
+            music::set_rating,
+            music::get_track_stats,
+            music::get_track_file_info,
+            music::play_album,
+            music::play_playlist,
+            music::get_up_next,
+            music::play_next,
+            music::add_to_up_next,
+            music::search_library,
+            music::play_track,
+            music::get_recently_played,
+            music::get_shuffle,
+            music::set_shuffle,
+            music::get_repeat,
+            music::set_repeat,
             get_artwork,
+            get_artwork_palette,
+            share_card::render_share_card,
+            audio::list_output_devices,
+            audio::set_output_device,
             get_album_info,
+            get_album_deep_dive,
+            analyze_album,
+            get_artist_info,
+            get_episode_info,
+            get_classical_info,
+            get_similar_artists,
+            get_lyrics,
             get_lyrics_analysis,
+            get_audio_features,
+            analyze_audio_features,
+            get_tracks_by_mood,
+            generate_playlist,
+            get_next_suggestion,
+            get_taste_profile,
+            add_note,
+            get_notes,
+            delete_note,
+            star_analysis,
+            unstar_analysis,
+            get_starred,
+            export_analysis,
+            search_insights,
+            get_lyrics_translation,
+            export_lrc,
+            karaoke::start_karaoke,
+            karaoke::stop_karaoke,
+            get_song_credits,
+            get_song_connections,
+            get_release_details,
+            get_song_links,
+            get_track_url,
+            get_upcoming_shows,
+            get_recent_setlists,
+            open_url,
+            validate_api_keys,
+            onboarding::get_onboarding_status,
+            onboarding::save_onboarding,
+            onboarding::request_automation,
+            genius_auth::genius_sign_in,
+            genius_auth::genius_sign_out,
+            genius_auth::genius_signed_in,
+            get_usage_stats,
+            digest::get_digest,
+            digest::generate_wrapped,
+            digest::export_wrapped,
+            digest::export_history,
+            digest::import_lastfm_history,
+            logging::get_recent_logs,
+            diagnostics::get_diagnostics,
+            is_offline,
+            config::get_config_error,
+            config::export_settings,
+            config::import_settings,
+            alarm::list_alarms,
+            alarm::add_alarm,
+            alarm::remove_alarm,
+            alarm::set_alarm_enabled,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             #[cfg(target_os = "macos")]
             app.set_activation_policy(tauri::ActivationPolicy::Accessory);
 
+            config::watch(app.handle());
+            alarm::start(app.handle());
+            digest::start(app.handle());
+
+            if headless {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.destroy()?;
+                }
+                headless::start(app.handle());
+                info!("running headless");
+            }
+
             if let Some(window) = app.get_webview_window("main") {
                 #[cfg(target_os = "macos")]
                 {
                     use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
                     if let Err(e) = apply_vibrancy(&window, NSVisualEffectMaterial::Popover, None, Some(22.0)) {
-                        eprintln!("Vibrancy unavailable: {e}");
+                        warn!("vibrancy unavailable: {e}");
                     }
                 }
 
@@ -517,19 +819,38 @@ pub fn run() {
                 tauri::image::Image::new_owned(img.into_raw(), w, h)
             };
 
-            let quit = MenuItem::with_id(app, "quit", "Quit Enhanced Music", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&quit])?;
+            let copy    = MenuItem::with_id(app, "copy", "Copy Now Playing", true, None::<&str>)?;
+            let offline = CheckMenuItem::with_id(app, "offline", "Offline Mode", true, net::is_offline(), None::<&str>)?;
+            let output  = Submenu::with_id(app, "output", "Audio Output", true)?;
+            let quit    = MenuItem::with_id(app, "quit", "Quit Enhanced Music", true, None::<&str>)?;
+            let menu    = Menu::with_items(app, &[&copy, &output, &offline, &quit])?;
+            app.manage(OfflineItem(offline));
+            app.manage(audio::OutputMenu(output));
+            audio::reload_menu(app.handle());
 
             TrayIconBuilder::new()
                 .icon(icon)
                 .menu(&menu)
                 .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| {
-                    if event.id() == "quit" {
-                        app.exit(0);
+                .on_menu_event(|app, event| match event.id().as_ref() {
+                    "quit" => app.exit(0),
+                    "copy" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = copy_now_playing(None, app).await {
+                                warn!("copy now playing failed: {e}");
+                            }
+                        });
                     }
+                    // The item has already toggled its own tick
+                    "offline" => set_offline(app, !net::is_offline()),
+                    id if id.starts_with(audio::MENU_PREFIX) => audio::on_menu_event(app, id),
+                    _ => {}
                 })
                 .on_tray_icon_event(|tray, event| {
+                    if let TrayIconEvent::Enter { .. } = event {
+                        audio::reload_menu(tray.app_handle());
+                    }
                     if let TrayIconEvent::Click {
                         button: MouseButton::Left,
                         button_state: MouseButtonState::Up,
@@ -559,6 +880,12 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            // Headless, closing the window leaves none: only the tray quits
+            if let tauri::RunEvent::ExitRequested { code: None, api, .. } = event {
+                api.prevent_exit();
+            }
+        });
 }
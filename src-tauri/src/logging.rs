@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::error::{Error, Result};
+
+const FILE_PREFIX: &str = "enhanced-music";
+const KEPT_FILES: usize = 7;
+
+/// `~/Library/Logs/enhanced-music`, where Console.app looks for user logs.
+pub fn log_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home).join("Library").join("Logs").join("enhanced-music")
+}
+
+/// Installs the global subscriber: human-readable output on stderr plus a
+/// daily-rotated file keeping the last week. `RUST_LOG` overrides the default
+/// `info` level (e.g. `RUST_LOG=enhanced_music_lib=debug,enhanced_music_core=debug`).
+pub fn init() {
+    static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let stderr = fmt::layer().with_writer(std::io::stderr);
+
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(KEPT_FILES)
+        .build(log_dir());
+
+    let file = match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = GUARD.set(guard);
+            Some(fmt::layer().with_ansi(false).with_writer(writer))
+        }
+        Err(e) => {
+            eprintln!("[logging] file logging unavailable: {e}");
+            None
+        }
+    };
+
+    let _ = tracing_subscriber::registry().with(filter).with(stderr).with(file).try_init();
+}
+
+/// Last `lines` lines (500 by default) across the rotated files, oldest
+/// first — meant to be pasted into a bug report.
+#[tauri::command]
+pub fn get_recent_logs(lines: Option<usize>) -> Result<String> {
+    let wanted = lines.unwrap_or(500);
+    let dir    = log_dir();
+
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(FILE_PREFIX)))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(e) => return Err(Error::Io(e.to_string())),
+    };
+    // Date-stamped names sort chronologically
+    files.sort();
+
+    let mut tail: Vec<String> = Vec::new();
+    for path in files.iter().rev() {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        let mut chunk: Vec<String> = content.lines().map(str::to_string).collect();
+        let keep = wanted.saturating_sub(tail.len()).min(chunk.len());
+        chunk.drain(..chunk.len() - keep);
+        chunk.append(&mut tail);
+        tail = chunk;
+        if tail.len() >= wanted {
+            break;
+        }
+    }
+    Ok(tail.join("\n"))
+}
@@ -0,0 +1,100 @@
+use enhanced_music_core::music::{self, FileInfo, LibraryTrack, LoveState, RecentTrack, Repeat, TrackStats};
+
+use crate::error::Result;
+
+// Commands over the core's Music controls; see there for what each does.
+
+#[tauri::command]
+pub async fn get_loved() -> Result<LoveState> {
+    music::get_loved().await
+}
+
+#[tauri::command]
+pub async fn set_loved(loved: bool) -> Result<LoveState> {
+    music::set_loved(loved).await
+}
+
+#[tauri::command]
+pub async fn set_disliked(disliked: bool) -> Result<LoveState> {
+    music::set_disliked(disliked).await
+}
+
+#[tauri::command]
+pub async fn get_rating() -> Result<u8> {
+    music::get_rating().await
+}
+
+#[tauri::command]
+pub async fn set_rating(rating: u8) -> Result<u8> {
+    music::set_rating(rating).await
+}
+
+#[tauri::command]
+pub async fn get_track_stats() -> Result<TrackStats> {
+    music::get_track_stats().await
+}
+
+#[tauri::command]
+pub async fn get_track_file_info() -> Result<FileInfo> {
+    music::get_track_file_info().await
+}
+
+#[tauri::command]
+pub async fn play_album(artist: String, album: String) -> Result<()> {
+    music::play_album(&artist, &album).await
+}
+
+#[tauri::command]
+pub async fn play_playlist(name: String) -> Result<()> {
+    music::play_playlist(&name).await
+}
+
+#[tauri::command]
+pub async fn get_up_next() -> Result<Vec<LibraryTrack>> {
+    music::get_up_next().await
+}
+
+#[tauri::command]
+pub async fn play_next(id: String) -> Result<Vec<LibraryTrack>> {
+    music::play_next(&id).await
+}
+
+#[tauri::command]
+pub async fn add_to_up_next(id: String) -> Result<Vec<LibraryTrack>> {
+    music::add_to_up_next(&id).await
+}
+
+#[tauri::command]
+pub async fn search_library(query: String) -> Result<Vec<LibraryTrack>> {
+    music::search_library(&query).await
+}
+
+#[tauri::command]
+pub async fn play_track(id: String) -> Result<()> {
+    music::play_track(&id).await
+}
+
+#[tauri::command]
+pub async fn get_recently_played(limit: Option<usize>) -> Result<Vec<RecentTrack>> {
+    music::get_recently_played(limit).await
+}
+
+#[tauri::command]
+pub async fn get_shuffle() -> Result<bool> {
+    music::get_shuffle().await
+}
+
+#[tauri::command]
+pub async fn set_shuffle(enabled: bool) -> Result<bool> {
+    music::set_shuffle(enabled).await
+}
+
+#[tauri::command]
+pub async fn get_repeat() -> Result<Repeat> {
+    music::get_repeat().await
+}
+
+#[tauri::command]
+pub async fn set_repeat(mode: Repeat) -> Result<Repeat> {
+    music::set_repeat(mode).await
+}
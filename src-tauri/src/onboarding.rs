@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use enhanced_music_core::music::{self, Automation};
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::config::{self, Config, LlmProviderKind, SharedConfig};
+use crate::error::{Error, Result};
+
+/// A key the setup wizard asks for, and where to get one.
+#[derive(Serialize)]
+struct KeyGuide {
+    /// Its name under `[api]`, as `save_onboarding` takes it.
+    key: &'static str,
+    label: &'static str,
+    url: &'static str,
+    required: bool,
+    present: bool,
+}
+
+#[derive(Serialize)]
+pub struct OnboardingStatus {
+    config_path: String,
+    config_exists: bool,
+    /// No Genius token or no key for the chosen LLM: lookups would come back
+    /// empty, so the wizard should show.
+    needs_setup: bool,
+    keys: Vec<KeyGuide>,
+    automation: Automation,
+}
+
+/// Privacy & Security → Automation in System Settings.
+const AUTOMATION_PANE: &str = "x-apple.systempreferences:com.apple.preference.security?Privacy_Automation";
+
+/// Keys offered by the wizard, in the order it shows them.
+const KEYS: [(&str, &str, &str); 6] = [
+    ("genius_token", "Genius", "https://genius.com/api-clients"),
+    ("anthropic_key", "Anthropic", "https://console.anthropic.com/settings/keys"),
+    ("openai_key", "OpenAI", "https://platform.openai.com/api-keys"),
+    ("lastfm_key", "Last.fm", "https://www.last.fm/api/account/create"),
+    ("discogs_token", "Discogs", "https://www.discogs.com/settings/developers"),
+    ("fanart_key", "fanart.tv", "https://fanart.tv/get-an-api-key/"),
+];
+
+fn key_value<'a>(config: &'a Config, key: &str) -> &'a str {
+    match key {
+        "genius_token"  => &config.api.genius_token,
+        "anthropic_key" => &config.api.anthropic_key,
+        "openai_key"    => &config.api.openai_key,
+        "lastfm_key"    => &config.api.lastfm_key,
+        "discogs_token" => &config.api.discogs_token,
+        "fanart_key"    => &config.api.fanart_key,
+        _ => "",
+    }
+}
+
+fn required(config: &Config, key: &str) -> bool {
+    match key {
+        "anthropic_key" => config.llm.provider == LlmProviderKind::Anthropic,
+        "openai_key"    => config.llm.provider == LlmProviderKind::OpenAi,
+        _ => false,
+    }
+}
+
+/// What the setup wizard needs to know on start. Checking Automation may
+/// show macOS's permission prompt, and waits for it.
+#[tauri::command]
+pub async fn get_onboarding_status(app: tauri::AppHandle) -> Result<OnboardingStatus> {
+    let config = app.state::<SharedConfig>().get();
+    let path   = config::config_path();
+    let keys   = KEYS
+        .iter()
+        .map(|&(key, label, url)| KeyGuide {
+            key,
+            label,
+            url,
+            required: required(&config, key),
+            present:  !key_value(&config, key).is_empty(),
+        })
+        .collect();
+    Ok(OnboardingStatus {
+        config_path: path.display().to_string(),
+        config_exists: path.exists(),
+        needs_setup: !config.has_keys(),
+        keys,
+        automation: music::automation(false).await?,
+    })
+}
+
+/// Writes the wizard's answers to `config.toml` — creating it if need be —
+/// and reloads. Empty values are skipped, so a key already set stays.
+#[tauri::command]
+pub async fn save_onboarding(
+    app: tauri::AppHandle,
+    keys: HashMap<String, String>,
+    provider: Option<LlmProviderKind>,
+) -> Result<()> {
+    if let Some(unknown) = keys.keys().find(|k| !KEYS.iter().any(|(key, ..)| key == k)) {
+        return Err(Error::Invalid(format!("clé inconnue : {unknown}")));
+    }
+    config::edit(&app, |doc| {
+        for (key, value) in keys.iter().filter(|(_, v)| !v.trim().is_empty()) {
+            doc["api"][key.as_str()] = toml_edit::value(value.trim());
+        }
+        if let Some(provider) = provider {
+            let name = match provider {
+                LlmProviderKind::Anthropic => "anthropic",
+                LlmProviderKind::OpenAi    => "openai",
+            };
+            doc["llm"]["provider"] = toml_edit::value(name);
+        }
+    })
+}
+
+/// Asks for the Automation permission, opening Music if need be: the first
+/// time macOS prompts; once refused it no longer does, so System Settings
+/// opens on the pane where it can be turned on.
+#[tauri::command]
+pub async fn request_automation() -> Result<Automation> {
+    let automation = music::automation(true).await?;
+    if automation == Automation::Denied {
+        crate::open_in_browser(AUTOMATION_PANE).await?;
+    }
+    Ok(automation)
+}
@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::Manager;
+use tracing::info;
+
+use crate::artwork;
+use crate::config::SharedConfig;
+use crate::error::{Error, Result};
+
+/// Longest interpretation excerpt printed on the card, in characters.
+const SNIPPET_CHARS: usize = 220;
+
+/// What goes on the card, laid out by [`draw`].
+struct Card {
+    title: String,
+    artist: String,
+    album: String,
+    snippet: Option<String>,
+    /// Background, derived from the artwork.
+    background: [u8; 3],
+}
+
+#[derive(Serialize)]
+pub struct ShareCard {
+    path: String,
+    /// Whether the PNG was also put on the clipboard.
+    copied: bool,
+}
+
+/// `~/Pictures/Enhanced Music`, where cards are saved unless a path is given.
+fn default_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home).join("Pictures").join("Enhanced Music")
+}
+
+fn file_name(title: &str, artist: &str) -> String {
+    let name: String = format!("{artist} - {title}")
+        .chars()
+        .map(|c| if matches!(c, '/' | ':' | '\\' | '\0') { '_' } else { c })
+        .collect();
+    format!("{}.png", name.trim_start_matches('.'))
+}
+
+/// Cuts at a word boundary and adds an ellipsis when the text is too long.
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SNIPPET_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(SNIPPET_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end_matches([',', ';', ':', '.']))
+}
+
+/// Dims the artwork's dominant colour so white text stays readable on it.
+fn darken([r, g, b]: [u8; 3]) -> [u8; 3] {
+    [r, g, b].map(|c| (c as f64 * 0.45) as u8)
+}
+
+/// Composes artwork, track and an excerpt of the interpretation into a PNG
+/// for social media, saves it (to `path`, or `~/Pictures/Enhanced Music`) and
+/// copies it to the clipboard unless `copy` is `false`.
+#[tauri::command]
+#[tracing::instrument(skip(app, snippet))]
+pub async fn render_share_card(
+    title: String,
+    artist: String,
+    album: String,
+    snippet: Option<String>,
+    path: Option<String>,
+    copy: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<ShareCard> {
+    let config = app.state::<SharedConfig>().get();
+    let name   = file_name(&title, &artist);
+    let bytes  = artwork::fetch(&config, &title, &artist, &album).await?;
+    let thumb  = artwork::thumbnail(&bytes, artwork::PALETTE_SIZE).await?;
+    let card   = Card {
+        background: darken(artwork::palette(&thumb).dominant_rgb),
+        snippet:    snippet.as_deref().map(excerpt).filter(|s| !s.is_empty()),
+        title,
+        artist,
+        album,
+    };
+
+    let copy = copy.unwrap_or(true);
+    let png  = tokio::task::spawn_blocking(move || draw(&card, &bytes, copy))
+        .await
+        .map_err(|e| Error::Io(e.to_string()))??;
+
+    let path = match path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let dir = default_dir();
+            std::fs::create_dir_all(&dir).map_err(|e| Error::Io(e.to_string()))?;
+            dir.join(name)
+        }
+    };
+    std::fs::write(&path, &png).map_err(|e| Error::Io(e.to_string()))?;
+    info!(path = %path.display(), copied = copy, "share card saved");
+    Ok(ShareCard { path: path.display().to_string(), copied: copy })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn draw(_card: &Card, _artwork: &[u8], _copy: bool) -> Result<Vec<u8>> {
+    Err(Error::Io("carte de partage disponible sur macOS uniquement".into()))
+}
+
+/// Renders the card with AppKit into an offscreen bitmap and returns it as
+/// PNG; with `copy`, the PNG also goes to the general pasteboard.
+#[cfg(target_os = "macos")]
+fn draw(card: &Card, artwork: &[u8], copy: bool) -> Result<Vec<u8>> {
+    use objc2::rc::autoreleasepool;
+    use objc2::runtime::AnyObject;
+    use objc2::AnyThread;
+    use objc2_app_kit::{
+        NSBezierPath, NSBitmapImageFileType, NSBitmapImageRep, NSColor, NSCompositingOperation,
+        NSDeviceRGBColorSpace, NSFont, NSFontAttributeName, NSForegroundColorAttributeName, NSGraphicsContext,
+        NSImage, NSLineBreakMode, NSMutableParagraphStyle, NSParagraphStyleAttributeName, NSPasteboard,
+        NSPasteboardTypePNG, NSStringDrawing,
+    };
+    use objc2_foundation::{NSData, NSDictionary, NSPoint, NSRect, NSSize, NSString};
+
+    const WIDTH: f64 = 1080.0;
+    const MARGIN: f64 = 120.0;
+    const ART: f64 = WIDTH - 2.0 * MARGIN;
+    let height = if card.snippet.is_some() { 1350.0 } else { 1200.0 };
+    // Layout is written top-down; AppKit's origin is bottom-left
+    let rect = |top: f64, h: f64| NSRect::new(NSPoint::new(MARGIN, height - top - h), NSSize::new(ART, h));
+
+    autoreleasepool(|_| {
+        let failed = || Error::Io("rendu de la carte impossible".into());
+
+        let rep = unsafe {
+            NSBitmapImageRep::initWithBitmapDataPlanes_pixelsWide_pixelsHigh_bitsPerSample_samplesPerPixel_hasAlpha_isPlanar_colorSpaceName_bytesPerRow_bitsPerPixel(
+                NSBitmapImageRep::alloc(),
+                std::ptr::null_mut(),
+                WIDTH as isize,
+                height as isize,
+                8,
+                4,
+                true,
+                false,
+                NSDeviceRGBColorSpace,
+                0,
+                0,
+            )
+        }
+        .ok_or_else(failed)?;
+        let context = NSGraphicsContext::graphicsContextWithBitmapImageRep(&rep).ok_or_else(failed)?;
+        let image   = NSImage::initWithData(NSImage::alloc(), &NSData::with_bytes(artwork)).ok_or_else(failed)?;
+
+        NSGraphicsContext::saveGraphicsState_class();
+        NSGraphicsContext::setCurrentContext(Some(&context));
+
+        let [r, g, b] = card.background.map(|c| c as f64 / 255.0);
+        NSColor::colorWithSRGBRed_green_blue_alpha(r, g, b, 1.0).setFill();
+        NSBezierPath::fillRect(NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(WIDTH, height)));
+
+        context.saveGraphicsState();
+        NSBezierPath::bezierPathWithRoundedRect_xRadius_yRadius(rect(MARGIN, ART), 28.0, 28.0).addClip();
+        image.drawInRect_fromRect_operation_fraction(rect(MARGIN, ART), NSRect::ZERO, NSCompositingOperation::SourceOver, 1.0);
+        context.restoreGraphicsState();
+
+        let text = |s: &str, font: &NSFont, alpha: f64, wrap: bool, frame: NSRect| {
+            let colour = NSColor::colorWithSRGBRed_green_blue_alpha(1.0, 1.0, 1.0, alpha);
+            let style  = NSMutableParagraphStyle::new();
+            style.setLineBreakMode(if wrap { NSLineBreakMode::ByWordWrapping } else { NSLineBreakMode::ByTruncatingTail });
+            style.setLineSpacing(if wrap { 6.0 } else { 0.0 });
+            let keys: [&objc2_foundation::NSAttributedStringKey; 3] =
+                unsafe { [NSFontAttributeName, NSForegroundColorAttributeName, NSParagraphStyleAttributeName] };
+            let values: [&AnyObject; 3] = [font.as_ref(), colour.as_ref(), style.as_ref()];
+            let attributes = NSDictionary::from_slices(&keys, &values);
+            unsafe { NSString::from_str(s).drawInRect_withAttributes(frame, Some(&attributes)) };
+        };
+
+        let below_art = MARGIN + ART;
+        text(&card.title, &NSFont::boldSystemFontOfSize(54.0), 1.0, false, rect(below_art + 34.0, 70.0));
+        let subtitle = if card.album.is_empty() { card.artist.clone() } else { format!("{} — {}", card.artist, card.album) };
+        text(&subtitle, &NSFont::systemFontOfSize(34.0), 0.75, false, rect(below_art + 108.0, 48.0));
+        if let Some(snippet) = &card.snippet {
+            text(&format!("« {snippet} »"), &NSFont::systemFontOfSize(27.0), 0.88, true, rect(below_art + 180.0, 140.0));
+        }
+        text("Enhanced Music", &NSFont::systemFontOfSize(20.0), 0.45, false, rect(height - 52.0, 28.0));
+
+        context.flushGraphics();
+        NSGraphicsContext::restoreGraphicsState_class();
+
+        let png = unsafe { rep.representationUsingType_properties(NSBitmapImageFileType::PNG, &NSDictionary::new()) }
+            .ok_or_else(failed)?;
+        if copy {
+            let pasteboard = NSPasteboard::generalPasteboard();
+            pasteboard.clearContents();
+            pasteboard.setData_forType(Some(&png), unsafe { NSPasteboardTypePNG });
+        }
+        Ok(png.to_vec())
+    })
+}
@@ -0,0 +1,64 @@
+// Decoder for the BlurHash strings `get_artwork` returns (https://blurha.sh),
+// rendered to a small PNG data URL the browser scales up smoothly.
+
+const BASE83 = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+const decode83 = (s: string) => [...s].reduce((v, c) => v * 83 + BASE83.indexOf(c), 0);
+
+const srgbToLinear = (c: number) => {
+  const v = c / 255;
+  return v <= 0.04045 ? v / 12.92 : Math.pow((v + 0.055) / 1.055, 2.4);
+};
+
+const linearToSrgb = (v: number) => {
+  const c = Math.max(0, Math.min(1, v));
+  return Math.round((c <= 0.0031308 ? c * 12.92 : 1.055 * Math.pow(c, 1 / 2.4) - 0.055) * 255);
+};
+
+const signPow = (v: number, exp: number) => Math.sign(v) * Math.pow(Math.abs(v), exp);
+
+export function blurhashToDataUrl(hash: string, size = 32): string | null {
+  if (hash.length < 6) return null;
+  const flag = decode83(hash[0]);
+  const nx   = (flag % 9) + 1;
+  const ny   = Math.floor(flag / 9) + 1;
+  if (hash.length !== 4 + 2 * nx * ny) return null;
+
+  const maxValue = (decode83(hash[1]) + 1) / 166;
+  const colours: number[][] = [];
+  for (let k = 0; k < nx * ny; k++) {
+    if (k === 0) {
+      const dc = decode83(hash.slice(2, 6));
+      colours.push([dc >> 16, (dc >> 8) & 255, dc & 255].map(srgbToLinear));
+    } else {
+      const ac = decode83(hash.slice(4 + k * 2, 6 + k * 2));
+      colours.push([Math.floor(ac / 361), Math.floor(ac / 19) % 19, ac % 19]
+        .map(q => signPow((q - 9) / 9, 2) * maxValue));
+    }
+  }
+
+  const canvas  = document.createElement("canvas");
+  canvas.width  = canvas.height = size;
+  const ctx     = canvas.getContext("2d");
+  if (!ctx) return null;
+  const image   = ctx.createImageData(size, size);
+  for (let y = 0; y < size; y++) {
+    for (let x = 0; x < size; x++) {
+      let r = 0, g = 0, b = 0;
+      for (let j = 0; j < ny; j++) {
+        for (let i = 0; i < nx; i++) {
+          const basis = Math.cos((Math.PI * x * i) / size) * Math.cos((Math.PI * y * j) / size);
+          const c     = colours[i + j * nx];
+          r += c[0] * basis; g += c[1] * basis; b += c[2] * basis;
+        }
+      }
+      const o = 4 * (x + y * size);
+      image.data[o]     = linearToSrgb(r);
+      image.data[o + 1] = linearToSrgb(g);
+      image.data[o + 2] = linearToSrgb(b);
+      image.data[o + 3] = 255;
+    }
+  }
+  ctx.putImageData(image, 0, 0);
+  return canvas.toDataURL();
+}